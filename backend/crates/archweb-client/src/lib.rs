@@ -5,6 +5,36 @@ use models::{SignoffGroup, SignoffSpec};
 
 pub const DEFAULT_BASE_URL: &str = "https://archlinux.org";
 
+/// Web page for a package on archweb, e.g.
+/// `https://archlinux.org/packages/core/x86_64/linux/`.
+pub fn package_url(base_url: &str, repo: &str, arch: &str, name: &str) -> String {
+    format!(
+        "{}/packages/{}/{}/{}/",
+        base_url.trim_end_matches('/'),
+        repo,
+        arch,
+        name
+    )
+}
+
+/// Repositories archweb has package pages for.
+const OFFICIAL_REPOS: &[&str] = &[
+    "core",
+    "extra",
+    "multilib",
+    "core-testing",
+    "extra-testing",
+    "multilib-testing",
+];
+
+/// Like `package_url`, but None for a package from a repository archweb
+/// doesn't know, such as a custom or third-party one.
+pub fn official_package_url(base_url: &str, repo: &str, arch: &str, name: &str) -> Option<String> {
+    OFFICIAL_REPOS
+        .contains(&repo)
+        .then(|| package_url(base_url, repo, arch, name))
+}
+
 #[derive(serde::Deserialize)]
 struct SignoffResponse {
     signoff_groups: Vec<SignoffGroup>,
@@ -163,6 +193,30 @@ impl SignoffSession {
 mod tests {
     use super::*;
 
+    #[test]
+    fn package_url_format() {
+        assert_eq!(
+            package_url(DEFAULT_BASE_URL, "core", "x86_64", "linux"),
+            "https://archlinux.org/packages/core/x86_64/linux/"
+        );
+        assert_eq!(
+            package_url("http://localhost:8000/", "extra", "any", "python-six"),
+            "http://localhost:8000/packages/extra/any/python-six/"
+        );
+    }
+
+    #[test]
+    fn official_package_url_only_for_arch_repos() {
+        assert_eq!(
+            official_package_url(DEFAULT_BASE_URL, "extra-testing", "any", "python-six").as_deref(),
+            Some("https://archlinux.org/packages/extra-testing/any/python-six/")
+        );
+        assert_eq!(
+            official_package_url(DEFAULT_BASE_URL, "chaotic-aur", "x86_64", "paru"),
+            None
+        );
+    }
+
     #[test]
     fn deserialize_signoff_response() {
         let json = r#"{
@@ -0,0 +1,182 @@
+pub mod models;
+
+use std::io::Read;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use models::{AurPackage, RpcResponse};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+
+pub const DEFAULT_BASE_URL: &str = "https://aur.archlinux.org";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RESPONSE_BYTES: u64 = 8 * 1024 * 1024;
+/// Names per `info` request, keeping the query string well under the
+/// server's URI length limit.
+const INFO_BATCH: usize = 100;
+
+/// How `search` matches its keyword, as the RPC `by` parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchBy {
+    Name,
+    NameDesc,
+    Maintainer,
+}
+
+impl SearchBy {
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchBy::Name => "name",
+            SearchBy::NameDesc => "name-desc",
+            SearchBy::Maintainer => "maintainer",
+        }
+    }
+}
+
+pub struct AurClient {
+    agent: ureq::Agent,
+    base_url: String,
+}
+
+impl AurClient {
+    pub fn new(ip_family: ureq::config::IpFamily) -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL, ip_family)
+    }
+
+    pub fn with_base_url(url: &str, ip_family: ureq::config::IpFamily) -> Self {
+        Self::with_timeouts(url, ip_family, DEFAULT_TIMEOUT, DEFAULT_CONNECT_TIMEOUT)
+    }
+
+    pub fn with_timeouts(
+        url: &str,
+        ip_family: ureq::config::IpFamily,
+        timeout: Duration,
+        connect_timeout: Duration,
+    ) -> Self {
+        let config = ureq::Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .timeout_connect(Some(connect_timeout))
+            .ip_family(ip_family)
+            .build();
+        Self {
+            agent: ureq::Agent::new_with_config(config),
+            base_url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Look up packages by exact name. Names the AUR doesn't know are simply
+    /// absent from the result.
+    pub fn info(&self, names: &[String]) -> Result<Vec<AurPackage>> {
+        let mut packages = Vec::new();
+        for batch in names.chunks(INFO_BATCH) {
+            let url = info_url(&self.base_url, batch);
+            packages.extend(self.rpc(&url).context("AUR info request failed")?);
+        }
+        Ok(packages)
+    }
+
+    pub fn search(&self, keyword: &str, by: SearchBy) -> Result<Vec<AurPackage>> {
+        let url = format!(
+            "{}/rpc/v5/search/{}?by={}",
+            self.base_url,
+            utf8_percent_encode(keyword, NON_ALPHANUMERIC),
+            by.as_str()
+        );
+        self.rpc(&url).context("AUR search request failed")
+    }
+
+    fn rpc(&self, url: &str) -> Result<Vec<AurPackage>> {
+        let mut body = self
+            .agent
+            .get(url)
+            .call()
+            .with_context(|| format!("GET {} failed", url))?
+            .into_body();
+        let mut buf = Vec::new();
+        body.as_reader()
+            .take(MAX_RESPONSE_BYTES)
+            .read_to_end(&mut buf)?;
+        parse_response(&buf)
+    }
+}
+
+fn info_url(base_url: &str, names: &[String]) -> String {
+    let args: Vec<String> = names
+        .iter()
+        .map(|n| format!("arg%5B%5D={}", utf8_percent_encode(n, NON_ALPHANUMERIC)))
+        .collect();
+    format!("{}/rpc/v5/info?{}", base_url, args.join("&"))
+}
+
+fn parse_response(body: &[u8]) -> Result<Vec<AurPackage>> {
+    let response: RpcResponse =
+        serde_json::from_slice(body).context("failed to parse AUR RPC response")?;
+    if response.response_type == "error" {
+        bail!(
+            "AUR RPC error: {}",
+            response.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    Ok(response.results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_url_encodes_every_name() {
+        let names = vec!["yay".to_string(), "libc++".to_string()];
+        assert_eq!(
+            info_url("https://aur.archlinux.org", &names),
+            "https://aur.archlinux.org/rpc/v5/info?arg%5B%5D=yay&arg%5B%5D=libc%2B%2B"
+        );
+    }
+
+    #[test]
+    fn parses_info_results() {
+        let body = br#"{
+            "resultcount": 1,
+            "results": [{
+                "ID": 1234,
+                "Name": "yay-bin",
+                "PackageBaseID": 99,
+                "PackageBase": "yay-bin",
+                "Version": "12.4.2-1",
+                "Description": "Yet another yogurt",
+                "URL": "https://github.com/Jguer/yay",
+                "NumVotes": 500,
+                "Popularity": 12.5,
+                "OutOfDate": 1720000000,
+                "Maintainer": null,
+                "FirstSubmitted": 1500000000,
+                "LastModified": 1710000000,
+                "URLPath": "/cgit/aur.git/snapshot/yay-bin.tar.gz",
+                "Depends": ["pacman>6.1", "git"]
+            }],
+            "type": "multiinfo",
+            "version": 5
+        }"#;
+        let pkgs = parse_response(body).unwrap();
+        assert_eq!(pkgs.len(), 1);
+        assert_eq!(pkgs[0].name, "yay-bin");
+        assert_eq!(pkgs[0].version, "12.4.2-1");
+        assert_eq!(pkgs[0].out_of_date, Some(1720000000));
+        assert!(pkgs[0].maintainer.is_none());
+        assert_eq!(pkgs[0].depends, vec!["pacman>6.1", "git"]);
+        assert!(pkgs[0].make_depends.is_empty());
+    }
+
+    #[test]
+    fn rpc_error_is_surfaced() {
+        let body = br#"{"error":"Too many package results.","resultcount":0,"results":[],"type":"error","version":5}"#;
+        let err = parse_response(body).unwrap_err();
+        assert!(err.to_string().contains("Too many package results"));
+    }
+
+    #[test]
+    fn search_by_names() {
+        assert_eq!(SearchBy::NameDesc.as_str(), "name-desc");
+        assert_eq!(SearchBy::Maintainer.as_str(), "maintainer");
+    }
+}
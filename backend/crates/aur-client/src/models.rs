@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// One package as returned by the `info` and `search` RPC types. `search`
+/// omits the dependency arrays, so everything past the core fields defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct AurPackage {
+    #[serde(rename = "ID")]
+    pub id: u64,
+    pub name: String,
+    #[serde(rename = "PackageBaseID")]
+    pub package_base_id: u64,
+    pub package_base: String,
+    pub version: String,
+    pub description: Option<String>,
+    #[serde(rename = "URL")]
+    pub url: Option<String>,
+    pub num_votes: u64,
+    pub popularity: f64,
+    /// Unix time the package was flagged out of date.
+    pub out_of_date: Option<i64>,
+    /// None when the package is orphaned.
+    pub maintainer: Option<String>,
+    pub first_submitted: i64,
+    pub last_modified: i64,
+    #[serde(rename = "URLPath")]
+    pub url_path: Option<String>,
+    #[serde(default)]
+    pub depends: Vec<String>,
+    #[serde(default)]
+    pub make_depends: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RpcResponse {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub results: Vec<AurPackage>,
+}
@@ -1,16 +1,19 @@
 mod callbacks;
+mod tempdb;
 mod transaction;
 
 pub use callbacks::{interrupt_if_cancelled, setup_dl_cb, setup_log_cb};
+pub use tempdb::private_sync_handle;
 pub use transaction::{TransactionGuard, try_interrupt};
 
-use alpm::{Alpm, LogLevel, Progress};
+use alpm::{Alpm, Dep, DepModVer, LogLevel, Progress};
 use alpm_utils::alpm_with_conf;
 use anyhow::{Context, Result};
 use pacman_key::KeyValidity;
 use pacmanconf::Config;
 use std::collections::HashMap;
 
+use crate::config::{AppConfig, PolicyAction};
 use crate::models::UpdateInfo;
 
 pub fn get_handle() -> Result<Alpm> {
@@ -53,15 +56,39 @@ pub fn lookup_file_owner<'a>(index: &'a FileOwnerIndex, path: &str) -> Option<&'
     index.get(relative).map(|s| s.as_str())
 }
 
-/// Find all packages with available updates by comparing local versions to sync databases.
-pub fn find_available_updates(handle: &Alpm, extra_ignored: &[String]) -> Vec<UpdateInfo> {
+/// A dependency as pacman writes it (`name`, `name>=1.0`), without the
+/// optdepends description that alpm's own Display appends.
+pub fn dep_string(dep: &Dep) -> String {
+    match dep.depmodver() {
+        DepModVer::Any => dep.name().to_string(),
+        DepModVer::Eq(v) => format!("{}={}", dep.name(), v),
+        DepModVer::Ge(v) => format!("{}>={}", dep.name(), v),
+        DepModVer::Le(v) => format!("{}<={}", dep.name(), v),
+        DepModVer::Gt(v) => format!("{}>{}", dep.name(), v),
+        DepModVer::Lt(v) => format!("{}<{}", dep.name(), v),
+    }
+}
+
+/// Find all packages with available updates by comparing local versions to
+/// sync databases. Ignores and update policies from `config` are applied: a
+/// held update is flagged as ignored, and holds that have run out no longer
+/// count.
+pub fn find_available_updates(handle: &Alpm, config: &AppConfig) -> Vec<UpdateInfo> {
     let localdb = handle.localdb();
+    let today = chrono::Local::now().date_naive();
     let mut updates = Vec::new();
 
     for pkg in localdb.pkgs() {
         for syncdb in handle.syncdbs() {
             if let Ok(syncpkg) = syncdb.pkg(pkg.name()) {
                 if syncpkg.version() > pkg.version() {
+                    let groups: Vec<&str> = syncpkg.groups().iter().collect();
+                    let policy = config.policy_for(pkg.name(), &groups).filter(|(p, _)| {
+                        p.hold_expiry(Some(syncpkg.version().as_str()), today, |a, b| {
+                            alpm::vercmp(a, b)
+                        })
+                        .is_none()
+                    });
                     let ignored = handle.ignorepkgs().iter().any(|n| n == pkg.name())
                         || handle.ignoregroups().iter().any(|g| {
                             handle.syncdbs().iter().any(|db| {
@@ -72,7 +99,8 @@ pub fn find_available_updates(handle: &Alpm, extra_ignored: &[String]) -> Vec<Up
                                     .unwrap_or(false)
                             })
                         })
-                        || extra_ignored.iter().any(|n| n == pkg.name());
+                        || config.is_ignored(pkg.name())
+                        || policy.is_some_and(|(p, _)| p.action == PolicyAction::Hold);
                     updates.push(UpdateInfo {
                         name: pkg.name().to_string(),
                         current_version: pkg.version().to_string(),
@@ -82,6 +110,8 @@ pub fn find_available_updates(handle: &Alpm, extra_ignored: &[String]) -> Vec<Up
                         new_size: syncpkg.isize(),
                         repository: syncdb.name().to_string(),
                         ignored,
+                        policy: policy.map(|(p, _)| p.action),
+                        policy_note: policy.map(|(p, group)| p.describe(group)),
                     });
                 }
                 break;
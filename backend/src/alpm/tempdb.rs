@@ -0,0 +1,209 @@
+//! A private copy of the sync databases for `checkupdates`-style checks.
+//!
+//! Refreshing /var/lib/pacman/sync without upgrading sets up a partial
+//! upgrade, so a fresh update check instead refreshes a per-user copy under the
+//! temp dir. The copy shares the real local db through a symlink and is seeded
+//! from the system sync dbs so the first refresh is incremental.
+
+use alpm::Alpm;
+use alpm_utils::alpm_with_conf;
+use anyhow::{Context, Result};
+use pacmanconf::Config;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+fn private_db_dir() -> PathBuf {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    std::env::temp_dir().join(format!("cockpit-pacman-checkup-db-{}", uid))
+}
+
+/// Create (or validate) the private db dir. It lives in a shared temp dir at a
+/// predictable path, so refuse to use anything that isn't a real directory
+/// closed to others and owned by us or by `owner`, which a directory we
+/// created is handed to.
+fn ensure_private_dir(dir: &Path, owner: Option<(u32, u32)>) -> Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {}", dir.display())),
+    }
+    let meta =
+        fs::symlink_metadata(dir).with_context(|| format!("Failed to stat {}", dir.display()))?;
+    // SAFETY: as above.
+    let uid = unsafe { libc::geteuid() };
+    let owner_uid = owner.map_or(uid, |(owner_uid, _)| owner_uid);
+    if !meta.is_dir() || ![uid, owner_uid].contains(&meta.uid()) || meta.mode() & 0o077 != 0 {
+        anyhow::bail!(
+            "Refusing to use {}: not a private directory owned by uid {}",
+            dir.display(),
+            owner_uid
+        );
+    }
+    if let Some((owner_uid, owner_gid)) = owner {
+        chown_if_needed(dir, owner_uid, owner_gid)?;
+    }
+    Ok(())
+}
+
+fn chown_if_needed(path: &Path, uid: u32, gid: u32) -> Result<()> {
+    let meta =
+        fs::symlink_metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    if meta.uid() != uid || meta.gid() != gid {
+        std::os::unix::fs::lchown(path, Some(uid), Some(gid))
+            .with_context(|| format!("Failed to chown {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Copy system sync db files that are newer than the private copy. The mtime
+/// is carried over because libalpm sends it as If-Modified-Since; a copy
+/// stamped "now" would never be refreshed.
+fn seed_sync_dbs(system_sync: &Path, private_sync: &Path) -> Result<()> {
+    fs::create_dir_all(private_sync)
+        .with_context(|| format!("Failed to create {}", private_sync.display()))?;
+    let Ok(entries) = fs::read_dir(system_sync) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let is_db = Path::new(&name)
+            .extension()
+            .is_some_and(|ext| ext == "db" || ext == "sig");
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !is_db || !meta.is_file() {
+            continue;
+        }
+        let target = private_sync.join(&name);
+        let stale = match (fs::metadata(&target), meta.modified()) {
+            (Ok(existing), Ok(source_mtime)) => {
+                existing.modified().map_or(true, |m| m < source_mtime)
+            }
+            _ => true,
+        };
+        if !stale {
+            continue;
+        }
+        fs::copy(entry.path(), &target)
+            .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        if let Ok(mtime) = meta.modified() {
+            fs::File::options()
+                .write(true)
+                .open(&target)
+                .and_then(|f| f.set_modified(mtime))
+                .with_context(|| format!("Failed to set mtime on {}", target.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// An alpm handle whose sync dbs live in the private copy, ready for
+/// `syncdbs_mut().update()`. The local db is the real one, read-only in
+/// practice since nothing here starts a transaction.
+pub fn private_sync_handle() -> Result<Alpm> {
+    let mut conf = Config::new().context("Failed to parse pacman.conf")?;
+    let system_db = PathBuf::from(&conf.db_path);
+    let dir = private_db_dir();
+
+    // The sandbox user can only be switched to from root; an unprivileged
+    // check downloads as the calling user.
+    // SAFETY: as above.
+    if unsafe { libc::geteuid() } != 0 {
+        conf.download_user = None;
+    }
+    // As root, the private copy belongs to pacman.conf's DownloadUser, so the
+    // sandboxed download can reach it.
+    let owner = match &conf.download_user {
+        Some(name) => Some(
+            crate::util::lookup_user(name)
+                .with_context(|| format!("DownloadUser '{}' does not exist", name))?,
+        ),
+        None => None,
+    };
+
+    ensure_private_dir(&dir, owner)?;
+    let local_link = dir.join("local");
+    if fs::symlink_metadata(&local_link).is_err() {
+        std::os::unix::fs::symlink(system_db.join("local"), &local_link)
+            .with_context(|| format!("Failed to link {}", local_link.display()))?;
+    }
+    let private_sync = dir.join("sync");
+    seed_sync_dbs(&system_db.join("sync"), &private_sync)?;
+    if let Some((uid, gid)) = owner {
+        chown_if_needed(&private_sync, uid, gid)?;
+    }
+
+    conf.db_path = dir.to_string_lossy().into_owned();
+    conf.log_file = "/dev/null".to_string();
+
+    alpm_with_conf(&conf).context("Failed to initialize alpm handle for private db copy")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn seed_copies_db_files_with_mtime_and_skips_up_to_date() {
+        let root = std::env::temp_dir().join(format!("cpac-seed-{}", std::process::id()));
+        let system = root.join("system");
+        let private = root.join("private");
+        fs::create_dir_all(&system).unwrap();
+        fs::write(system.join("core.db"), b"core").unwrap();
+        fs::write(system.join("core.db.sig"), b"sig").unwrap();
+        fs::write(system.join("core.files"), b"files").unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        fs::File::options()
+            .write(true)
+            .open(system.join("core.db"))
+            .and_then(|f| f.set_modified(old))
+            .unwrap();
+
+        seed_sync_dbs(&system, &private).unwrap();
+        assert_eq!(fs::read(private.join("core.db")).unwrap(), b"core");
+        assert!(private.join("core.db.sig").exists());
+        assert!(!private.join("core.files").exists());
+        let copied = fs::metadata(private.join("core.db"))
+            .and_then(|m| m.modified())
+            .unwrap();
+        assert_eq!(copied, old);
+
+        // A private copy that a refresh made newer is left alone.
+        fs::write(private.join("core.db"), b"refreshed").unwrap();
+        seed_sync_dbs(&system, &private).unwrap();
+        assert_eq!(fs::read(private.join("core.db")).unwrap(), b"refreshed");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn private_dir_must_not_be_shared() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("cpac-privdir-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let dir = root.join("db");
+        ensure_private_dir(&dir, None).unwrap();
+        assert_eq!(
+            fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        // Handed to the download user, here ourselves.
+        let meta = fs::metadata(&dir).unwrap();
+        ensure_private_dir(&dir, Some((meta.uid(), meta.gid()))).unwrap();
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(ensure_private_dir(&dir, None).is_err());
+
+        let link = root.join("link");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+        assert!(ensure_private_dir(&link, None).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -70,6 +70,15 @@ impl<'a> TransactionGuard<'a> {
         self.handle.trans_add_pkg(pkg)
     }
 
+    /// Load a package file for `add_pkg`, as `pacman -U` does.
+    pub fn pkg_load(
+        &self,
+        path: &str,
+        level: alpm::SigLevel,
+    ) -> Result<alpm::LoadedPackage<'_>, alpm::Error> {
+        self.handle.pkg_load(path, true, level)
+    }
+
     pub fn remove_pkg(&self, pkg: &alpm::Package) -> Result<(), alpm::Error> {
         self.handle.trans_remove_pkg(pkg)
     }
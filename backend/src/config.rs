@@ -1,5 +1,6 @@
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::Write;
 use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
@@ -10,10 +11,9 @@ use ts_rs::TS;
 
 const CONFIG_PATH: &str = "/etc/cockpit-pacman/config.json";
 const CONFIG_LOCK_PATH: &str = "/etc/cockpit-pacman/config.json.lock";
-const TIMER_DROP_IN_DIR: &str = "/etc/systemd/system/cockpit-pacman-scheduled.timer.d";
-const TIMER_DROP_IN_PATH: &str =
-    "/etc/systemd/system/cockpit-pacman-scheduled.timer.d/schedule.conf";
+const SYSTEMD_UNIT_DIR: &str = "/etc/systemd/system";
 const SYSTEMCTL_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_STALE_DB_DAYS: u32 = 7;
 
 /// Run `systemctl <args>` bounded by SYSTEMCTL_TIMEOUT so a wedged systemd can't
 /// hang the request.
@@ -38,11 +38,28 @@ fn timer_absent(stderr: &str) -> bool {
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, TS)]
 #[ts(export, export_to = "../../src/bindings/index.ts")]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "kebab-case")]
 pub enum ScheduleMode {
     Check,
     #[default]
     Upgrade,
+    /// Apply only updates fixing an open security advisory, plus the updates
+    /// they must move with; the rest is reported.
+    Security,
+    CacheClean,
+    OrphanReport,
+    MirrorRefresh,
+    KeyringRefresh,
+}
+
+impl ScheduleMode {
+    /// Modes that sync the databases and look at available updates.
+    pub fn checks_updates(self) -> bool {
+        matches!(
+            self,
+            ScheduleMode::Check | ScheduleMode::Upgrade | ScheduleMode::Security
+        )
+    }
 }
 
 impl std::fmt::Display for ScheduleMode {
@@ -50,6 +67,11 @@ impl std::fmt::Display for ScheduleMode {
         match self {
             ScheduleMode::Check => write!(f, "check"),
             ScheduleMode::Upgrade => write!(f, "upgrade"),
+            ScheduleMode::Security => write!(f, "security"),
+            ScheduleMode::CacheClean => write!(f, "cache-clean"),
+            ScheduleMode::OrphanReport => write!(f, "orphan-report"),
+            ScheduleMode::MirrorRefresh => write!(f, "mirror-refresh"),
+            ScheduleMode::KeyringRefresh => write!(f, "keyring-refresh"),
         }
     }
 }
@@ -61,6 +83,11 @@ impl std::str::FromStr for ScheduleMode {
         match s.to_lowercase().as_str() {
             "check" => Ok(ScheduleMode::Check),
             "upgrade" => Ok(ScheduleMode::Upgrade),
+            "security" => Ok(ScheduleMode::Security),
+            "cache-clean" => Ok(ScheduleMode::CacheClean),
+            "orphan-report" => Ok(ScheduleMode::OrphanReport),
+            "mirror-refresh" => Ok(ScheduleMode::MirrorRefresh),
+            "keyring-refresh" => Ok(ScheduleMode::KeyringRefresh),
             _ => Err(anyhow::anyhow!("Invalid schedule mode: {}", s)),
         }
     }
@@ -76,6 +103,14 @@ pub struct ScheduleConfig {
     pub schedule: String,
     #[serde(default)]
     pub max_packages: usize,
+    /// Only upgrade packages whose sync-db build date is at least this many
+    /// days old; 0 turns the policy off.
+    #[serde(default)]
+    pub min_age_days: u32,
+    #[serde(default, skip_serializing_if = "ScheduleHooks::is_empty")]
+    pub hooks: ScheduleHooks,
+    #[serde(default, skip_serializing_if = "PostUpgradePolicy::is_empty")]
+    pub post_upgrade: PostUpgradePolicy,
     // Round-trip keys this binary doesn't know about (e.g. fields added by a
     // newer version) instead of dropping them on the next update() rewrite.
     #[serde(flatten)]
@@ -93,17 +128,452 @@ impl Default for ScheduleConfig {
             mode: ScheduleMode::Upgrade,
             schedule: default_schedule(),
             max_packages: 0,
+            min_age_days: 0,
+            hooks: ScheduleHooks::default(),
+            post_upgrade: PostUpgradePolicy::default(),
             extra: serde_json::Map::new(),
         }
     }
 }
 
+/// Upper bound on a single hook's timeout.
+pub const MAX_HOOK_TIMEOUT_SECS: u64 = 900;
+/// Upper bound on the timeouts of a stage's hooks together. Hooks run outside
+/// the scheduled run's own timeout, and both stages plus the run must stay
+/// well inside the unit's TimeoutStartSec (test-enforced).
+pub const MAX_HOOK_STAGE_SECS: u64 = 1800;
+
+/// A program run around a scheduled run. No shell is involved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct HookCommand {
+    pub argv: Vec<String>,
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_hook_timeout() -> u64 {
+    300
+}
+
+/// Commands run in order before and after a schedule's runs. A failing `pre`
+/// hook skips the run; `post` hooks see its outcome.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ScheduleHooks {
+    #[serde(default)]
+    pub pre: Vec<HookCommand>,
+    #[serde(default)]
+    pub post: Vec<HookCommand>,
+}
+
+impl ScheduleHooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for hook in self.pre.iter().chain(&self.post) {
+            match hook.argv.first() {
+                Some(program) if program.starts_with('/') => {}
+                _ => bail!("A hook needs an absolute program path"),
+            }
+            if hook.timeout_secs == 0 || hook.timeout_secs > MAX_HOOK_TIMEOUT_SECS {
+                bail!(
+                    "Hook timeout must be between 1 and {} seconds",
+                    MAX_HOOK_TIMEOUT_SECS
+                );
+            }
+        }
+        for (stage, hooks) in [("pre", &self.pre), ("post", &self.post)] {
+            let total: u64 = hooks.iter().map(|h| h.timeout_secs).sum();
+            if total > MAX_HOOK_STAGE_SECS {
+                bail!(
+                    "The {} hooks' timeouts add up to {} seconds, over the limit of {}",
+                    stage,
+                    total,
+                    MAX_HOOK_STAGE_SECS
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a scheduled run does once an upgrade is committed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct PostUpgradePolicy {
+    /// Restart services still running replaced binaries, except those
+    /// tagged `restart_blocked`.
+    #[serde(default)]
+    pub restart_services: bool,
+    /// `HH:MM-HH:MM` in local time. When set and the upgrade needs a reboot,
+    /// one is scheduled through logind inside this window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub reboot_window: Option<String>,
+}
+
+impl PostUpgradePolicy {
+    pub fn is_empty(&self) -> bool {
+        !self.restart_services && self.reboot_window.is_none()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(window) = &self.reboot_window {
+            window.parse::<RebootWindow>()?;
+        }
+        Ok(())
+    }
+}
+
+/// A daily span of local time; the end is exclusive and may be past midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RebootWindow {
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+}
+
+impl std::str::FromStr for RebootWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |t: &str| chrono::NaiveTime::parse_from_str(t.trim(), "%H:%M");
+        let window = s
+            .split_once('-')
+            .and_then(|(start, end)| Some((parse(start).ok()?, parse(end).ok()?)))
+            .map(|(start, end)| RebootWindow { start, end });
+        match window {
+            Some(window) if window.start != window.end => Ok(window),
+            _ => bail!("Invalid reboot window '{}', expected HH:MM-HH:MM", s),
+        }
+    }
+}
+
+impl RebootWindow {
+    pub fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// The first moment at or after `earliest` that falls inside the window.
+    pub fn next_from(&self, earliest: chrono::NaiveDateTime) -> chrono::NaiveDateTime {
+        if self.contains(earliest.time()) {
+            return earliest;
+        }
+        let start = earliest.date().and_time(self.start);
+        if start > earliest {
+            start
+        } else {
+            start + chrono::Duration::days(1)
+        }
+    }
+}
+
+/// Package set a machine should converge to. `required` packages must be
+/// installed explicitly and `forbidden` ones absent. With `strict`, explicit
+/// packages listed in neither `required` nor `orphan_keep` are demoted to
+/// dependencies, so orphan cleanup can take them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct DesiredState {
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(flatten)]
+    #[ts(skip)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl DesiredState {
+    pub fn is_empty(&self) -> bool {
+        self.required.is_empty() && self.forbidden.is_empty() && !self.strict
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for name in self.required.iter().chain(&self.forbidden) {
+            crate::validation::validate_package_name(name)
+                .with_context(|| format!("Invalid package name '{}' in desired state", name))?;
+        }
+        if let Some(name) = self.required.iter().find(|n| self.forbidden.contains(n)) {
+            bail!("Package '{}' is both required and forbidden", name);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Always upgrade, exempt from the scheduled minimum-age hold.
+    Auto,
+    /// Report the update but leave it out of scheduled runs.
+    NotifyOnly,
+    /// Keep the installed version everywhere until the hold ends.
+    Hold,
+}
+
+/// How updates to a package or group are handled. A hold ends once
+/// `until_version` (or newer) is available or `until_date` (YYYY-MM-DD) has
+/// arrived, whichever comes first; with neither it lasts until removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct UpdatePolicy {
+    pub action: PolicyAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub until_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub until_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub reason: Option<String>,
+    #[serde(flatten)]
+    #[ts(skip)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl UpdatePolicy {
+    pub fn until_date(&self) -> Option<chrono::NaiveDate> {
+        self.until_date
+            .as_deref()
+            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+    }
+
+    /// `group` policies cannot hold until a version: members are versioned
+    /// independently.
+    pub fn validate(&self, group: bool) -> Result<()> {
+        if self.action != PolicyAction::Hold
+            && (self.until_version.is_some() || self.until_date.is_some())
+        {
+            bail!("until_version and until_date only apply to holds");
+        }
+        if group && self.until_version.is_some() {
+            bail!("Group policies cannot hold until a version");
+        }
+        if let Some(version) = &self.until_version {
+            crate::validation::validate_version(version)?;
+        }
+        if self.until_date.is_some() && self.until_date().is_none() {
+            bail!("until_date must be a date in YYYY-MM-DD form");
+        }
+        if self.reason.as_ref().is_some_and(|r| r.len() > 500) {
+            bail!("Policy reason must be at most 500 characters");
+        }
+        Ok(())
+    }
+
+    /// Why this hold has ended, if it has: the wanted version is available
+    /// or the date has arrived.
+    pub fn hold_expiry(
+        &self,
+        available: Option<&str>,
+        today: chrono::NaiveDate,
+        vercmp: impl Fn(&str, &str) -> std::cmp::Ordering,
+    ) -> Option<String> {
+        if self.action != PolicyAction::Hold {
+            return None;
+        }
+        if let (Some(wanted), Some(version)) = (&self.until_version, available)
+            && vercmp(version, wanted) != std::cmp::Ordering::Less
+        {
+            return Some(format!("version {} is available", version));
+        }
+        self.until_date()
+            .filter(|&date| today >= date)
+            .map(|date| format!("held until {}", date))
+    }
+
+    /// Human-readable summary, e.g. "held until 2025-07-01: waiting for fix".
+    pub fn describe(&self, group: Option<&str>) -> String {
+        let mut text = match self.action {
+            PolicyAction::Auto => "always upgrade".to_string(),
+            PolicyAction::NotifyOnly => "notify only".to_string(),
+            PolicyAction::Hold => "held".to_string(),
+        };
+        if let Some(version) = &self.until_version {
+            text.push_str(&format!(" until version {}", version));
+        }
+        if let Some(date) = &self.until_date {
+            text.push_str(&format!(" until {}", date));
+        }
+        if let Some(group) = group {
+            text.push_str(&format!(" (group {})", group));
+        }
+        if let Some(reason) = &self.reason {
+            text.push_str(&format!(": {}", reason));
+        }
+        text
+    }
+}
+
+/// Occasions a notifier can be told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    RunFailed,
+    RunSkipped,
+    UpdatesAvailable,
+    SecurityAdvisories,
+    RebootRequired,
+    PacnewFiles,
+    /// Sent by `test-notifier`; delivered whatever `events` says.
+    Test,
+}
+
+impl std::fmt::Display for NotifyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            NotifyEvent::RunFailed => "run_failed",
+            NotifyEvent::RunSkipped => "run_skipped",
+            NotifyEvent::UpdatesAvailable => "updates_available",
+            NotifyEvent::SecurityAdvisories => "security_advisories",
+            NotifyEvent::RebootRequired => "reboot_required",
+            NotifyEvent::PacnewFiles => "pacnew_files",
+            NotifyEvent::Test => "test",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Where a notification goes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierSink {
+    /// Mail through the local `sendmail`.
+    Sendmail {
+        to: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        from: Option<String>,
+    },
+    /// JSON POST; with a `secret` the body is signed with HMAC-SHA256.
+    Webhook {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        secret: Option<String>,
+    },
+    /// Run a program with the JSON payload on stdin. No shell is involved.
+    Command { argv: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct Notifier {
+    #[serde(flatten)]
+    pub sink: NotifierSink,
+    /// Events to send; empty means all of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<NotifyEvent>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Mail addresses end up in message headers, so anything that could start a
+/// new header or a second recipient is refused.
+fn validate_mail_address(address: &str) -> Result<()> {
+    if address.len() > 254
+        || !address.contains('@')
+        || address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || ",;<>\"".contains(c))
+    {
+        bail!("Invalid mail address: {}", address);
+    }
+    Ok(())
+}
+
+impl Notifier {
+    pub fn wants(&self, event: NotifyEvent) -> bool {
+        event == NotifyEvent::Test
+            || self.enabled && (self.events.is_empty() || self.events.contains(&event))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match &self.sink {
+            NotifierSink::Sendmail { to, from } => {
+                if to.is_empty() {
+                    bail!("A sendmail notifier needs at least one recipient");
+                }
+                for address in to.iter().chain(from) {
+                    validate_mail_address(address)?;
+                }
+            }
+            NotifierSink::Webhook { url, secret } => {
+                if !(url.starts_with("https://") || url.starts_with("http://"))
+                    || url.chars().any(|c| c.is_whitespace() || c.is_control())
+                {
+                    bail!("Webhook URL must be an http:// or https:// URL");
+                }
+                if secret.as_deref().is_some_and(str::is_empty) {
+                    bail!("Webhook secret cannot be empty");
+                }
+            }
+            NotifierSink::Command { argv } => match argv.first() {
+                Some(program) if program.starts_with('/') => {}
+                _ => bail!("A command notifier needs an absolute program path"),
+            },
+        }
+        Ok(())
+    }
+
+    /// A copy safe to show: a webhook secret is dropped.
+    pub fn redacted(&self) -> Self {
+        let mut copy = self.clone();
+        if let NotifierSink::Webhook { secret, .. } = &mut copy.sink {
+            *secret = None;
+        }
+        copy
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
     pub ignored_packages: Vec<String>,
     #[serde(default)]
+    pub ignored_groups: Vec<String>,
+    #[serde(default)]
     pub schedule: ScheduleConfig,
+    /// Additional schedules by name, each driving its own instance of the
+    /// `cockpit-pacman-scheduled@.timer` template.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedules: BTreeMap<String, ScheduleConfig>,
+    #[serde(default)]
+    pub desired_state: DesiredState,
+    /// Age in days after which a sync db is reported as stale. Unset means
+    /// DEFAULT_STALE_DB_DAYS; 0 turns the warning off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale_db_days: Option<u32>,
+    /// Dependency-installed packages to retain even when nothing explicit
+    /// needs them. They count as roots for orphan detection.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orphan_keep: Vec<String>,
+    /// Update policies by package name. They take precedence over
+    /// `group_policies`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub package_policies: BTreeMap<String, UpdatePolicy>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub group_policies: BTreeMap<String, UpdatePolicy>,
+    /// Notification sinks by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notifiers: BTreeMap<String, Notifier>,
     // Round-trip keys this binary doesn't know about (e.g. fields added by a
     // newer version) instead of dropping them on the next update() rewrite.
     #[serde(flatten)]
@@ -178,6 +648,10 @@ impl AppConfig {
         })
     }
 
+    pub fn stale_db_days(&self) -> u32 {
+        self.stale_db_days.unwrap_or(DEFAULT_STALE_DB_DAYS)
+    }
+
     pub fn add_ignored(&mut self, package: &str) -> bool {
         if !self.ignored_packages.contains(&package.to_string()) {
             self.ignored_packages.push(package.to_string());
@@ -201,104 +675,188 @@ impl AppConfig {
         self.ignored_packages.contains(&package.to_string())
     }
 
+    pub fn add_ignored_group(&mut self, group: &str) -> bool {
+        if !self.ignored_groups.contains(&group.to_string()) {
+            self.ignored_groups.push(group.to_string());
+            self.ignored_groups.sort();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove_ignored_group(&mut self, group: &str) -> bool {
+        if let Some(pos) = self.ignored_groups.iter().position(|g| g == group) {
+            self.ignored_groups.remove(pos);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn add_orphan_keep(&mut self, package: &str) -> bool {
+        if !self.orphan_keep.contains(&package.to_string()) {
+            self.orphan_keep.push(package.to_string());
+            self.orphan_keep.sort();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove_orphan_keep(&mut self, package: &str) -> bool {
+        if let Some(pos) = self.orphan_keep.iter().position(|p| p == package) {
+            self.orphan_keep.remove(pos);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The policy covering `name`: its own, else that of the first of its
+    /// `groups` with one. The group is returned alongside a group policy.
+    pub fn policy_for<'a>(
+        &'a self,
+        name: &str,
+        groups: &[&'a str],
+    ) -> Option<(&'a UpdatePolicy, Option<&'a str>)> {
+        if let Some(policy) = self.package_policies.get(name) {
+            return Some((policy, None));
+        }
+        groups
+            .iter()
+            .find_map(|&g| self.group_policies.get(g).map(|p| (p, Some(g))))
+    }
+
+    /// Register the configured ignores on an alpm handle, the same way pacman
+    /// applies IgnorePkg and IgnoreGroup from pacman.conf.
+    pub fn apply_ignores(&self, handle: &mut alpm::Alpm) -> Result<()> {
+        for pkg_name in &self.ignored_packages {
+            handle.add_ignorepkg(pkg_name.as_str())?;
+        }
+        for group in &self.ignored_groups {
+            handle.add_ignoregroup(group.as_str())?;
+        }
+        Ok(())
+    }
+
+    /// The unnamed `schedule`, or the named one from `schedules`.
+    pub fn schedule_named(&self, name: Option<&str>) -> Option<&ScheduleConfig> {
+        match name {
+            None => Some(&self.schedule),
+            Some(name) => self.schedules.get(name),
+        }
+    }
+
     pub fn apply_schedule_to_systemd(&self) -> Result<()> {
-        let schedule = &self.schedule;
-
-        if schedule.enabled {
-            // Create drop-in directory with proper permissions
-            fs::create_dir_all(TIMER_DROP_IN_DIR).with_context(|| {
-                format!(
-                    "Failed to create timer drop-in directory {}",
-                    TIMER_DROP_IN_DIR
-                )
-            })?;
-            fs::set_permissions(TIMER_DROP_IN_DIR, fs::Permissions::from_mode(0o755))
-                .with_context(|| format!("Failed to set permissions on {}", TIMER_DROP_IN_DIR))?;
-
-            // Write drop-in file with restrictive permissions
-            let drop_in_content =
-                format!("[Timer]\nOnCalendar=\nOnCalendar={}\n", schedule.schedule);
-
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .mode(0o644)
-                .open(TIMER_DROP_IN_PATH)
-                .with_context(|| format!("Failed to open timer drop-in: {}", TIMER_DROP_IN_PATH))?;
-
-            file.write_all(drop_in_content.as_bytes())
-                .with_context(|| {
-                    format!("Failed to write timer drop-in to {}", TIMER_DROP_IN_PATH)
-                })?;
-
-            // Undo the drop-in we just wrote when a systemctl step fails (spawn
-            // error, timeout, or non-zero exit).
-            let rollback = || {
-                let _ = fs::remove_file(TIMER_DROP_IN_PATH);
-                let _ = run_systemctl(&["daemon-reload"]);
-            };
+        apply_timer_to_systemd(None, &self.schedule)
+    }
+}
 
-            match run_systemctl(&["daemon-reload"]) {
-                Ok(o) if o.status.success() => {}
-                Ok(o) => {
-                    rollback();
-                    bail!(
-                        "systemctl daemon-reload failed: {}",
-                        String::from_utf8_lossy(&o.stderr)
-                    );
-                }
-                Err(e) => {
-                    rollback();
-                    return Err(e).context("Failed to run systemctl daemon-reload");
-                }
+/// Timer unit for a schedule: the plain timer for the unnamed schedule, an
+/// instance of the template for a named one.
+pub fn timer_unit(name: Option<&str>) -> String {
+    match name {
+        None => "cockpit-pacman-scheduled.timer".to_string(),
+        Some(name) => format!("cockpit-pacman-scheduled@{}.timer", name),
+    }
+}
+
+/// Write the OnCalendar drop-in for a schedule's timer and enable it, or
+/// disable the timer and remove the drop-in when the schedule is off.
+pub fn apply_timer_to_systemd(name: Option<&str>, schedule: &ScheduleConfig) -> Result<()> {
+    let unit = timer_unit(name);
+    let drop_in_dir = format!("{}/{}.d", SYSTEMD_UNIT_DIR, unit);
+    let drop_in_path = format!("{}/schedule.conf", drop_in_dir);
+
+    if schedule.enabled {
+        // Create drop-in directory with proper permissions
+        fs::create_dir_all(&drop_in_dir)
+            .with_context(|| format!("Failed to create timer drop-in directory {}", drop_in_dir))?;
+        fs::set_permissions(&drop_in_dir, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to set permissions on {}", drop_in_dir))?;
+
+        // Write drop-in file with restrictive permissions
+        let drop_in_content = format!("[Timer]\nOnCalendar=\nOnCalendar={}\n", schedule.schedule);
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o644)
+            .open(&drop_in_path)
+            .with_context(|| format!("Failed to open timer drop-in: {}", drop_in_path))?;
+
+        file.write_all(drop_in_content.as_bytes())
+            .with_context(|| format!("Failed to write timer drop-in to {}", drop_in_path))?;
+
+        // Undo the drop-in we just wrote when a systemctl step fails (spawn
+        // error, timeout, or non-zero exit).
+        let rollback = || {
+            let _ = fs::remove_file(&drop_in_path);
+            let _ = run_systemctl(&["daemon-reload"]);
+        };
+
+        match run_systemctl(&["daemon-reload"]) {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => {
+                rollback();
+                bail!(
+                    "systemctl daemon-reload failed: {}",
+                    String::from_utf8_lossy(&o.stderr)
+                );
+            }
+            Err(e) => {
+                rollback();
+                return Err(e).context("Failed to run systemctl daemon-reload");
             }
+        }
 
-            match run_systemctl(&["enable", "--now", "cockpit-pacman-scheduled.timer"]) {
-                Ok(o) if o.status.success() => {}
-                Ok(o) => {
-                    rollback();
-                    bail!(
-                        "Failed to enable timer: {}",
-                        String::from_utf8_lossy(&o.stderr)
-                    );
-                }
-                Err(e) => {
-                    rollback();
-                    return Err(e).context("Failed to enable timer");
-                }
+        match run_systemctl(&["enable", "--now", unit.as_str()]) {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => {
+                rollback();
+                bail!(
+                    "Failed to enable timer: {}",
+                    String::from_utf8_lossy(&o.stderr)
+                );
             }
-        } else {
-            // A not-enabled or absent timer is fine to "disable", but a real
-            // systemctl failure must surface or the timer keeps firing while
-            // config claims it's off.
-            match run_systemctl(&["disable", "--now", "cockpit-pacman-scheduled.timer"]) {
-                Ok(o) if o.status.success() => {}
-                Ok(o) => {
-                    let stderr = String::from_utf8_lossy(&o.stderr);
-                    if !timer_absent(&stderr) {
-                        bail!("Failed to disable timer: {}", stderr);
-                    }
+            Err(e) => {
+                rollback();
+                return Err(e).context("Failed to enable timer");
+            }
+        }
+    } else {
+        // A not-enabled or absent timer is fine to "disable", but a real
+        // systemctl failure must surface or the timer keeps firing while
+        // config claims it's off.
+        match run_systemctl(&["disable", "--now", unit.as_str()]) {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => {
+                let stderr = String::from_utf8_lossy(&o.stderr);
+                if !timer_absent(&stderr) {
+                    bail!("Failed to disable timer: {}", stderr);
                 }
-                Err(e) => return Err(e).context("Failed to disable timer"),
             }
+            Err(e) => return Err(e).context("Failed to disable timer"),
+        }
 
-            let _ = fs::remove_file(TIMER_DROP_IN_PATH);
+        let _ = fs::remove_file(&drop_in_path);
+        let _ = fs::remove_dir(&drop_in_dir);
 
-            match run_systemctl(&["daemon-reload"]) {
-                Ok(o) if o.status.success() => {}
-                Ok(o) => {
-                    bail!(
-                        "systemctl daemon-reload failed: {}",
-                        String::from_utf8_lossy(&o.stderr)
-                    );
-                }
-                Err(e) => return Err(e).context("Failed to run systemctl daemon-reload"),
+        match run_systemctl(&["daemon-reload"]) {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => {
+                bail!(
+                    "systemctl daemon-reload failed: {}",
+                    String::from_utf8_lossy(&o.stderr)
+                );
             }
+            Err(e) => return Err(e).context("Failed to run systemctl daemon-reload"),
         }
-
-        Ok(())
     }
+
+    Ok(())
 }
 
 #[derive(Serialize, TS)]
@@ -306,6 +864,7 @@ impl AppConfig {
 pub struct IgnoredPackagesResponse {
     pub packages: Vec<String>,
     pub total: usize,
+    pub groups: Vec<String>,
 }
 
 impl From<&AppConfig> for IgnoredPackagesResponse {
@@ -313,10 +872,50 @@ impl From<&AppConfig> for IgnoredPackagesResponse {
         Self {
             total: config.ignored_packages.len(),
             packages: config.ignored_packages.clone(),
+            groups: config.ignored_groups.clone(),
         }
     }
 }
 
+#[derive(Serialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct OrphanKeepResponse {
+    pub packages: Vec<String>,
+    pub total: usize,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct PolicyEntry {
+    pub name: String,
+    pub policy: UpdatePolicy,
+    pub description: String,
+    /// Set when a hold has run out; it is dropped on the next upgrade.
+    pub expired: Option<String>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct PoliciesResponse {
+    pub packages: Vec<PolicyEntry>,
+    pub groups: Vec<PolicyEntry>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct NotifierEntry {
+    pub name: String,
+    /// The notifier with any webhook secret removed.
+    pub notifier: Notifier,
+    pub has_secret: bool,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct NotifiersResponse {
+    pub notifiers: Vec<NotifierEntry>,
+}
+
 #[derive(Serialize, TS)]
 #[ts(export, export_to = "../../src/bindings/index.ts")]
 pub struct IgnoreOperationResponse {
@@ -332,32 +931,51 @@ pub struct IgnoreOperationResponse {
     rename = "ScheduleConfig"
 )]
 pub struct ScheduleConfigResponse {
+    /// Set for named schedules; absent for the default one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub name: Option<String>,
     pub enabled: bool,
     pub mode: String,
     pub schedule: String,
     pub max_packages: usize,
+    pub min_age_days: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub hooks: Option<ScheduleHooks>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub post_upgrade: Option<PostUpgradePolicy>,
     pub timer_active: bool,
     pub timer_next_run: Option<String>,
 }
 
 impl ScheduleConfigResponse {
     pub fn from_config(config: &ScheduleConfig) -> Self {
-        let (timer_active, timer_next_run) = get_timer_status();
+        Self::for_schedule(None, config)
+    }
+
+    pub fn for_schedule(name: Option<&str>, config: &ScheduleConfig) -> Self {
+        let (timer_active, timer_next_run) = get_timer_status(&timer_unit(name));
         Self {
+            name: name.map(|n| n.to_string()),
             enabled: config.enabled,
             mode: config.mode.to_string(),
             schedule: config.schedule.clone(),
             max_packages: config.max_packages,
+            min_age_days: config.min_age_days,
+            hooks: (!config.hooks.is_empty()).then(|| config.hooks.clone()),
+            post_upgrade: (!config.post_upgrade.is_empty()).then(|| config.post_upgrade.clone()),
             timer_active,
             timer_next_run,
         }
     }
 }
 
-fn get_timer_status() -> (bool, Option<String>) {
+fn get_timer_status(unit: &str) -> (bool, Option<String>) {
     let output = run_systemctl(&[
         "show",
-        "cockpit-pacman-scheduled.timer",
+        unit,
         "--property=ActiveState,NextElapseUSecRealtime",
     ]);
 
@@ -385,6 +1003,12 @@ fn get_timer_status() -> (bool, Option<String>) {
     }
 }
 
+#[derive(Serialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct SchedulesResponse {
+    pub schedules: Vec<ScheduleConfigResponse>,
+}
+
 #[derive(Serialize, TS)]
 #[ts(export, export_to = "../../src/bindings/index.ts")]
 pub struct ScheduleSetResponse {
@@ -393,8 +1017,43 @@ pub struct ScheduleSetResponse {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
-    use super::timer_absent;
+    use super::{
+        PostUpgradePolicy, RebootWindow, ScheduleConfig, ScheduleHooks, ScheduleMode, timer_absent,
+        timer_unit,
+    };
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    #[test]
+    fn named_schedules_use_template_instances() {
+        assert_eq!(timer_unit(None), "cockpit-pacman-scheduled.timer");
+        assert_eq!(
+            timer_unit(Some("nightly")),
+            "cockpit-pacman-scheduled@nightly.timer"
+        );
+    }
+
+    #[test]
+    fn schedule_modes_round_trip() {
+        for mode in [
+            ScheduleMode::Check,
+            ScheduleMode::Upgrade,
+            ScheduleMode::Security,
+            ScheduleMode::CacheClean,
+            ScheduleMode::OrphanReport,
+            ScheduleMode::MirrorRefresh,
+            ScheduleMode::KeyringRefresh,
+        ] {
+            let text = mode.to_string();
+            assert_eq!(text.parse::<ScheduleMode>().unwrap(), mode);
+            assert_eq!(
+                serde_json::to_string(&mode).unwrap(),
+                format!("\"{}\"", text)
+            );
+        }
+        assert!("cache_clean".parse::<ScheduleMode>().is_err());
+    }
 
     #[test]
     fn timer_absent_matches_missing_unit_but_not_real_failures() {
@@ -411,4 +1070,71 @@ mod tests {
         assert!(!timer_absent("Failed to disable unit: Access denied"));
         assert!(!timer_absent(""));
     }
+
+    #[test]
+    fn schedule_hooks_default_timeout_and_validation() {
+        let hooks: ScheduleHooks =
+            serde_json::from_str(r#"{"pre":[{"argv":["/usr/local/bin/drain","web1"]}]}"#).unwrap();
+        assert_eq!(hooks.pre[0].timeout_secs, 300);
+        assert!(hooks.post.is_empty());
+        hooks.validate().unwrap();
+
+        // Each stage has its own budget.
+        let full: ScheduleHooks = serde_json::from_str(
+            r#"{"pre":[{"argv":["/a"],"timeout_secs":900},{"argv":["/b"],"timeout_secs":900}],
+                "post":[{"argv":["/c"],"timeout_secs":900},{"argv":["/d"],"timeout_secs":900}]}"#,
+        )
+        .unwrap();
+        full.validate().unwrap();
+
+        // Schedules without hooks keep their old shape.
+        let value = serde_json::to_value(ScheduleConfig::default()).unwrap();
+        assert!(value.get("hooks").is_none());
+
+        for json in [
+            r#"{"pre":[{"argv":[]}]}"#,
+            r#"{"post":[{"argv":["drain"]}]}"#,
+            r#"{"post":[{"argv":["/bin/true"],"timeout_secs":0}]}"#,
+            r#"{"post":[{"argv":["/bin/true"],"timeout_secs":901}]}"#,
+            r#"{"pre":[{"argv":["/a"],"timeout_secs":900},{"argv":["/b"],"timeout_secs":900},{"argv":["/c"],"timeout_secs":1}]}"#,
+        ] {
+            let hooks: ScheduleHooks = serde_json::from_str(json).unwrap();
+            assert!(hooks.validate().is_err(), "{json} should be rejected");
+        }
+    }
+
+    fn at(day: u32, time: &str) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 6, day)
+            .unwrap()
+            .and_time(time.parse().unwrap())
+    }
+
+    #[test]
+    fn reboot_window_picks_the_next_time_inside_it() {
+        let night: RebootWindow = "02:00-05:00".parse().unwrap();
+        assert_eq!(night.next_from(at(1, "03:10:00")), at(1, "03:10:00"));
+        assert_eq!(night.next_from(at(1, "01:00:00")), at(1, "02:00:00"));
+        assert_eq!(night.next_from(at(1, "05:00:00")), at(2, "02:00:00"));
+
+        // A window across midnight.
+        let late: RebootWindow = "23:00-01:30".parse().unwrap();
+        assert!(late.contains("00:45:00".parse().unwrap()));
+        assert_eq!(late.next_from(at(1, "00:20:00")), at(1, "00:20:00"));
+        assert_eq!(late.next_from(at(1, "12:00:00")), at(1, "23:00:00"));
+    }
+
+    #[test]
+    fn post_upgrade_policy_validates_the_window() {
+        for window in ["02:00-05:00", "23:00-01:30", " 2:00 - 4:00 "] {
+            let policy = PostUpgradePolicy {
+                restart_services: false,
+                reboot_window: Some(window.to_string()),
+            };
+            policy.validate().unwrap();
+        }
+        for window in ["", "02:00", "02:00-02:00", "25:00-03:00", "2am-4am"] {
+            assert!(window.parse::<RebootWindow>().is_err(), "{window}");
+        }
+        assert!(PostUpgradePolicy::default().is_empty());
+    }
 }
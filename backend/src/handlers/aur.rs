@@ -0,0 +1,195 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use aur_client::AurClient;
+use aur_client::models::AurPackage;
+use serde::{Deserialize, Serialize};
+
+use crate::alpm::get_handle;
+use crate::db::get_repo_map;
+use crate::models::{ForeignPackage, ForeignUpdatesResponse};
+use crate::util::{config_path, emit_json, write_json_atomic};
+
+/// AUR answers from the last successful check. `None` records that the AUR
+/// had no such package, so a stale report can still say "deleted" rather than
+/// "unchecked".
+#[derive(Serialize, Deserialize, Default)]
+struct AurCache {
+    fetched_at: i64,
+    packages: BTreeMap<String, Option<AurPackage>>,
+}
+
+fn aur_cache_path() -> Result<PathBuf> {
+    config_path("aur-cache.json")
+}
+
+fn read_aur_cache(path: PathBuf) -> Option<AurCache> {
+    let content = std::fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_aur_cache(path: &Path, cache: &AurCache) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_json_atomic(path, cache)
+}
+
+/// Installed foreign packages (name, version) against what the AUR knows.
+/// Returns the report rows and the names `aur` has no answer for.
+pub(crate) fn build_report(
+    foreign: &[(String, String)],
+    aur: &BTreeMap<String, Option<AurPackage>>,
+    vercmp: impl Fn(&str, &str) -> Ordering,
+) -> (Vec<ForeignPackage>, Vec<String>) {
+    let mut packages = Vec::new();
+    let mut unchecked = Vec::new();
+    for (name, installed) in foreign {
+        let Some(entry) = aur.get(name) else {
+            unchecked.push(name.clone());
+            continue;
+        };
+        packages.push(match entry {
+            Some(pkg) => ForeignPackage {
+                name: name.clone(),
+                installed_version: installed.clone(),
+                aur_version: Some(pkg.version.clone()),
+                newer_available: vercmp(&pkg.version, installed) == Ordering::Greater,
+                out_of_date: pkg.out_of_date,
+                maintainer: pkg.maintainer.clone(),
+                orphaned: pkg.maintainer.is_none(),
+                in_aur: true,
+            },
+            None => ForeignPackage {
+                name: name.clone(),
+                installed_version: installed.clone(),
+                aur_version: None,
+                newer_available: false,
+                out_of_date: None,
+                maintainer: None,
+                orphaned: false,
+                in_aur: false,
+            },
+        });
+    }
+    (packages, unchecked)
+}
+
+/// Check every foreign package (in no sync db) against the AUR. When the AUR
+/// is unreachable the last successful answers are served, marked stale, like
+/// `check_security`.
+pub fn check_foreign_updates() -> Result<()> {
+    let handle = get_handle()?;
+    let repo_map = get_repo_map(&handle);
+    let foreign: Vec<(String, String)> = handle
+        .localdb()
+        .pkgs()
+        .iter()
+        .filter(|p| !repo_map.contains_key(p.name()))
+        .map(|p| (p.name().to_string(), p.version().to_string()))
+        .collect();
+    let names: Vec<String> = foreign.iter().map(|(n, _)| n.clone()).collect();
+
+    let client = AurClient::new(crate::util::detected_ip_family());
+    let (aur, stale, cached_at) = match client.info(&names) {
+        Ok(found) => {
+            let mut packages: BTreeMap<String, Option<AurPackage>> =
+                names.iter().map(|n| (n.clone(), None)).collect();
+            for pkg in found {
+                packages.insert(pkg.name.clone(), Some(pkg));
+            }
+            let cache = AurCache {
+                fetched_at: chrono::Utc::now().timestamp(),
+                packages,
+            };
+            if let Ok(path) = aur_cache_path() {
+                let _ = write_aur_cache(&path, &cache);
+            }
+            (cache.packages, false, None)
+        }
+        Err(e) => {
+            // Without a cache an empty report would read as "all up to date",
+            // so the error propagates instead.
+            let Some(cache) = aur_cache_path().ok().and_then(read_aur_cache) else {
+                return Err(e);
+            };
+            (cache.packages, true, Some(cache.fetched_at))
+        }
+    };
+
+    let (packages, unchecked) = build_report(&foreign, &aur, |a, b| alpm::vercmp(a, b));
+    emit_json(&ForeignUpdatesResponse {
+        packages,
+        unchecked,
+        stale,
+        cached_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aur_pkg(name: &str, version: &str, maintainer: Option<&str>) -> AurPackage {
+        AurPackage {
+            id: 1,
+            name: name.to_string(),
+            package_base_id: 1,
+            package_base: name.to_string(),
+            version: version.to_string(),
+            description: None,
+            url: None,
+            num_votes: 0,
+            popularity: 0.0,
+            out_of_date: None,
+            maintainer: maintainer.map(str::to_string),
+            first_submitted: 0,
+            last_modified: 0,
+            url_path: None,
+            depends: Vec::new(),
+            make_depends: Vec::new(),
+        }
+    }
+
+    fn foreign(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn report_flags_updates_orphans_and_deletions() {
+        let mut aur = BTreeMap::new();
+        aur.insert(
+            "yay".to_string(),
+            Some(aur_pkg("yay", "12.4-1", Some("jguer"))),
+        );
+        let mut flagged = aur_pkg("old-tool", "1.0-1", None);
+        flagged.out_of_date = Some(1_700_000_000);
+        aur.insert("old-tool".to_string(), Some(flagged));
+        aur.insert("gone".to_string(), None);
+
+        let (packages, unchecked) = build_report(
+            &foreign(&[("yay", "12.3-1"), ("old-tool", "1.0-1"), ("gone", "2-1")]),
+            &aur,
+            |a, b| a.cmp(b),
+        );
+        assert!(unchecked.is_empty());
+        assert!(packages[0].newer_available && !packages[0].orphaned);
+        assert_eq!(packages[1].out_of_date, Some(1_700_000_000));
+        assert!(packages[1].orphaned && !packages[1].newer_available);
+        assert!(!packages[2].in_aur && packages[2].aur_version.is_none());
+    }
+
+    #[test]
+    fn names_missing_from_cache_are_unchecked() {
+        let aur = BTreeMap::new();
+        let (packages, unchecked) =
+            build_report(&foreign(&[("new-local", "1-1")]), &aur, |a, b| a.cmp(b));
+        assert!(packages.is_empty());
+        assert_eq!(unchecked, vec!["new-local"]);
+    }
+}
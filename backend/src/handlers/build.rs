@@ -0,0 +1,406 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt, lchown};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::models::StreamEvent;
+use crate::util::{TimeoutGuard, emit_event, is_cancelled, lookup_user};
+
+/// System user makepkg runs as (created by the packaged sysusers.d entry).
+/// makepkg refuses to run as root, and a PKGBUILD is arbitrary shell.
+pub const BUILD_USER: &str = "cockpit-pacman-build";
+const BUILD_ROOT: &str = "/var/lib/cockpit-pacman/build";
+pub const DEFAULT_BUILD_TIMEOUT_SECS: u64 = 3600;
+const KILL_GRACE: Duration = Duration::from_secs(10);
+const BUILD_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/bin";
+
+#[derive(Debug, Clone, Copy)]
+pub struct BuildUser {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+pub fn lookup_build_user() -> Result<BuildUser> {
+    let Some((uid, gid)) = lookup_user(BUILD_USER) else {
+        bail!(
+            "Build user '{}' does not exist; run systemd-sysusers or create it",
+            BUILD_USER
+        );
+    };
+    if uid == 0 {
+        bail!("Build user '{}' must not be root", BUILD_USER);
+    }
+    Ok(BuildUser { uid, gid })
+}
+
+/// A private copy of the PKGBUILD directory, owned by the build user and
+/// removed on drop. Building in a copy keeps src/ and pkg/ out of the
+/// caller's tree and leaves nothing root-owned for makepkg to trip over.
+pub struct Workspace {
+    pub dir: PathBuf,
+    pub pkgdest: PathBuf,
+}
+
+impl Workspace {
+    pub fn create(source: &Path, user: BuildUser) -> Result<Self> {
+        let root = Path::new(BUILD_ROOT);
+        ensure_build_root(root)?;
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let dir = root.join(format!("{}-{}", std::process::id(), nanos));
+        fs::create_dir(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let workspace = Self {
+            pkgdest: dir.join("out"),
+            dir,
+        };
+        let build = workspace.dir.join("build");
+        copy_tree(source, &build)?;
+        fs::create_dir(&workspace.pkgdest)?;
+        chown_tree(&workspace.dir, user)?;
+        fs::set_permissions(&workspace.dir, fs::Permissions::from_mode(0o700))?;
+        Ok(workspace)
+    }
+
+    pub fn build_dir(&self) -> PathBuf {
+        self.dir.join("build")
+    }
+
+    /// Built package archives, without their detached signatures.
+    pub fn packages(&self) -> Result<Vec<PathBuf>> {
+        let mut packages: Vec<PathBuf> = fs::read_dir(&self.pkgdest)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(is_package_file)
+            })
+            .collect();
+        packages.sort();
+        Ok(packages)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            eprintln!(
+                "Warning: failed to remove build workspace {}: {}",
+                self.dir.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Create (or validate) the directory workspaces go in. Everything below it
+/// is written as root, so refuse anything but a real directory owned by us
+/// that only we can write to.
+fn ensure_build_root(root: &Path) -> Result<()> {
+    if let Some(parent) = root.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    match fs::DirBuilder::new().mode(0o755).create(root) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {}", root.display())),
+    }
+    let meta =
+        fs::symlink_metadata(root).with_context(|| format!("Failed to stat {}", root.display()))?;
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o022 != 0 {
+        bail!(
+            "Refusing to use {}: not a directory owned and only writable by uid {}",
+            root.display(),
+            uid
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn is_package_file(name: &str) -> bool {
+    name.contains(".pkg.tar") && !name.ends_with(".sig")
+}
+
+/// Copy regular files and directories. Symlinks are skipped: the copy runs as
+/// root, so following one could hand the build user a file it can't read.
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name();
+        // Leftovers from an earlier manual build are not sources.
+        if file_type.is_dir() && (name == "src" || name == "pkg") {
+            continue;
+        }
+        let target = to.join(&name);
+        if file_type.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn chown_tree(path: &Path, user: BuildUser) -> Result<()> {
+    lchown(path, Some(user.uid), Some(user.gid))
+        .with_context(|| format!("Failed to chown {}", path.display()))?;
+    if path.is_dir() && !path.is_symlink() {
+        for entry in fs::read_dir(path)? {
+            chown_tree(&entry?.path(), user)?;
+        }
+    }
+    Ok(())
+}
+
+/// makepkg as the build user with nothing inherited from our environment.
+/// std drops supplementary groups when it switches uid as root.
+fn makepkg_command(workspace: &Workspace, user: BuildUser, args: &[&str]) -> Command {
+    let build_dir = workspace.build_dir();
+    let mut cmd = Command::new("makepkg");
+    cmd.args(args)
+        .current_dir(&build_dir)
+        .env_clear()
+        .env("PATH", BUILD_PATH)
+        .env("HOME", &workspace.dir)
+        .env("LC_ALL", "C.UTF-8")
+        .env("PKGDEST", &workspace.pkgdest)
+        .env("SRCDEST", &build_dir)
+        .env("BUILDDIR", &build_dir)
+        .uid(user.uid)
+        .gid(user.gid)
+        .stdin(Stdio::null())
+        // Own process group, so cancelling reaches the build tools makepkg
+        // started, not just makepkg itself.
+        .process_group(0);
+    cmd
+}
+
+/// Dependencies makepkg checks before building: depends, makedepends and
+/// checkdepends of every section, generic or for `carch`.
+pub fn build_dependencies(
+    workspace: &Workspace,
+    user: BuildUser,
+    carch: &str,
+) -> Result<Vec<String>> {
+    let output = makepkg_command(workspace, user, &["--printsrcinfo"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to run makepkg --printsrcinfo")?;
+    if !output.status.success() {
+        bail!(
+            "makepkg --printsrcinfo failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_srcinfo_deps(
+        &String::from_utf8_lossy(&output.stdout),
+        carch,
+    ))
+}
+
+pub(crate) fn parse_srcinfo_deps(srcinfo: &str, carch: &str) -> Vec<String> {
+    let mut deps: Vec<String> = Vec::new();
+    for line in srcinfo.lines() {
+        let Some((key, value)) = line.trim().split_once(" = ") else {
+            continue;
+        };
+        let base = key.strip_suffix(&format!("_{}", carch)).unwrap_or(key);
+        if matches!(base, "depends" | "makedepends" | "checkdepends")
+            && !deps.iter().any(|d| d == value)
+        {
+            deps.push(value.to_string());
+        }
+    }
+    deps
+}
+
+pub enum BuildOutcome {
+    Succeeded,
+    Failed(ExitStatus),
+    Cancelled,
+    TimedOut,
+}
+
+fn stream_lines<R: Read + Send + 'static>(
+    reader: Option<R>,
+    level: &'static str,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let Some(reader) = reader else {
+            return;
+        };
+        for line in BufReader::new(reader).lines().map_while(|l| l.ok()) {
+            if !line.trim().is_empty() {
+                emit_event(&StreamEvent::Log {
+                    level: level.to_string(),
+                    message: line,
+                });
+            }
+        }
+    })
+}
+
+/// SIGTERM the whole process group, then SIGKILL after the grace. Unlike
+/// pacman, makepkg holds no state a hard kill can corrupt.
+fn terminate_group(child: &mut std::process::Child) {
+    kill_group(child, libc::SIGTERM);
+    let deadline = Instant::now() + KILL_GRACE;
+    while Instant::now() < deadline {
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    kill_group(child, libc::SIGKILL);
+    let _ = child.wait();
+}
+
+/// Signal makepkg's process group. The group id stays reserved while any
+/// member lives, even once makepkg itself is reaped.
+fn kill_group(child: &std::process::Child, signal: libc::c_int) {
+    // SAFETY: kill(2) on a process group we created has no memory effects.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), signal);
+    }
+}
+
+/// Run makepkg, streaming its output as log events, until it exits, the
+/// operation times out or a cancel arrives.
+pub fn run_makepkg(
+    workspace: &Workspace,
+    user: BuildUser,
+    timeout: &TimeoutGuard,
+) -> Result<BuildOutcome> {
+    let mut child = makepkg_command(
+        workspace,
+        user,
+        &["--noconfirm", "--noprogressbar", "--cleanbuild"],
+    )
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .context("Failed to spawn makepkg")?;
+
+    let stdout = stream_lines(child.stdout.take(), "info");
+    let stderr = stream_lines(child.stderr.take(), "warning");
+
+    let outcome = loop {
+        if is_cancelled() {
+            terminate_group(&mut child);
+            break BuildOutcome::Cancelled;
+        }
+        if timeout.is_timed_out() {
+            terminate_group(&mut child);
+            break BuildOutcome::TimedOut;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break BuildOutcome::Succeeded,
+            Ok(Some(status)) => break BuildOutcome::Failed(status),
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(e) => {
+                terminate_group(&mut child);
+                return Err(e).context("Failed to check makepkg status");
+            }
+        }
+    };
+
+    // Anything makepkg left running in its group, a daemon a build or test
+    // started, would hold the pipes open and the joins below forever.
+    kill_group(&child, libc::SIGKILL);
+    let _ = stdout.join();
+    let _ = stderr.join();
+    Ok(outcome)
+}
+
+/// The PKGBUILD directory as an absolute, symlink-free path.
+pub fn resolve_build_dir(dir: &str) -> Result<PathBuf> {
+    let path = Path::new(dir);
+    if !path.is_absolute() {
+        bail!("Build directory must be an absolute path");
+    }
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Build directory {} not found", dir))?;
+    if !path.join("PKGBUILD").is_file() {
+        bail!("No PKGBUILD in {}", path.display());
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srcinfo_deps_cover_all_kinds_and_arch() {
+        let srcinfo = "pkgbase = tool\n\
+            \tpkgver = 1.0\n\
+            \tmakedepends = cargo\n\
+            \tcheckdepends = python-pytest\n\
+            \tdepends = glibc\n\
+            \tdepends_x86_64 = lib32-glibc\n\
+            \tdepends_aarch64 = arm-only\n\
+            \n\
+            pkgname = tool\n\
+            \tdepends = glibc\n\
+            \tdepends = openssl>=3\n\
+            \toptdepends = bash: completion\n";
+        assert_eq!(
+            parse_srcinfo_deps(srcinfo, "x86_64"),
+            vec![
+                "cargo",
+                "python-pytest",
+                "glibc",
+                "lib32-glibc",
+                "openssl>=3"
+            ]
+        );
+    }
+
+    #[test]
+    fn package_files_exclude_signatures() {
+        assert!(is_package_file("tool-1.0-1-x86_64.pkg.tar.zst"));
+        assert!(!is_package_file("tool-1.0-1-x86_64.pkg.tar.zst.sig"));
+        assert!(!is_package_file("tool.log"));
+    }
+
+    #[test]
+    fn build_dir_must_be_absolute() {
+        assert!(resolve_build_dir("relative/dir").is_err());
+    }
+
+    #[test]
+    fn build_root_must_not_be_writable_by_others() {
+        let base = std::env::temp_dir().join(format!("cpac-buildroot-{}", std::process::id()));
+        let root = base.join("state").join("build");
+        ensure_build_root(&root).unwrap();
+        assert_eq!(
+            fs::metadata(&root).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+
+        fs::set_permissions(&root, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(ensure_build_root(&root).is_err());
+
+        let link = base.join("link");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+        assert!(ensure_build_root(&link).is_err());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use alpm::Alpm;
 use anyhow::Result;
 use std::collections::{HashMap, HashSet};
 use std::fs;
@@ -71,10 +72,52 @@ pub fn clean_cache(keep_versions: u32, filter_pkgs: &[String]) -> Result<()> {
         return Ok(());
     }
 
+    let (removed_count, freed_bytes) = remove_old_versions(
+        &handle,
+        cache_path,
+        keep_versions,
+        filter_pkgs,
+        |level, message| {
+            emit_event(&StreamEvent::Log {
+                level: level.to_string(),
+                message,
+            })
+        },
+    );
+
+    let message = if removed_count == 0 {
+        "No packages to remove".to_string()
+    } else {
+        format!(
+            "Removed {} package{}, freed {}",
+            removed_count,
+            if removed_count == 1 { "" } else { "s" },
+            format_bytes(freed_bytes)
+        )
+    };
+
+    emit_event(&StreamEvent::Complete {
+        success: true,
+        message: Some(message),
+    });
+
+    Ok(())
+}
+
+/// Delete all but the newest `keep_versions` cached versions of each package
+/// (only those in `filter_pkgs`, when given), passing each removal and failure
+/// to `log`. Returns the number of packages removed and the bytes freed.
+pub(crate) fn remove_old_versions(
+    handle: &Alpm,
+    cache_path: &Path,
+    keep_versions: u32,
+    filter_pkgs: &[String],
+    mut log: impl FnMut(&str, String),
+) -> (u32, u64) {
     let filter: HashSet<&str> = filter_pkgs.iter().map(|s| s.as_str()).collect();
 
     let mut groups: HashMap<String, Vec<(fs::DirEntry, String, String)>> = HashMap::new();
-    for (entry, filename, name, version) in load_cache_packages(&handle, cache_path) {
+    for (entry, filename, name, version) in load_cache_packages(handle, cache_path) {
         if !filter.is_empty() && !filter.contains(name.as_str()) {
             continue;
         }
@@ -99,51 +142,29 @@ pub fn clean_cache(keep_versions: u32, filter_pkgs: &[String]) -> Result<()> {
                 Ok(()) => {
                     removed_count += 1;
                     freed_bytes += size;
-                    emit_event(&StreamEvent::Log {
-                        level: "info".to_string(),
-                        message: format!("Removed {}", filename),
-                    });
+                    log("info", format!("Removed {}", filename));
 
                     let sig_path = path.with_file_name(format!("{}.sig", filename));
                     if sig_path.exists()
                         && let Err(e) = fs::remove_file(&sig_path)
                     {
-                        emit_event(&StreamEvent::Log {
-                            level: "warning".to_string(),
-                            message: format!("Failed to remove {}.sig: {}", filename, e),
-                        });
+                        log(
+                            "warning",
+                            format!("Failed to remove {}.sig: {}", filename, e),
+                        );
                     }
                 }
                 Err(e) => {
-                    emit_event(&StreamEvent::Log {
-                        level: "warning".to_string(),
-                        message: format!("Failed to remove {}: {}", filename, e),
-                    });
+                    log("warning", format!("Failed to remove {}: {}", filename, e));
                 }
             }
         }
     }
 
-    let message = if removed_count == 0 {
-        "No packages to remove".to_string()
-    } else {
-        format!(
-            "Removed {} package{}, freed {}",
-            removed_count,
-            if removed_count == 1 { "" } else { "s" },
-            format_bytes(freed_bytes)
-        )
-    };
-
-    emit_event(&StreamEvent::Complete {
-        success: true,
-        message: Some(message),
-    });
-
-    Ok(())
+    (removed_count, freed_bytes)
 }
 
-fn format_bytes(bytes: u64) -> String {
+pub(crate) fn format_bytes(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
     let mut size = bytes as f64;
     for unit in UNITS {
@@ -1,6 +1,8 @@
 use anyhow::Result;
 
-use crate::config::{AppConfig, IgnoreOperationResponse, IgnoredPackagesResponse};
+use crate::config::{
+    AppConfig, IgnoreOperationResponse, IgnoredPackagesResponse, OrphanKeepResponse,
+};
 use crate::util::emit_json;
 
 pub fn list_ignored() -> Result<()> {
@@ -40,3 +42,75 @@ pub fn remove_ignored(package: &str) -> Result<()> {
 
     emit_json(&response)
 }
+
+pub fn add_ignored_group(group: &str) -> Result<()> {
+    let added = AppConfig::update(|config| Ok(config.add_ignored_group(group)))?;
+
+    let response = IgnoreOperationResponse {
+        success: true,
+        package: group.to_string(),
+        message: if added {
+            format!("Group '{}' added to ignored list", group)
+        } else {
+            format!("Group '{}' was already in ignored list", group)
+        },
+    };
+
+    emit_json(&response)
+}
+
+pub fn remove_ignored_group(group: &str) -> Result<()> {
+    let removed = AppConfig::update(|config| Ok(config.remove_ignored_group(group)))?;
+
+    let response = IgnoreOperationResponse {
+        success: removed,
+        package: group.to_string(),
+        message: if removed {
+            format!("Group '{}' removed from ignored list", group)
+        } else {
+            format!("Group '{}' was not in ignored list", group)
+        },
+    };
+
+    emit_json(&response)
+}
+
+pub fn list_orphan_keep() -> Result<()> {
+    let config = AppConfig::load()?;
+    emit_json(&OrphanKeepResponse {
+        total: config.orphan_keep.len(),
+        packages: config.orphan_keep,
+    })
+}
+
+pub fn add_orphan_keep(package: &str) -> Result<()> {
+    let added = AppConfig::update(|config| Ok(config.add_orphan_keep(package)))?;
+
+    let response = IgnoreOperationResponse {
+        success: true,
+        package: package.to_string(),
+        message: if added {
+            format!("Package '{}' added to orphan keep list", package)
+        } else {
+            format!("Package '{}' was already in orphan keep list", package)
+        },
+    };
+
+    emit_json(&response)
+}
+
+pub fn remove_orphan_keep(package: &str) -> Result<()> {
+    let removed = AppConfig::update(|config| Ok(config.remove_orphan_keep(package)))?;
+
+    let response = IgnoreOperationResponse {
+        success: removed,
+        package: package.to_string(),
+        message: if removed {
+            format!("Package '{}' removed from orphan keep list", package)
+        } else {
+            format!("Package '{}' was not in orphan keep list", package)
+        },
+    };
+
+    emit_json(&response)
+}
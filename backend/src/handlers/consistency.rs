@@ -0,0 +1,233 @@
+use alpm::Package;
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::alpm::{dep_string, get_handle};
+use crate::models::{
+    InstalledConflict, Lib32Mismatch, MissingDependency, NamedVersion, NewerThanRepo,
+    SplitVersionMismatch, SystemConsistencyResponse,
+};
+use crate::util::emit_json;
+
+/// `epoch:pkgver` without the pkgrel. lib32 packages are rebuilt on their own
+/// schedule, so only the upstream version has to agree with the native one.
+pub(crate) fn upstream_version(version: &str) -> &str {
+    version.rsplit_once('-').map_or(version, |(v, _)| v)
+}
+
+/// Installed packages sharing a pkgbase but not a version: one half of a split
+/// package was upgraded without the other.
+pub(crate) fn split_mismatches(
+    installed: &[(String, Option<String>, String)],
+) -> Vec<SplitVersionMismatch> {
+    let mut by_base: BTreeMap<&str, Vec<NamedVersion>> = BTreeMap::new();
+    for (name, base, version) in installed {
+        if let Some(base) = base {
+            by_base.entry(base).or_default().push(NamedVersion {
+                name: name.clone(),
+                version: version.clone(),
+            });
+        }
+    }
+    by_base
+        .into_iter()
+        .filter(|(_, pkgs)| pkgs.iter().any(|p| p.version != pkgs[0].version))
+        .map(|(base, mut packages)| {
+            packages.sort_by(|a, b| a.name.cmp(&b.name));
+            SplitVersionMismatch {
+                pkgbase: base.to_string(),
+                packages,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn lib32_mismatches(versions: &HashMap<String, String>) -> Vec<Lib32Mismatch> {
+    let mut mismatches: Vec<Lib32Mismatch> = versions
+        .iter()
+        .filter_map(|(name, version)| {
+            let native = name.strip_prefix("lib32-")?;
+            let native_version = versions.get(native)?;
+            (upstream_version(version) != upstream_version(native_version)).then(|| Lib32Mismatch {
+                name: name.clone(),
+                version: version.clone(),
+                native: native.to_string(),
+                native_version: native_version.clone(),
+            })
+        })
+        .collect();
+    mismatches.sort_by(|a, b| a.name.cmp(&b.name));
+    mismatches
+}
+
+/// Symptoms of a partial upgrade or a botched manual fix: packages ahead of
+/// or missing from the repos, split and lib32 packages out of step, and the
+/// dependency/conflict breakage `pacman -Dk` reports.
+pub fn system_consistency() -> Result<()> {
+    let handle = get_handle()?;
+    let local: Vec<&Package> = handle.localdb().pkgs().iter().collect();
+
+    let mut newer_than_repo = Vec::new();
+    let mut not_in_repos = Vec::new();
+    for pkg in &local {
+        let found = handle
+            .syncdbs()
+            .iter()
+            .find_map(|db| db.pkg(pkg.name()).ok().map(|p| (db.name(), p)));
+        match found {
+            Some((repo, sync_pkg)) => {
+                if alpm::vercmp(pkg.version().as_str(), sync_pkg.version().as_str())
+                    == Ordering::Greater
+                {
+                    newer_than_repo.push(NewerThanRepo {
+                        name: pkg.name().to_string(),
+                        installed_version: pkg.version().to_string(),
+                        repo_version: sync_pkg.version().to_string(),
+                        repository: repo.to_string(),
+                    });
+                }
+            }
+            None => not_in_repos.push(NamedVersion {
+                name: pkg.name().to_string(),
+                version: pkg.version().to_string(),
+            }),
+        }
+    }
+
+    let installed: Vec<(String, Option<String>, String)> = local
+        .iter()
+        .map(|p| {
+            (
+                p.name().to_string(),
+                p.base().map(|b| b.to_string()),
+                p.version().to_string(),
+            )
+        })
+        .collect();
+    let versions: HashMap<String, String> = installed
+        .iter()
+        .map(|(name, _, version)| (name.clone(), version.clone()))
+        .collect();
+
+    // Same arguments pacman -Dk passes: the local db checked against itself.
+    let missing_dependencies: Vec<MissingDependency> = handle
+        .check_deps(
+            local.iter(),
+            Vec::<&Package>::new().iter(),
+            local.iter(),
+            false,
+        )
+        .iter()
+        .map(|m| MissingDependency {
+            package: m.target().to_string(),
+            dependency: dep_string(m.depend()),
+        })
+        .collect();
+    let conflicts: Vec<InstalledConflict> = handle
+        .check_conflicts(local.iter())
+        .iter()
+        .map(|c| InstalledConflict {
+            package1: c.package1().name().to_string(),
+            package2: c.package2().name().to_string(),
+            reason: dep_string(c.reason()),
+        })
+        .collect();
+
+    let split_mismatches = split_mismatches(&installed);
+    let lib32_mismatches = lib32_mismatches(&versions);
+    let issue_count = newer_than_repo.len()
+        + not_in_repos.len()
+        + split_mismatches.len()
+        + lib32_mismatches.len()
+        + missing_dependencies.len()
+        + conflicts.len();
+
+    emit_json(&SystemConsistencyResponse {
+        newer_than_repo,
+        not_in_repos,
+        split_mismatches,
+        lib32_mismatches,
+        missing_dependencies,
+        conflicts,
+        issue_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nv(name: &str, version: &str) -> NamedVersion {
+        NamedVersion {
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn upstream_version_drops_pkgrel() {
+        assert_eq!(upstream_version("1:24.0.1-2"), "1:24.0.1");
+        assert_eq!(upstream_version("2.3"), "2.3");
+    }
+
+    #[test]
+    fn split_packages_out_of_step_are_reported() {
+        let installed = vec![
+            (
+                "libreoffice-fresh".to_string(),
+                Some("libreoffice".to_string()),
+                "24.2-1".to_string(),
+            ),
+            (
+                "libreoffice-sdk".to_string(),
+                Some("libreoffice".to_string()),
+                "24.8-1".to_string(),
+            ),
+            (
+                "systemd".to_string(),
+                Some("systemd".to_string()),
+                "256-1".to_string(),
+            ),
+            (
+                "systemd-libs".to_string(),
+                Some("systemd".to_string()),
+                "256-1".to_string(),
+            ),
+            ("orphan".to_string(), None, "1-1".to_string()),
+        ];
+        assert_eq!(
+            split_mismatches(&installed),
+            vec![SplitVersionMismatch {
+                pkgbase: "libreoffice".to_string(),
+                packages: vec![
+                    nv("libreoffice-fresh", "24.2-1"),
+                    nv("libreoffice-sdk", "24.8-1")
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn lib32_compares_upstream_version_only() {
+        let versions: HashMap<String, String> = [
+            ("mesa", "1:24.2.1-1"),
+            ("lib32-mesa", "1:24.2.1-3"),
+            ("glibc", "2.40-1"),
+            ("lib32-glibc", "2.39-2"),
+            ("lib32-orphan", "1.0-1"),
+        ]
+        .into_iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(
+            lib32_mismatches(&versions),
+            vec![Lib32Mismatch {
+                name: "lib32-glibc".to_string(),
+                version: "2.39-2".to_string(),
+                native: "glibc".to_string(),
+                native_version: "2.40-1".to_string(),
+            }]
+        );
+    }
+}
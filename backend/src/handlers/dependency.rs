@@ -1,9 +1,14 @@
+use alpm::{Alpm, Dep, Package};
 use anyhow::Result;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
 
-use crate::alpm::{get_handle, reason_to_string};
+use crate::alpm::{dep_string, get_handle, reason_to_string};
 use crate::db::get_repo_map;
-use crate::models::{DependencyEdge, DependencyNode, DependencyTreeResponse};
+use crate::models::{
+    DependencyEdge, DependencyGraphResponse, DependencyNode, DependencyTreeResponse,
+    GraphDependency, GraphPackage, UnresolvedDependency,
+};
 use crate::util::emit_json;
 
 const MAX_NODES: usize = 500;
@@ -264,3 +269,457 @@ fn add_dependency(
 
     queue.push_back((resolved_name, new_depth));
 }
+
+/// The `provides` entry of `satisfier` that matched `dep`, when the
+/// dependency was not satisfied by the package name itself.
+pub(crate) fn matched_provide(dep: &Dep, satisfier: &Package) -> Option<String> {
+    if dep.name() == satisfier.name() {
+        return None;
+    }
+    satisfier
+        .provides()
+        .iter()
+        .find(|p| p.name() == dep.name())
+        .map(dep_string)
+}
+
+/// Extra edge kinds `dependency-graph` can add on top of hard depends.
+#[derive(Default)]
+pub struct GraphEdges {
+    pub optdepends: bool,
+    pub makedepends: bool,
+}
+
+impl GraphEdges {
+    pub fn parse(list: Option<&str>) -> Result<Self> {
+        let mut edges = GraphEdges::default();
+        for kind in list.unwrap_or("").split(',').filter(|s| !s.is_empty()) {
+            match kind {
+                "optdepends" => edges.optdepends = true,
+                "makedepends" => edges.makedepends = true,
+                other => anyhow::bail!(
+                    "Invalid edge type '{}': expected optdepends or makedepends",
+                    other
+                ),
+            }
+        }
+        Ok(edges)
+    }
+}
+
+struct GraphBuilder<'h> {
+    handle: &'h Alpm,
+    repo_map: std::sync::Arc<crate::db::RepoMap>,
+    graph: DependencyGraphResponse,
+    index: HashMap<String, usize>,
+    edge_set: HashSet<(String, String, &'static str)>,
+    queue: VecDeque<String>,
+}
+
+impl<'h> GraphBuilder<'h> {
+    fn node(&mut self, pkg: &Package, installed: bool) {
+        if self.index.contains_key(pkg.name()) {
+            return;
+        }
+        // Installed nodes always come from the local db, which has the reason.
+        let reason = installed.then(|| reason_to_string(pkg.reason()).to_string());
+        self.index
+            .insert(pkg.name().to_string(), self.graph.nodes.len());
+        self.graph.nodes.push(GraphPackage {
+            name: pkg.name().to_string(),
+            version: pkg.version().to_string(),
+            installed,
+            reason,
+            repository: self.repo_map.get(pkg.name()).map(|s| s.to_string()),
+        });
+        if installed {
+            self.queue.push_back(pkg.name().to_string());
+        }
+    }
+
+    /// Resolve `dep` to the concrete package satisfying it, version
+    /// constraint and provides included. Hard depends must be met by an
+    /// installed package; optional and build edges may point into the sync dbs.
+    fn edge(&mut self, source: &str, dep: &Dep, edge_type: &'static str) {
+        let wanted = dep_string(dep);
+        let local = self.handle.localdb().pkgs().find_satisfier(wanted.as_str());
+        let satisfier = match local {
+            Some(pkg) => Some((pkg, true)),
+            None if edge_type != "depends" => self
+                .handle
+                .syncdbs()
+                .find_satisfier(wanted.as_str())
+                .map(|pkg| (pkg, false)),
+            None => None,
+        };
+        let Some((pkg, installed)) = satisfier else {
+            self.graph.unresolved.push(UnresolvedDependency {
+                package: source.to_string(),
+                dependency: wanted,
+                edge_type: edge_type.to_string(),
+            });
+            return;
+        };
+
+        self.node(pkg, installed);
+        let key = (source.to_string(), pkg.name().to_string(), edge_type);
+        if self.edge_set.insert(key) {
+            self.graph.edges.push(GraphDependency {
+                source: source.to_string(),
+                target: pkg.name().to_string(),
+                edge_type: edge_type.to_string(),
+                dependency: wanted,
+                via_provides: matched_provide(dep, pkg),
+            });
+        }
+    }
+}
+
+/// Every installed package (or everything reachable from `root`) with each
+/// dependency resolved to the package that satisfies it. Unlike
+/// `dependency-tree` there is no node cap.
+fn build_graph(
+    handle: &Alpm,
+    root: Option<&str>,
+    extra: &GraphEdges,
+) -> Result<DependencyGraphResponse> {
+    let localdb = handle.localdb();
+    let mut builder = GraphBuilder {
+        handle,
+        repo_map: get_repo_map(handle),
+        graph: DependencyGraphResponse {
+            root: root.map(|r| r.to_string()),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            cycles: Vec::new(),
+            unresolved: Vec::new(),
+        },
+        index: HashMap::new(),
+        edge_set: HashSet::new(),
+        queue: VecDeque::new(),
+    };
+
+    match root {
+        Some(name) => {
+            let pkg = localdb
+                .pkg(name)
+                .map_err(|_| anyhow::anyhow!("Package '{}' is not installed", name))?;
+            builder.node(pkg, true);
+        }
+        None => {
+            for pkg in localdb.pkgs() {
+                builder.node(pkg, true);
+            }
+        }
+    }
+
+    while let Some(name) = builder.queue.pop_front() {
+        let Ok(pkg) = localdb.pkg(name.as_str()) else {
+            continue;
+        };
+        for dep in pkg.depends() {
+            builder.edge(&name, dep, "depends");
+        }
+        if extra.optdepends {
+            for dep in pkg.optdepends() {
+                builder.edge(&name, dep, "optdepends");
+            }
+        }
+        if extra.makedepends {
+            // The local db does not record makedepends; the sync db does.
+            let sync = handle
+                .syncdbs()
+                .iter()
+                .find_map(|db| db.pkg(name.as_str()).ok());
+            for dep in sync.iter().flat_map(|p| p.makedepends()) {
+                builder.edge(&name, dep, "makedepends");
+            }
+        }
+    }
+
+    let mut graph = builder.graph;
+    let index = builder.index;
+    let hard: Vec<(usize, usize)> = graph
+        .edges
+        .iter()
+        .filter(|e| e.edge_type == "depends")
+        .map(|e| (index[&e.source], index[&e.target]))
+        .collect();
+    graph.cycles = dependency_cycles(graph.nodes.len(), &hard)
+        .into_iter()
+        .map(|c| c.into_iter().map(|i| graph.nodes[i].name.clone()).collect())
+        .collect();
+    Ok(graph)
+}
+
+/// Strongly connected components with more than one member, or a package
+/// depending on itself, by an iterative Tarjan so deep chains cannot
+/// overflow the stack.
+pub(crate) fn dependency_cycles(n: usize, edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for &(a, b) in edges {
+        adj[a].push(b);
+    }
+
+    let mut order = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut next = 0;
+    let mut cycles = Vec::new();
+
+    for start in 0..n {
+        if order[start] != UNVISITED {
+            continue;
+        }
+        let mut work: Vec<(usize, usize)> = vec![(start, 0)];
+        while let Some(&(v, child)) = work.last() {
+            if order[v] == UNVISITED {
+                order[v] = next;
+                low[v] = next;
+                next += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+            if let Some(&w) = adj[v].get(child) {
+                if let Some(top) = work.last_mut() {
+                    top.1 += 1;
+                }
+                if order[w] == UNVISITED {
+                    work.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(order[w]);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == order[v] {
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                if component.len() > 1 || adj[v].contains(&v) {
+                    component.sort_unstable();
+                    cycles.push(component);
+                }
+            }
+        }
+    }
+    cycles.sort();
+    cycles
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub(crate) fn format_dot(graph: &DependencyGraphResponse) -> String {
+    let mut out = String::from("digraph dependencies {\n");
+    for node in &graph.nodes {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\\n{}\"{}];\n",
+            dot_escape(&node.name),
+            dot_escape(&node.name),
+            dot_escape(&node.version),
+            if node.installed { "" } else { ", style=dashed" }
+        ));
+    }
+    for edge in &graph.edges {
+        let style = match edge.edge_type.as_str() {
+            "optdepends" => " [style=dashed]",
+            "makedepends" => " [style=dotted]",
+            _ => "",
+        };
+        out.push_str(&format!(
+            "  \"{}\" -> \"{}\"{};\n",
+            dot_escape(&edge.source),
+            dot_escape(&edge.target),
+            style
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn format_graphml(graph: &DependencyGraphResponse) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"version\" for=\"node\" attr.name=\"version\" attr.type=\"string\"/>\n",
+        "  <key id=\"installed\" for=\"node\" attr.name=\"installed\" attr.type=\"boolean\"/>\n",
+        "  <key id=\"reason\" for=\"node\" attr.name=\"reason\" attr.type=\"string\"/>\n",
+        "  <key id=\"repository\" for=\"node\" attr.name=\"repository\" attr.type=\"string\"/>\n",
+        "  <key id=\"type\" for=\"edge\" attr.name=\"type\" attr.type=\"string\"/>\n",
+        "  <key id=\"dependency\" for=\"edge\" attr.name=\"dependency\" attr.type=\"string\"/>\n",
+        "  <key id=\"provides\" for=\"edge\" attr.name=\"provides\" attr.type=\"string\"/>\n",
+        "  <graph id=\"dependencies\" edgedefault=\"directed\">\n",
+    ));
+    let data = |out: &mut String, key: &str, value: &str| {
+        out.push_str(&format!(
+            "      <data key=\"{}\">{}</data>\n",
+            key,
+            xml_escape(value)
+        ));
+    };
+    for node in &graph.nodes {
+        out.push_str(&format!("    <node id=\"{}\">\n", xml_escape(&node.name)));
+        data(&mut out, "version", &node.version);
+        data(
+            &mut out,
+            "installed",
+            if node.installed { "true" } else { "false" },
+        );
+        if let Some(reason) = &node.reason {
+            data(&mut out, "reason", reason);
+        }
+        if let Some(repo) = &node.repository {
+            data(&mut out, "repository", repo);
+        }
+        out.push_str("    </node>\n");
+    }
+    for edge in &graph.edges {
+        out.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\">\n",
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        ));
+        data(&mut out, "type", &edge.edge_type);
+        data(&mut out, "dependency", &edge.dependency);
+        if let Some(provide) = &edge.via_provides {
+            data(&mut out, "provides", provide);
+        }
+        out.push_str("    </edge>\n");
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+pub fn dependency_graph(
+    format: Option<&str>,
+    edges: Option<&str>,
+    root: Option<&str>,
+) -> Result<()> {
+    let format = format.unwrap_or("json");
+    if !matches!(format, "json" | "dot" | "graphml") {
+        anyhow::bail!(
+            "Invalid graph format '{}': expected json, dot or graphml",
+            format
+        );
+    }
+    let extra = GraphEdges::parse(edges)?;
+    let handle = get_handle()?;
+    let graph = build_graph(&handle, root, &extra)?;
+
+    let text = match format {
+        "dot" => format_dot(&graph),
+        "graphml" => format_graphml(&graph),
+        _ => return emit_json(&graph),
+    };
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, installed: bool) -> GraphPackage {
+        GraphPackage {
+            name: name.to_string(),
+            version: "1-1".to_string(),
+            installed,
+            reason: None,
+            repository: None,
+        }
+    }
+
+    fn sample() -> DependencyGraphResponse {
+        DependencyGraphResponse {
+            root: None,
+            nodes: vec![
+                package("app", true),
+                package("lib\"x", true),
+                package("gcc", false),
+            ],
+            edges: vec![
+                GraphDependency {
+                    source: "app".to_string(),
+                    target: "lib\"x".to_string(),
+                    edge_type: "depends".to_string(),
+                    dependency: "libx.so>=2".to_string(),
+                    via_provides: Some("libx.so=2-64".to_string()),
+                },
+                GraphDependency {
+                    source: "app".to_string(),
+                    target: "gcc".to_string(),
+                    edge_type: "makedepends".to_string(),
+                    dependency: "gcc".to_string(),
+                    via_provides: None,
+                },
+            ],
+            cycles: Vec::new(),
+            unresolved: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn finds_cycles_and_self_loops() {
+        // 0 -> 1 -> 2 -> 0, 3 -> 3, 4 -> 0
+        let edges = [(0, 1), (1, 2), (2, 0), (3, 3), (4, 0)];
+        assert_eq!(dependency_cycles(5, &edges), vec![vec![0, 1, 2], vec![3]]);
+        assert!(dependency_cycles(3, &[(0, 1), (1, 2), (0, 2)]).is_empty());
+    }
+
+    #[test]
+    fn long_chains_do_not_recurse() {
+        let n = 100_000;
+        let mut edges: Vec<(usize, usize)> = (0..n - 1).map(|i| (i, i + 1)).collect();
+        edges.push((n - 1, 0));
+        assert_eq!(dependency_cycles(n, &edges)[0].len(), n);
+    }
+
+    #[test]
+    fn dot_escapes_and_styles_edges() {
+        let dot = format_dot(&sample());
+        assert!(dot.starts_with("digraph dependencies {\n"));
+        assert!(dot.contains("  \"lib\\\"x\" [label=\"lib\\\"x\\n1-1\"];\n"));
+        assert!(dot.contains("  \"gcc\" [label=\"gcc\\n1-1\", style=dashed];\n"));
+        assert!(dot.contains("  \"app\" -> \"gcc\" [style=dotted];\n"));
+    }
+
+    #[test]
+    fn graphml_escapes_attributes() {
+        let xml = format_graphml(&sample());
+        assert!(xml.contains("<node id=\"lib&quot;x\">"));
+        assert!(xml.contains("<data key=\"dependency\">libx.so&gt;=2</data>"));
+        assert!(xml.contains("<data key=\"provides\">libx.so=2-64</data>"));
+        assert!(xml.ends_with("</graphml>\n"));
+    }
+
+    #[test]
+    fn edge_list_is_validated() {
+        let edges = GraphEdges::parse(Some("optdepends,makedepends")).unwrap();
+        assert!(edges.optdepends && edges.makedepends);
+        assert!(!GraphEdges::parse(None).unwrap().optdepends);
+        assert!(GraphEdges::parse(Some("checkdepends")).is_err());
+    }
+}
@@ -0,0 +1,406 @@
+use alpm::{Alpm, PackageReason};
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+
+use crate::alpm::{dep_string, get_handle};
+use crate::config::AppConfig;
+use crate::models::{
+    FootprintDependency, FootprintSummary, LargestFootprintsResponse, PackageFootprint,
+};
+use crate::util::emit_json;
+
+pub(crate) struct LocalNode {
+    pub name: String,
+    pub version: String,
+    pub size: i64,
+    pub explicit: bool,
+    /// Indices of the installed packages satisfying each dependency.
+    pub deps: Vec<usize>,
+    /// Indices of the installed packages satisfying each optdepend.
+    pub optdeps: Vec<usize>,
+}
+
+/// The local db as an index-based dependency graph, with every depends and
+/// optdepends entry resolved to the installed package that satisfies it
+/// (through provides where needed).
+pub(crate) struct LocalGraph {
+    pub nodes: Vec<LocalNode>,
+    pub index: HashMap<String, usize>,
+}
+
+impl LocalGraph {
+    pub fn from_handle(handle: &Alpm) -> Self {
+        let pkgs = handle.localdb().pkgs();
+        let index: HashMap<String, usize> = pkgs
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.name().to_string(), i))
+            .collect();
+        let resolve = |dep: &alpm::Dep| {
+            pkgs.find_satisfier(dep_string(dep).as_str())
+                .and_then(|p| index.get(p.name()).copied())
+        };
+
+        let nodes = pkgs
+            .iter()
+            .map(|pkg| LocalNode {
+                name: pkg.name().to_string(),
+                version: pkg.version().to_string(),
+                size: pkg.isize(),
+                explicit: pkg.reason() == PackageReason::Explicit,
+                deps: pkg.depends().iter().filter_map(resolve).collect(),
+                optdeps: pkg.optdepends().iter().filter_map(resolve).collect(),
+            })
+            .collect();
+
+        LocalGraph { nodes, index }
+    }
+
+    /// Mark everything reachable from `starts`, never entering `blocked`.
+    pub fn reachable(
+        &self,
+        starts: impl IntoIterator<Item = usize>,
+        blocked: Option<usize>,
+        include_optional: bool,
+    ) -> Vec<bool> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = starts.into_iter().filter(|&i| Some(i) != blocked).collect();
+        while let Some(i) = stack.pop() {
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+            let node = &self.nodes[i];
+            let optional = if include_optional {
+                node.optdeps.as_slice()
+            } else {
+                &[]
+            };
+            stack.extend(
+                node.deps
+                    .iter()
+                    .chain(optional)
+                    .filter(|&&d| !seen[d] && Some(d) != blocked),
+            );
+        }
+        seen
+    }
+
+    /// Dependencies that would be left unneeded if `root` were removed: those
+    /// reachable from it but from no other explicit package. Reachability
+    /// rather than pairwise required-by checks, so dependency cycles that only
+    /// `root` holds up are counted too. Optdepends keep a package alive, as
+    /// they do for `list-orphans`.
+    pub fn exclusive_closure(&self, root: usize) -> Vec<usize> {
+        let from_root = self.reachable([root], None, true);
+        let kept = self.reachable(
+            (0..self.nodes.len()).filter(|&i| self.nodes[i].explicit && i != root),
+            Some(root),
+            true,
+        );
+        (0..self.nodes.len())
+            .filter(|&i| i != root && from_root[i] && !kept[i] && !self.nodes[i].explicit)
+            .collect()
+    }
+
+    /// Dependency-installed packages unreachable from every explicit package
+    /// and every package in `keep`: plain orphans, orphans of orphans, and
+    /// clusters that only require each other. With `include_optional`, being
+    /// an optdepend of something needed is enough to stay.
+    pub fn orphans(&self, keep: &[usize], include_optional: bool) -> Vec<usize> {
+        let roots = (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].explicit)
+            .chain(keep.iter().copied());
+        let needed = self.reachable(roots, None, include_optional);
+        (0..self.nodes.len()).filter(|&i| !needed[i]).collect()
+    }
+
+    /// For every node, the next package on a shortest dependency path down
+    /// to `target`, found by a breadth-first walk over reverse edges. `None`
+    /// where no path exists, and for `target` itself.
+    pub fn next_hops_to(&self, target: usize, include_optional: bool) -> Vec<Option<usize>> {
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            let optional = if include_optional {
+                node.optdeps.as_slice()
+            } else {
+                &[]
+            };
+            for &d in node.deps.iter().chain(optional) {
+                dependents[d].push(i);
+            }
+        }
+
+        let mut next = vec![None; self.nodes.len()];
+        let mut seen = vec![false; self.nodes.len()];
+        seen[target] = true;
+        let mut queue = VecDeque::from([target]);
+        while let Some(j) = queue.pop_front() {
+            for &i in &dependents[j] {
+                if !seen[i] {
+                    seen[i] = true;
+                    next[i] = Some(j);
+                    queue.push_back(i);
+                }
+            }
+        }
+        next
+    }
+
+    /// Installed packages outside the removal set that hard-depend on
+    /// something in it.
+    pub fn broken_by(&self, removed: &[usize]) -> Vec<usize> {
+        let mut is_removed = vec![false; self.nodes.len()];
+        for &i in removed {
+            is_removed[i] = true;
+        }
+        (0..self.nodes.len())
+            .filter(|&i| !is_removed[i] && self.nodes[i].deps.iter().any(|&d| is_removed[d]))
+            .collect()
+    }
+}
+
+/// Names of the orphaned packages, honouring the `orphan_keep` list from the
+/// app config. A config that fails to load is an error rather than an empty
+/// keep list, so removal never takes a package the user asked to retain.
+pub(crate) fn find_orphans(handle: &Alpm, include_optional: bool) -> Result<Vec<String>> {
+    let config = AppConfig::load()?;
+    let graph = LocalGraph::from_handle(handle);
+    let keep: Vec<usize> = config
+        .orphan_keep
+        .iter()
+        .filter_map(|name| graph.index.get(name).copied())
+        .collect();
+    Ok(graph
+        .orphans(&keep, include_optional)
+        .into_iter()
+        .map(|i| graph.nodes[i].name.clone())
+        .collect())
+}
+
+fn footprint_of(graph: &LocalGraph, root: usize) -> PackageFootprint {
+    let closure = graph.exclusive_closure(root);
+    let removed: Vec<usize> = std::iter::once(root)
+        .chain(closure.iter().copied())
+        .collect();
+
+    let mut exclusive_dependencies: Vec<FootprintDependency> = closure
+        .iter()
+        .map(|&i| FootprintDependency {
+            name: graph.nodes[i].name.clone(),
+            installed_size: graph.nodes[i].size,
+        })
+        .collect();
+    exclusive_dependencies.sort_by_key(|d| std::cmp::Reverse(d.installed_size));
+
+    let mut breaks: Vec<String> = graph
+        .broken_by(&removed)
+        .into_iter()
+        .map(|i| graph.nodes[i].name.clone())
+        .collect();
+    breaks.sort();
+
+    let node = &graph.nodes[root];
+    PackageFootprint {
+        name: node.name.clone(),
+        version: node.version.clone(),
+        installed_size: node.size,
+        reclaimable_size: removed.iter().map(|&i| graph.nodes[i].size).sum(),
+        exclusive_dependencies,
+        breaks,
+    }
+}
+
+pub fn package_footprint(name: &str) -> Result<()> {
+    let handle = get_handle()?;
+    let graph = LocalGraph::from_handle(&handle);
+    let root = *graph
+        .index
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Package '{}' not found", name))?;
+    emit_json(&footprint_of(&graph, root))
+}
+
+pub fn largest_footprints(limit: usize) -> Result<()> {
+    let handle = get_handle()?;
+    let graph = LocalGraph::from_handle(&handle);
+
+    let mut packages: Vec<FootprintSummary> = (0..graph.nodes.len())
+        .filter(|&i| graph.nodes[i].explicit)
+        .map(|i| {
+            let closure = graph.exclusive_closure(i);
+            let node = &graph.nodes[i];
+            FootprintSummary {
+                name: node.name.clone(),
+                version: node.version.clone(),
+                installed_size: node.size,
+                reclaimable_size: node.size
+                    + closure.iter().map(|&d| graph.nodes[d].size).sum::<i64>(),
+                exclusive_count: closure.len(),
+            }
+        })
+        .collect();
+
+    packages.sort_by(|a, b| {
+        b.reclaimable_size
+            .cmp(&a.reclaimable_size)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let total = packages.len();
+    packages.truncate(limit);
+
+    emit_json(&LargestFootprintsResponse { packages, total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (name, explicit, size, deps, optdeps)
+    type Spec<'a> = (&'a str, bool, i64, &'a [&'a str], &'a [&'a str]);
+
+    fn graph(specs: &[Spec]) -> LocalGraph {
+        let index: HashMap<String, usize> = specs
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.0.to_string(), i))
+            .collect();
+        let resolve = |names: &[&str]| names.iter().map(|n| index[*n]).collect();
+        let nodes = specs
+            .iter()
+            .map(|&(name, explicit, size, deps, optdeps)| LocalNode {
+                name: name.to_string(),
+                version: "1-1".to_string(),
+                size,
+                explicit,
+                deps: resolve(deps),
+                optdeps: resolve(optdeps),
+            })
+            .collect();
+        LocalGraph { nodes, index }
+    }
+
+    fn names(g: &LocalGraph, idx: &[usize]) -> Vec<String> {
+        let mut v: Vec<String> = idx.iter().map(|&i| g.nodes[i].name.clone()).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn shared_dependencies_are_not_exclusive() {
+        let g = graph(&[
+            ("gimp", true, 100, &["gegl", "glib2"], &[]),
+            ("gegl", false, 50, &["glib2"], &[]),
+            ("glib2", false, 10, &[], &[]),
+            ("nautilus", true, 30, &["glib2"], &[]),
+        ]);
+        let fp = footprint_of(&g, 0);
+        assert_eq!(fp.reclaimable_size, 150);
+        assert_eq!(
+            fp.exclusive_dependencies
+                .iter()
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["gegl"]
+        );
+        assert!(fp.breaks.is_empty());
+    }
+
+    #[test]
+    fn dependency_cycles_held_only_by_root_are_exclusive() {
+        let g = graph(&[
+            ("app", true, 1, &["a"], &[]),
+            ("a", false, 2, &["b"], &[]),
+            ("b", false, 4, &["a"], &[]),
+        ]);
+        assert_eq!(names(&g, &g.exclusive_closure(0)), vec!["a", "b"]);
+        assert_eq!(footprint_of(&g, 0).reclaimable_size, 7);
+    }
+
+    #[test]
+    fn explicit_dependencies_stay() {
+        let g = graph(&[
+            ("app", true, 1, &["python"], &[]),
+            ("python", true, 80, &[], &[]),
+        ]);
+        assert!(g.exclusive_closure(0).is_empty());
+    }
+
+    #[test]
+    fn optdepends_of_others_keep_a_package() {
+        let g = graph(&[
+            ("app", true, 1, &["libfoo"], &[]),
+            ("libfoo", false, 5, &[], &[]),
+            ("viewer", true, 1, &[], &["libfoo"]),
+        ]);
+        assert!(g.exclusive_closure(0).is_empty());
+        // The root's own optdepends go with it.
+        let g = graph(&[
+            ("app", true, 1, &[], &["plugin"]),
+            ("plugin", false, 3, &[], &[]),
+        ]);
+        assert_eq!(names(&g, &g.exclusive_closure(0)), vec!["plugin"]);
+    }
+
+    #[test]
+    fn reports_reverse_dependencies_that_break() {
+        let g = graph(&[
+            ("qt6-base", false, 40, &[], &[]),
+            ("app", true, 1, &["qt6-base"], &[]),
+            ("lib", true, 5, &[], &[]),
+            ("tool", true, 1, &["lib"], &[]),
+        ]);
+        assert_eq!(footprint_of(&g, 2).breaks, vec!["tool"]);
+        assert!(footprint_of(&g, 1).breaks.is_empty());
+    }
+
+    #[test]
+    fn next_hops_follow_shortest_paths() {
+        let g = graph(&[
+            ("app", true, 1, &["a", "lib"], &[]),
+            ("a", false, 1, &["b"], &[]),
+            ("b", false, 1, &["lib"], &[]),
+            ("lib", false, 1, &[], &[]),
+            ("viewer", true, 1, &[], &["lib"]),
+        ]);
+        let hard = g.next_hops_to(3, false);
+        assert_eq!(hard[0], Some(3));
+        assert_eq!(hard[1], Some(2));
+        assert_eq!(hard[3], None);
+        assert_eq!(hard[4], None);
+        assert_eq!(g.next_hops_to(3, true)[4], Some(3));
+    }
+
+    #[test]
+    fn orphans_include_chains_and_cycles() {
+        let g = graph(&[
+            ("app", true, 1, &["lib"], &[]),
+            ("lib", false, 1, &[], &[]),
+            ("old", false, 1, &["older"], &[]),
+            ("older", false, 1, &[], &[]),
+            ("a", false, 1, &["b"], &[]),
+            ("b", false, 1, &["a"], &[]),
+        ]);
+        assert_eq!(
+            names(&g, &g.orphans(&[], true)),
+            vec!["a", "b", "old", "older"]
+        );
+    }
+
+    #[test]
+    fn keep_list_and_optdepends_hold_packages() {
+        let g = graph(&[
+            ("viewer", true, 1, &[], &["codec"]),
+            ("codec", false, 1, &["libcodec"], &[]),
+            ("libcodec", false, 1, &[], &[]),
+            ("tool", false, 1, &["libtool"], &[]),
+            ("libtool", false, 1, &[], &[]),
+        ]);
+        assert_eq!(names(&g, &g.orphans(&[3], true)), Vec::<String>::new());
+        assert_eq!(
+            names(&g, &g.orphans(&[3], false)),
+            vec!["codec", "libcodec"]
+        );
+        assert_eq!(names(&g, &g.orphans(&[], true)), vec!["libtool", "tool"]);
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::alpm::get_handle;
+use crate::config::AppConfig;
+use crate::models::{
+    GroupInfoResponse, GroupListResponse, GroupMember, GroupRepository, GroupSummary,
+};
+use crate::util::emit_json;
+
+pub fn list_groups() -> Result<()> {
+    let handle = get_handle()?;
+    let localdb = handle.localdb();
+
+    // Group name -> (repos it appears in, member names across all repos)
+    let mut groups: BTreeMap<String, (Vec<String>, BTreeSet<String>)> = BTreeMap::new();
+    for syncdb in handle.syncdbs() {
+        let Ok(db_groups) = syncdb.groups() else {
+            continue;
+        };
+        for group in db_groups {
+            let entry = groups.entry(group.name().to_string()).or_default();
+            entry.0.push(syncdb.name().to_string());
+            entry
+                .1
+                .extend(group.packages().iter().map(|p| p.name().to_string()));
+        }
+    }
+
+    let groups: Vec<GroupSummary> = groups
+        .into_iter()
+        .map(|(name, (repositories, members))| GroupSummary {
+            name,
+            repositories,
+            total: members.len(),
+            installed: members
+                .iter()
+                .filter(|m| localdb.pkg(m.as_str()).is_ok())
+                .count(),
+        })
+        .collect();
+
+    let response = GroupListResponse {
+        total: groups.len(),
+        groups,
+    };
+    emit_json(&response)
+}
+
+pub fn group_info(name: &str) -> Result<()> {
+    let handle = get_handle()?;
+    let localdb = handle.localdb();
+    let config = AppConfig::load().unwrap_or_default();
+
+    let mut repositories = Vec::new();
+    let mut all_members = BTreeSet::new();
+    let mut installed_members = BTreeSet::new();
+
+    for syncdb in handle.syncdbs() {
+        let Ok(group) = syncdb.group(name) else {
+            continue;
+        };
+        let mut members: Vec<GroupMember> = group
+            .packages()
+            .iter()
+            .map(|pkg| {
+                let local_pkg = localdb.pkg(pkg.name()).ok();
+                all_members.insert(pkg.name().to_string());
+                if local_pkg.is_some() {
+                    installed_members.insert(pkg.name().to_string());
+                }
+                GroupMember {
+                    name: pkg.name().to_string(),
+                    version: pkg.version().to_string(),
+                    description: pkg.desc().map(|s| s.to_string()),
+                    installed: local_pkg.is_some(),
+                    installed_version: local_pkg.map(|p| p.version().to_string()),
+                }
+            })
+            .collect();
+        members.sort_by(|a, b| a.name.cmp(&b.name));
+        repositories.push(GroupRepository {
+            repository: syncdb.name().to_string(),
+            members,
+        });
+    }
+
+    if repositories.is_empty() {
+        anyhow::bail!("Group '{}' not found in sync databases", name);
+    }
+
+    let ignored = config.ignored_groups.iter().any(|g| g == name)
+        || handle.ignoregroups().iter().any(|g| g == name);
+
+    let response = GroupInfoResponse {
+        name: name.to_string(),
+        repositories,
+        total: all_members.len(),
+        total_installed: installed_members.len(),
+        ignored,
+    };
+    emit_json(&response)
+}
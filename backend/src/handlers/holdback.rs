@@ -0,0 +1,251 @@
+use alpm::Alpm;
+use std::collections::HashMap;
+
+use crate::alpm::dep_string;
+use crate::config::PolicyAction;
+use crate::models::UpdateInfo;
+
+const SECS_PER_DAY: i64 = 86_400;
+
+/// An available update as seen by the minimum-age policy.
+pub(crate) struct AgeCandidate {
+    pub name: String,
+    pub version: String,
+    pub pkgbase: Option<String>,
+    pub build_date: i64,
+    /// Packages with an `auto` policy never count as too young themselves.
+    pub exempt: bool,
+    /// Indices of other candidates the new version depends on.
+    pub depends: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct HeldUpdate {
+    pub name: String,
+    pub version: String,
+    /// Whole days since the build, for packages held for their own age.
+    pub age_days: Option<i64>,
+    /// The too-young update this one is held together with.
+    pub held_with: Option<String>,
+}
+
+impl HeldUpdate {
+    pub fn detail(&self, min_age_days: u32) -> String {
+        match &self.held_with {
+            Some(with) => format!(
+                "Held back: {} {} (held with {})",
+                self.name, self.version, with
+            ),
+            None => format!(
+                "Held back: {} {} is {} day(s) old (minimum {})",
+                self.name,
+                self.version,
+                self.age_days.unwrap_or(0),
+                min_age_days
+            ),
+        }
+    }
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut cur = i;
+    while parent[cur] != root {
+        let next = parent[cur];
+        parent[cur] = root;
+        cur = next;
+    }
+    root
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (ra, rb) = (find(parent, a), find(parent, b));
+    if ra != rb {
+        parent[rb] = ra;
+    }
+}
+
+/// Group id for each candidate. Updates sharing a pkgbase or linked by a
+/// dependency either way land in the same group and must move together for
+/// the upgrade to not be a partial one.
+pub(crate) fn update_groups(candidates: &[AgeCandidate]) -> Vec<usize> {
+    let mut parent: Vec<usize> = (0..candidates.len()).collect();
+    let mut by_base: HashMap<&str, usize> = HashMap::new();
+    for (i, c) in candidates.iter().enumerate() {
+        if let Some(base) = c.pkgbase.as_deref() {
+            match by_base.get(base) {
+                Some(&first) => union(&mut parent, first, i),
+                None => {
+                    by_base.insert(base, i);
+                }
+            }
+        }
+        for &d in &c.depends {
+            union(&mut parent, i, d);
+        }
+    }
+    (0..candidates.len())
+        .map(|i| find(&mut parent, i))
+        .collect()
+}
+
+/// Updates to hold back because their build is younger than `min_age_days`.
+/// A single young package holds its whole group (see `update_groups`), so the
+/// upgrade that goes ahead is never a partial one.
+pub(crate) fn age_holdbacks(
+    candidates: &[AgeCandidate],
+    now: i64,
+    min_age_days: u32,
+) -> Vec<HeldUpdate> {
+    if min_age_days == 0 {
+        return Vec::new();
+    }
+    let min_age = i64::from(min_age_days) * SECS_PER_DAY;
+    let groups = update_groups(candidates);
+
+    let young: Vec<bool> = candidates
+        .iter()
+        .map(|c| !c.exempt && now.saturating_sub(c.build_date) < min_age)
+        .collect();
+    // First young member of each group, named as the reason for the others.
+    let mut cause: HashMap<usize, usize> = HashMap::new();
+    for i in (0..candidates.len()).filter(|&i| young[i]) {
+        cause.entry(groups[i]).or_insert(i);
+    }
+
+    (0..candidates.len())
+        .filter_map(|i| {
+            let &by = cause.get(&groups[i])?;
+            let c = &candidates[i];
+            Some(HeldUpdate {
+                name: c.name.clone(),
+                version: c.version.clone(),
+                age_days: young[i].then(|| now.saturating_sub(c.build_date) / SECS_PER_DAY),
+                held_with: (!young[i]).then(|| candidates[by].name.clone()),
+            })
+        })
+        .collect()
+}
+
+/// Age candidates for `updates`, read from the sync dbs. A dependency links
+/// two updates when the sync package satisfying it is itself being updated.
+pub(crate) fn age_candidates(handle: &Alpm, updates: &[UpdateInfo]) -> Vec<AgeCandidate> {
+    let index: HashMap<&str, usize> = updates
+        .iter()
+        .enumerate()
+        .map(|(i, u)| (u.name.as_str(), i))
+        .collect();
+    let syncdbs = handle.syncdbs();
+
+    updates
+        .iter()
+        .map(|update| {
+            let pkg = syncdbs
+                .iter()
+                .find(|db| db.name() == update.repository)
+                .and_then(|db| db.pkg(update.name.as_str()).ok());
+            let depends = pkg
+                .map(|p| {
+                    p.depends()
+                        .iter()
+                        .filter_map(|dep| syncdbs.find_satisfier(dep_string(dep).as_str()))
+                        .filter_map(|s| index.get(s.name()).copied())
+                        .collect()
+                })
+                .unwrap_or_default();
+            AgeCandidate {
+                name: update.name.clone(),
+                version: update.new_version.clone(),
+                pkgbase: pkg.and_then(|p| p.base()).map(|b| b.to_string()),
+                build_date: pkg.map(|p| p.build_date()).unwrap_or(0),
+                exempt: update.policy == Some(PolicyAction::Auto),
+                depends,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_750_000_000;
+
+    fn candidate(name: &str, base: Option<&str>, age_days: i64, depends: &[usize]) -> AgeCandidate {
+        AgeCandidate {
+            name: name.to_string(),
+            version: "2-1".to_string(),
+            pkgbase: base.map(|b| b.to_string()),
+            build_date: NOW - age_days * SECS_PER_DAY - 60,
+            exempt: false,
+            depends: depends.to_vec(),
+        }
+    }
+
+    fn held_names(held: &[HeldUpdate]) -> Vec<&str> {
+        held.iter().map(|h| h.name.as_str()).collect()
+    }
+
+    #[test]
+    fn only_young_packages_and_their_groups_are_held() {
+        let candidates = [
+            candidate("llvm-libs", Some("llvm"), 0, &[]),
+            candidate("llvm", Some("llvm"), 5, &[0]),
+            candidate("mesa", Some("mesa"), 5, &[0]),
+            candidate("bash", Some("bash"), 5, &[]),
+        ];
+        let held = age_holdbacks(&candidates, NOW, 2);
+        assert_eq!(held_names(&held), vec!["llvm-libs", "llvm", "mesa"]);
+        assert_eq!(held[0].age_days, Some(0));
+        assert_eq!(held[0].held_with, None);
+        assert_eq!(held[2].held_with.as_deref(), Some("llvm-libs"));
+    }
+
+    #[test]
+    fn dependencies_of_young_packages_are_held_too() {
+        // The young package depends on an old one: upgrading only the
+        // dependency could break the installed version of the young one.
+        let candidates = [
+            candidate("app", None, 1, &[1]),
+            candidate("lib", None, 9, &[]),
+        ];
+        assert_eq!(
+            held_names(&age_holdbacks(&candidates, NOW, 2)),
+            vec!["app", "lib"]
+        );
+    }
+
+    #[test]
+    fn auto_policy_exempts_from_the_minimum_age() {
+        let mut candidates = [candidate("firefox", None, 0, &[])];
+        candidates[0].exempt = true;
+        assert!(age_holdbacks(&candidates, NOW, 2).is_empty());
+    }
+
+    #[test]
+    fn zero_minimum_holds_nothing() {
+        let candidates = [candidate("app", None, 0, &[])];
+        assert!(age_holdbacks(&candidates, NOW, 0).is_empty());
+        assert_eq!(age_holdbacks(&candidates, NOW, 1).len(), 1);
+    }
+
+    #[test]
+    fn details_name_the_cause() {
+        let held = age_holdbacks(
+            &[
+                candidate("a", Some("x"), 1, &[]),
+                candidate("b", Some("x"), 3, &[]),
+            ],
+            NOW,
+            2,
+        );
+        assert_eq!(
+            held[0].detail(2),
+            "Held back: a 2-1 is 1 day(s) old (minimum 2)"
+        );
+        assert_eq!(held[1].detail(2), "Held back: b 2-1 (held with a)");
+    }
+}
@@ -0,0 +1,140 @@
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::config::HookCommand;
+use crate::util::output_with_input_timeout;
+
+/// Output lines kept per hook in a run's details; earlier ones are dropped.
+const HOOK_OUTPUT_LINES: usize = 20;
+
+/// Run `hooks` in order with `env` added to their environment, returning
+/// whether all of them succeeded and a detail block for each one run. With
+/// `stop_on_failure` the first failing hook ends the sequence.
+pub(crate) fn run_hooks(
+    stage: &str,
+    hooks: &[HookCommand],
+    env: &[(&str, String)],
+    stop_on_failure: bool,
+) -> (bool, Vec<String>) {
+    let mut all_ok = true;
+    let mut details = Vec::new();
+    for hook in hooks {
+        let ok = run_hook(stage, hook, env, &mut details);
+        all_ok &= ok;
+        if !ok && stop_on_failure {
+            break;
+        }
+    }
+    (all_ok, details)
+}
+
+fn run_hook(
+    stage: &str,
+    hook: &HookCommand,
+    env: &[(&str, String)],
+    details: &mut Vec<String>,
+) -> bool {
+    let label = format!("{} hook {}", stage, hook.argv.join(" "));
+    let Some((program, args)) = hook.argv.split_first() else {
+        details.push(format!("{}: no program given", label));
+        return false;
+    };
+    eprintln!("Running {}", label);
+
+    let mut cmd = Command::new(program);
+    cmd.args(args).stdin(Stdio::null());
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+    let output = match output_with_input_timeout(cmd, None, Duration::from_secs(hook.timeout_secs))
+    {
+        Ok(output) => output,
+        Err(e) => {
+            details.push(format!("{}: {:#}", label, e));
+            return false;
+        }
+    };
+
+    let ok = output.status.success();
+    details.push(if ok {
+        format!("{}: ok", label)
+    } else {
+        format!("{}: {}", label, output.status)
+    });
+    let text = [output.stdout, output.stderr]
+        .iter()
+        .map(|o| String::from_utf8_lossy(o).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n");
+    details.extend(output_lines(&text));
+    ok
+}
+
+/// The last `HOOK_OUTPUT_LINES` non-empty lines, indented under the hook's
+/// status line.
+fn output_lines(text: &str) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let skip = lines.len().saturating_sub(HOOK_OUTPUT_LINES);
+    let mut out = Vec::new();
+    if skip > 0 {
+        out.push(format!("  ({} earlier lines omitted)", skip));
+    }
+    out.extend(lines[skip..].iter().map(|l| format!("  {}", l.trim_end())));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str, timeout_secs: u64) -> HookCommand {
+        HookCommand {
+            argv: vec!["/bin/sh".into(), "-c".into(), script.into()],
+            timeout_secs,
+        }
+    }
+
+    #[test]
+    fn captures_output_and_environment() {
+        let (ok, details) = run_hooks(
+            "Post-run",
+            &[sh("echo status=$COCKPIT_PACMAN_STATUS; echo warn >&2", 10)],
+            &[("COCKPIT_PACMAN_STATUS", "ok".to_string())],
+            false,
+        );
+        assert!(ok);
+        assert!(details[0].starts_with("Post-run hook /bin/sh -c"));
+        assert!(details[0].ends_with(": ok"));
+        assert_eq!(&details[1..], ["  status=ok", "  warn"]);
+    }
+
+    #[test]
+    fn stops_at_first_failure_when_asked() {
+        let hooks = [sh("echo first; exit 3", 10), sh("echo second", 10)];
+
+        let (ok, details) = run_hooks("Pre-run", &hooks, &[], true);
+        assert!(!ok);
+        assert!(details[0].ends_with("exit status: 3"));
+        assert!(!details.iter().any(|d| d.contains("second")));
+
+        let (ok, details) = run_hooks("Post-run", &hooks, &[], false);
+        assert!(!ok);
+        assert!(details.iter().any(|d| d == "  second"));
+    }
+
+    #[test]
+    fn timeout_fails_the_hook() {
+        let (ok, details) = run_hooks("Pre-run", &[sh("sleep 5", 1)], &[], true);
+        assert!(!ok);
+        assert!(details[0].contains("timed out after 1s"));
+    }
+
+    #[test]
+    fn long_output_keeps_the_tail() {
+        let text: String = (1..=25).map(|i| format!("line {}\n\n", i)).collect();
+        let lines = output_lines(&text);
+        assert_eq!(lines.len(), HOOK_OUTPUT_LINES + 1);
+        assert_eq!(lines[0], "  (5 earlier lines omitted)");
+        assert_eq!(lines[HOOK_OUTPUT_LINES], "  line 25");
+    }
+}
@@ -0,0 +1,309 @@
+use alpm::{Pkg, SigLevel, SigList, SigStatus, SigValidity};
+use anyhow::{Context, Result, bail};
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use super::update_diff::{MAX_FILES_LISTED, deps, file_diff, file_names, list_diff};
+use crate::alpm::get_handle;
+use crate::models::{
+    BackupFile, InspectPackageResponse, InstalledComparison, PackageSignatureCheck, SignatureInfo,
+};
+use crate::util::{emit_json, output_with_timeout};
+
+const MAX_DOWNLOAD_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+const MAX_SIGNATURE_BYTES: u64 = 64 * 1024;
+const MAX_SCRIPTLET_BYTES: usize = 64 * 1024;
+const BSDTAR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A downloaded package and its detached signature in a private directory,
+/// removed on drop.
+struct Download {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The last path segment of a package URL, refusing anything that is not a
+/// package archive name.
+pub(crate) fn url_filename(url: &str) -> Result<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let name = percent_encoding::percent_decode_str(name).decode_utf8_lossy();
+    if name.is_empty() || name.contains('/') || name.starts_with('.') || !name.contains(".pkg.tar")
+    {
+        bail!("URL does not point at a package file");
+    }
+    Ok(name.into_owned())
+}
+
+fn fetch_to(agent: &ureq::Agent, url: &str, dest: &Path, limit: u64) -> Result<bool> {
+    let response = match agent.get(url).call() {
+        Ok(r) => r,
+        Err(ureq::Error::StatusCode(404)) => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("GET {} failed", url)),
+    };
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(dest)?;
+    let mut body = response.into_body();
+    let copied = std::io::copy(&mut body.as_reader().take(limit + 1), &mut file)?;
+    if copied > limit {
+        bail!("{} is larger than {} bytes", url, limit);
+    }
+    file.flush()?;
+    Ok(true)
+}
+
+fn download(url: &str) -> Result<Download> {
+    let filename = url_filename(url)?;
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let dir = std::env::temp_dir().join(format!(
+        "cockpit-pacman-inspect-{}-{}",
+        std::process::id(),
+        nanos
+    ));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let download = Download {
+        path: dir.join(&filename),
+        dir,
+    };
+
+    let agent = ureq::Agent::new_with_config(
+        ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(600)))
+            .timeout_connect(Some(Duration::from_secs(10)))
+            .ip_family(crate::util::detected_ip_family())
+            .build(),
+    );
+    if !fetch_to(&agent, url, &download.path, MAX_DOWNLOAD_BYTES)? {
+        bail!("{} not found", url);
+    }
+    // The detached signature is optional; alpm reads it next to the file.
+    let mut sig_path = download.path.clone().into_os_string();
+    sig_path.push(".sig");
+    let _ = fetch_to(
+        &agent,
+        &format!("{}.sig", url),
+        Path::new(&sig_path),
+        MAX_SIGNATURE_BYTES,
+    );
+    Ok(download)
+}
+
+fn sig_status(status: SigStatus) -> &'static str {
+    match status {
+        SigStatus::Valid => "valid",
+        SigStatus::KeyExpired => "key_expired",
+        SigStatus::SigExpired => "signature_expired",
+        SigStatus::KeyUnknown => "key_unknown",
+        SigStatus::KeyDisabled => "key_disabled",
+        SigStatus::Invalid => "invalid",
+    }
+}
+
+fn sig_validity(validity: SigValidity) -> &'static str {
+    match validity {
+        SigValidity::Full => "full",
+        SigValidity::Marginal => "marginal",
+        SigValidity::Never => "never",
+        SigValidity::Unknown => "unknown",
+    }
+}
+
+fn check_signature(pkg: &Pkg, path: &Path) -> PackageSignatureCheck {
+    let mut sig_path = path.to_path_buf().into_os_string();
+    sig_path.push(".sig");
+    let present = Path::new(&sig_path).exists();
+    if !present {
+        return PackageSignatureCheck {
+            present: false,
+            valid: false,
+            signatures: Vec::new(),
+            error: None,
+        };
+    }
+
+    let mut siglist = SigList::new();
+    let result = pkg.check_signature(&mut siglist);
+    let signatures: Vec<SignatureInfo> = siglist
+        .results()
+        .iter()
+        .map(|r| {
+            let key = r.key();
+            SignatureInfo {
+                status: sig_status(r.status()).to_string(),
+                validity: sig_validity(r.validity()).to_string(),
+                fingerprint: key.fingerprint().to_string(),
+                uid: key.uid().map(|s| s.to_string()),
+            }
+        })
+        .collect();
+    PackageSignatureCheck {
+        present,
+        valid: result.is_ok()
+            && !signatures.is_empty()
+            && siglist
+                .results()
+                .iter()
+                .all(|r| r.status() == SigStatus::Valid),
+        signatures,
+        error: result.err().map(|e| e.to_string()),
+    }
+}
+
+/// `.INSTALL` straight from the archive; alpm only reports that one exists.
+fn read_scriptlet(path: &Path) -> Option<String> {
+    let mut cmd = Command::new("bsdtar");
+    cmd.arg("-xOf").arg(path).arg(".INSTALL");
+    let output = output_with_timeout(cmd, BSDTAR_TIMEOUT).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut bytes = output.stdout;
+    bytes.truncate(MAX_SCRIPTLET_BYTES);
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub(crate) fn version_change(new: Ordering) -> &'static str {
+    match new {
+        Ordering::Greater => "upgrade",
+        Ordering::Less => "downgrade",
+        Ordering::Equal => "reinstall",
+    }
+}
+
+/// Load a package file or URL without installing it and report what it would
+/// put on the system, its signature and how it differs from the installed
+/// version.
+pub fn inspect_package(source: &str) -> Result<()> {
+    let is_url = source.starts_with("https://") || source.starts_with("http://");
+    let download = if is_url {
+        Some(download(source)?)
+    } else {
+        None
+    };
+    let path = match &download {
+        Some(d) => d.path.clone(),
+        None => {
+            let path = Path::new(source);
+            if !path.is_absolute() {
+                bail!("Package path must be absolute");
+            }
+            if !path.is_file() {
+                bail!("{} is not a file", source);
+            }
+            path.to_path_buf()
+        }
+    };
+
+    let handle = get_handle()?;
+    // Load unverified so an unsigned or untrusted file can still be reviewed;
+    // the signature is checked and reported separately.
+    let pkg = handle
+        .pkg_load(path.to_string_lossy().as_bytes(), true, SigLevel::NONE)
+        .with_context(|| format!("Failed to load package {}", source))?;
+
+    let files = file_names(&pkg);
+    let installed = handle
+        .localdb()
+        .pkg(pkg.name())
+        .ok()
+        .map(|local| InstalledComparison {
+            installed_version: local.version().to_string(),
+            change: version_change(alpm::vercmp(
+                pkg.version().as_str(),
+                local.version().as_str(),
+            ))
+            .to_string(),
+            depends: list_diff(&deps(local.depends()), &deps(pkg.depends())),
+            provides: list_diff(&deps(local.provides()), &deps(pkg.provides())),
+            conflicts: list_diff(&deps(local.conflicts()), &deps(pkg.conflicts())),
+            files: file_diff(&file_names(local), &files),
+        });
+
+    let response = InspectPackageResponse {
+        source: source.to_string(),
+        name: pkg.name().to_string(),
+        version: pkg.version().to_string(),
+        description: pkg.desc().map(|s| s.to_string()),
+        url: pkg.url().map(|s| s.to_string()),
+        arch: pkg.arch().map(|s| s.to_string()),
+        packager: pkg.packager().map(|s| s.to_string()),
+        build_date: pkg.build_date(),
+        installed_size: pkg.isize(),
+        licenses: pkg.licenses().iter().map(|s| s.to_string()).collect(),
+        groups: pkg.groups().iter().map(|s| s.to_string()).collect(),
+        depends: deps(pkg.depends()),
+        optdepends: deps(pkg.optdepends()),
+        makedepends: deps(pkg.makedepends()),
+        provides: deps(pkg.provides()),
+        conflicts: deps(pkg.conflicts()),
+        replaces: deps(pkg.replaces()),
+        files_truncated: files.len() > MAX_FILES_LISTED,
+        files: files.into_iter().take(MAX_FILES_LISTED).collect(),
+        backup: pkg
+            .backup()
+            .iter()
+            .map(|b| BackupFile {
+                path: b.name().to_string(),
+                hash: b.hash().to_string(),
+            })
+            .collect(),
+        has_scriptlet: pkg.has_scriptlet(),
+        scriptlet: if pkg.has_scriptlet() {
+            read_scriptlet(&path)
+        } else {
+            None
+        },
+        signature: check_signature(&pkg, &path),
+        installed,
+    };
+    emit_json(&response)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_filename_takes_last_segment() {
+        assert_eq!(
+            url_filename("https://example.org/repo/tool-1.0-1-x86_64.pkg.tar.zst?x=1").unwrap(),
+            "tool-1.0-1-x86_64.pkg.tar.zst"
+        );
+        assert_eq!(
+            url_filename("https://example.org/libc%2B%2B-1-1-x86_64.pkg.tar.zst").unwrap(),
+            "libc++-1-1-x86_64.pkg.tar.zst"
+        );
+    }
+
+    #[test]
+    fn url_filename_rejects_non_packages() {
+        assert!(url_filename("https://example.org/").is_err());
+        assert!(url_filename("https://example.org/index.html").is_err());
+        assert!(url_filename("https://example.org/a%2F..%2Fb.pkg.tar.zst").is_err());
+    }
+
+    #[test]
+    fn version_change_labels() {
+        assert_eq!(version_change(Ordering::Greater), "upgrade");
+        assert_eq!(version_change(Ordering::Less), "downgrade");
+        assert_eq!(version_change(Ordering::Equal), "reinstall");
+    }
+}
@@ -193,6 +193,17 @@ pub fn refresh_keyring() -> Result<()> {
     })
 }
 
+/// Refresh every key without progress output, for scheduled runs.
+pub(crate) fn refresh_keys_quietly(timeout_secs: u64) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new().context("Failed to create tokio runtime")?;
+    let options = OperationOptions {
+        timeout_secs: Some(timeout_secs),
+        cancel_token: None,
+    };
+    rt.block_on(Keyring::new().refresh_keys(|_| {}, options))
+        .context("Keyring refresh failed")
+}
+
 pub fn init_keyring() -> Result<()> {
     setup_signal_handler();
 
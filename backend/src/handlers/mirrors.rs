@@ -144,6 +144,23 @@ pub fn refresh_mirrors(
     protocol: &str,
     sort_by: &str,
 ) -> Result<()> {
+    let (mirrors, last_check) = rank_mirrors(count, country, protocol, sort_by)?;
+
+    emit_json(&RefreshMirrorsResponse {
+        total: mirrors.len(),
+        mirrors,
+        last_check,
+    })
+}
+
+/// The best `count` mirrors from the status feed as mirrorlist entries, with
+/// the feed's last-check time.
+pub(crate) fn rank_mirrors(
+    count: usize,
+    country: Option<&str>,
+    protocol: &str,
+    sort_by: &str,
+) -> Result<(Vec<MirrorEntry>, Option<String>)> {
     let status = arch_mirror_client::fetch(&status_agent())?;
 
     let candidates = arch_mirror_client::rank(
@@ -168,11 +185,7 @@ pub fn refresh_mirrors(
         })
         .collect();
 
-    emit_json(&RefreshMirrorsResponse {
-        total: mirrors.len(),
-        mirrors,
-        last_check: status.last_check,
-    })
+    Ok((mirrors, status.last_check))
 }
 
 const MIRROR_TEST_CONCURRENCY: usize = 8;
@@ -311,6 +324,23 @@ const BACKUP_PREFIX: &str = "/etc/pacman.d/mirrorlist.backup.";
 const BACKUP_META_PATH: &str = "/etc/pacman.d/.mirrorlist-backups.meta.json";
 
 pub fn save_mirrorlist(mirrors: &[MirrorEntry]) -> Result<()> {
+    let backup_path = write_mirrorlist(mirrors, BackupSource::Manual)?;
+
+    let response = SaveMirrorlistResponse {
+        success: true,
+        backup_path,
+        message: format!("Saved {} mirrors to {}", mirrors.len(), MIRRORLIST_PATH),
+    };
+
+    emit_json(&response)
+}
+
+/// Replace the mirrorlist with `mirrors`, backing up the old one. Returns the
+/// backup path, if there was a file to back up.
+pub(crate) fn write_mirrorlist(
+    mirrors: &[MirrorEntry],
+    source: BackupSource,
+) -> Result<Option<String>> {
     for mirror in mirrors {
         validate_mirror_url(&mirror.url)?;
     }
@@ -338,7 +368,7 @@ pub fn save_mirrorlist(mirrors: &[MirrorEntry]) -> Result<()> {
 
     // Serialize the whole write/backup/rename/cleanup cycle against other
     // mirrorlist mutations (save, restore, delete) sharing this lock.
-    let backup_path = crate::util::with_file_lock(Path::new(LOCK_PATH), || {
+    crate::util::with_file_lock(Path::new(LOCK_PATH), || {
         // Write to temp file first (atomic write pattern)
         let temp_path = parent.join(format!(".mirrorlist.tmp.{}", std::process::id()));
         {
@@ -367,18 +397,10 @@ pub fn save_mirrorlist(mirrors: &[MirrorEntry]) -> Result<()> {
 
         // Clean up old backups, keeping only the most recent MAX_BACKUPS
         cleanup_old_backups();
-        note_backup(&backup_path, BackupSource::Manual);
+        note_backup(&backup_path, source);
 
         Ok(backup_path)
-    })?;
-
-    let response = SaveMirrorlistResponse {
-        success: true,
-        backup_path,
-        message: format!("Saved {} mirrors to {}", mirrors.len(), MIRRORLIST_PATH),
-    };
-
-    emit_json(&response)
+    })
 }
 
 fn count_mirrors_in_file(path: &Path) -> Result<(usize, usize)> {
@@ -1,28 +1,54 @@
 pub mod archive;
+pub mod aur;
+pub mod build;
 pub mod cache;
 pub mod config;
+pub mod consistency;
 pub mod dependency;
 pub mod downgrade;
+pub mod footprint;
+pub mod groups;
+pub(crate) mod holdback;
+pub(crate) mod hooks;
+pub mod inspect;
 pub mod keyring;
 pub mod lock;
 pub mod log;
 pub mod mirrors;
 pub mod mutation;
 pub mod news;
+pub mod notify;
+pub mod optdeps;
+pub mod packagelist;
 pub mod pacnew;
+pub mod policy;
+pub mod post_upgrade;
 pub mod query;
+pub(crate) mod query_lang;
 pub mod reboot;
 pub mod repos;
 pub mod scheduled;
 pub mod security;
 pub mod services;
 pub mod signoff;
+pub mod state;
+pub mod syncdb;
+pub mod update_diff;
+pub mod why;
 
 pub use archive::{downgrade_from_archive, list_archive_versions};
+pub use aur::check_foreign_updates;
 pub use cache::{clean_cache, get_cache_info};
-pub use config::{add_ignored, list_ignored, remove_ignored};
-pub use dependency::get_dependency_tree;
+pub use config::{
+    add_ignored, add_ignored_group, add_orphan_keep, list_ignored, list_orphan_keep,
+    remove_ignored, remove_ignored_group, remove_orphan_keep,
+};
+pub use consistency::system_consistency;
+pub use dependency::{dependency_graph, get_dependency_tree};
 pub use downgrade::{downgrade_package, list_downgrades};
+pub use footprint::{largest_footprints, package_footprint};
+pub use groups::{group_info, list_groups};
+pub use inspect::inspect_package;
 pub use keyring::{init_keyring, keyring_status, refresh_keyring};
 pub use lock::{check_lock, remove_stale_lock};
 pub use log::{get_grouped_history, get_history};
@@ -31,10 +57,16 @@ pub use mirrors::{
     restore_mirror_backup, save_mirrorlist, test_mirrors,
 };
 pub use mutation::{
-    install_package, preflight_upgrade, remove_orphans, remove_package, run_upgrade, sync_database,
+    build_package, import_packages, install_optdeps, install_package, preflight_upgrade,
+    remove_orphans, remove_package, run_upgrade, state_apply, sync_database, sync_repos,
 };
 pub use news::{fetch_news, mark_dismissed, mark_news_read, read_dismissal, read_news_state};
+pub use notify::{list_notifiers, remove_notifier, set_notifier, test_notifier};
+pub use optdeps::{list_optional_only, optional_dependencies};
+pub use packagelist::{export_packages, import_plan};
 pub use pacnew::get_pacnew_status;
+pub use policy::{list_policies, remove_policy, set_policy};
+pub use post_upgrade::cancel_pending_reboot;
 pub use query::{
     check_updates, list_installed, list_orphans, local_package_info, search, sync_package_info,
 };
@@ -43,8 +75,13 @@ pub use repos::{
     delete_repo_backup, list_repo_backups, list_repos, restore_repo_backup, save_repos,
 };
 pub use scheduled::{
-    get_schedule_config, get_scheduled_runs, record_interrupted, scheduled_run, set_schedule_config,
+    get_schedule_config, get_scheduled_runs, list_schedules, record_interrupted, remove_schedule,
+    scheduled_run, set_named_schedule, set_post_upgrade, set_schedule_config, set_schedule_hooks,
 };
 pub use security::{check_security, security_info};
 pub use services::get_services_status;
 pub use signoff::{read_credentials_from_stdin, signoff_list, signoff_revoke, signoff_sign};
+pub use state::{get_desired_state, set_desired_state, state_plan};
+pub use syncdb::sync_db_status;
+pub use update_diff::update_diff;
+pub use why::why_installed;
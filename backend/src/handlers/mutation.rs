@@ -4,10 +4,11 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::alpm::{
-    TransactionGuard, get_handle, interrupt_if_cancelled, progress_to_string, setup_dl_cb,
-    setup_log_cb, try_interrupt,
+    TransactionGuard, find_available_updates, get_handle, interrupt_if_cancelled,
+    progress_to_string, setup_dl_cb, setup_log_cb, try_interrupt,
 };
 use crate::check_cancel_early;
+use crate::config::{AppConfig, PolicyAction};
 use crate::db::invalidate_repo_map_cache;
 use crate::inhibit::ShutdownInhibitor;
 use crate::models::{
@@ -211,30 +212,48 @@ fn prepare_failure(err_msg: &str) -> anyhow::Error {
     anyhow::anyhow!(message)
 }
 
+/// Commit `tx`, reporting a failure as `Complete`. Returns whether the commit
+/// went through; on `Ok(true)` the caller owes the success `Complete`.
+fn commit_transaction(
+    tx: &mut TransactionGuard,
+    timeout: &TimeoutGuard,
+    interrupt_msg: &str,
+) -> Result<bool> {
+    let _inhibitor = ShutdownInhibitor::take("Applying package changes");
+    match tx.commit().err().map(|e| e.to_string()) {
+        Some(err_msg) => handle_commit_error(&err_msg, is_cancelled(), timeout, interrupt_msg),
+        None => Ok(true),
+    }
+}
+
 fn commit_and_complete(
     tx: &mut TransactionGuard,
     timeout: &TimeoutGuard,
     interrupt_msg: &str,
     success_msg: Option<String>,
 ) -> Result<()> {
-    let _inhibitor = ShutdownInhibitor::take("Applying package changes");
-    match tx.commit().err().map(|e| e.to_string()) {
-        Some(err_msg) => {
-            handle_commit_error(&err_msg, is_cancelled(), timeout, interrupt_msg).map(|_| ())
-        }
-        None => {
-            // Invariant: enqueue the success signal immediately after commit()
-            // returns Ok, before anything else. emit_event hands it to the async
-            // stdout writer; main drains the writer (shutdown_event_writer) before
-            // the process exits, so a succeeded upgrade always reports success and
-            // is never lost. Do not insert work between commit() and here.
-            emit_event(&StreamEvent::Complete {
-                success: true,
-                message: success_msg,
-            });
-            Ok(())
-        }
+    if commit_transaction(tx, timeout, interrupt_msg)? {
+        // Invariant: enqueue the success signal immediately after commit()
+        // returns Ok, before anything else. emit_event hands it to the async
+        // stdout writer; main drains the writer (shutdown_event_writer) before
+        // the process exits, so a succeeded upgrade always reports success and
+        // is never lost. Do not insert work between commit() and here.
+        emit_event(&StreamEvent::Complete {
+            success: true,
+            message: success_msg,
+        });
     }
+    Ok(())
+}
+
+/// Updates a `hold` policy keeps back. Holds that have run out no longer
+/// count.
+fn held_updates(handle: &Alpm, config: &AppConfig) -> Vec<String> {
+    find_available_updates(handle, config)
+        .into_iter()
+        .filter(|u| u.policy == Some(PolicyAction::Hold))
+        .map(|u| u.name)
+        .collect()
 }
 
 pub fn preflight_upgrade(ignore_pkgs: &[String]) -> Result<()> {
@@ -243,11 +262,18 @@ pub fn preflight_upgrade(ignore_pkgs: &[String]) -> Result<()> {
     setup_signal_handler();
 
     let mut handle = get_handle()?;
+    let config = AppConfig::load().unwrap_or_default();
+    config.apply_ignores(&mut handle)?;
 
-    for pkg_name in ignore_pkgs {
+    // The same holds run_upgrade enforces, so the plan matches what it commits.
+    for pkg_name in ignore_pkgs.iter().chain(&held_updates(&handle, &config)) {
         handle.add_ignorepkg(pkg_name.as_str())?;
     }
 
+    // A stale db makes "nothing to upgrade" misleading, so this is reported
+    // even when the transaction turns out empty.
+    let stale_warning = super::syncdb::stale_preflight_warning(&handle);
+
     let state = Rc::new(RefCell::new(PreflightState::default()));
     let state_cb = Rc::clone(&state);
 
@@ -347,6 +373,7 @@ pub fn preflight_upgrade(ignore_pkgs: &[String]) -> Result<()> {
     if packages_to_upgrade == 0 {
         let response = PreflightResponse {
             success: true,
+            warnings: stale_warning.into_iter().collect(),
             ..Default::default()
         };
         return emit_json(&response);
@@ -355,7 +382,7 @@ pub fn preflight_upgrade(ignore_pkgs: &[String]) -> Result<()> {
     let upgrade_pkgs = tx.add();
     let upgrade_names: Vec<String> = upgrade_pkgs.iter().map(|p| p.name().to_string()).collect();
 
-    let mut warnings = Vec::new();
+    let mut warnings: Vec<PreflightWarning> = stale_warning.into_iter().collect();
 
     let firmware_pkgs: Vec<String> = upgrade_names
         .iter()
@@ -439,6 +466,138 @@ pub fn sync_database(force: bool, timeout_secs: Option<u64>) -> Result<()> {
     }
 }
 
+/// Handle holding only `repo` among the sync dbs, so `update` touches nothing
+/// else.
+fn single_repo_handle(repo: &str) -> Result<Alpm> {
+    let mut handle = get_handle()?;
+    let others: Vec<String> = handle
+        .syncdbs()
+        .iter()
+        .map(|db| db.name().to_string())
+        .filter(|name| name != repo)
+        .collect();
+    for name in others {
+        if let Some(db) = handle.syncdbs_mut().iter().find(|db| db.name() == name) {
+            db.unregister();
+        }
+    }
+    if handle.syncdbs().is_empty() {
+        anyhow::bail!("Repository '{}' is not configured in pacman.conf", repo);
+    }
+    setup_log_cb(&mut handle);
+    setup_dl_cb(&mut handle);
+    Ok(handle)
+}
+
+/// Outcome of refreshing one repo: which server served it and whether the db
+/// changed, or every server's error when none did.
+struct RepoSyncOutcome {
+    server: Option<String>,
+    updated: bool,
+    errors: Vec<String>,
+}
+
+/// Walk the repo's Server list one entry at a time, the order pacman would
+/// try them in, stopping at the first that answers.
+fn sync_one_repo(repo: &str, force: bool) -> Result<RepoSyncOutcome> {
+    let mut handle = single_repo_handle(repo)?;
+    let servers: Vec<String> = handle
+        .syncdbs()
+        .iter()
+        .flat_map(|db| {
+            db.servers()
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    if servers.is_empty() {
+        anyhow::bail!("Repository '{}' has no Server entries", repo);
+    }
+
+    let mut errors = Vec::new();
+    for server in servers {
+        if is_cancelled() {
+            break;
+        }
+        let dbs = handle.syncdbs_mut();
+        if let Some(db) = dbs.first() {
+            db.set_servers([server.as_str()].into_iter())?;
+        }
+        match dbs.update(force) {
+            Ok(updated) => {
+                return Ok(RepoSyncOutcome {
+                    server: Some(server),
+                    updated,
+                    errors,
+                });
+            }
+            Err(e) => errors.push(format!("{}: {}", server, e)),
+        }
+    }
+    Ok(RepoSyncOutcome {
+        server: None,
+        updated: false,
+        errors,
+    })
+}
+
+/// `sync-database --repo`: refresh only the named repos, each on its own so
+/// one failing mirror doesn't mask the others. Every repo gets a `repo_sync`
+/// event before the final Complete, which fails if any repo did.
+pub fn sync_repos(repos: &[String], force: bool, timeout_secs: Option<u64>) -> Result<()> {
+    setup_signal_handler();
+    let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_MUTATION_TIMEOUT_SECS));
+
+    check_cancel_early!(&timeout);
+
+    let mut failed = Vec::new();
+    for repo in repos {
+        check_cancel_early!(&timeout);
+        let event = match sync_one_repo(repo, force) {
+            Ok(outcome) => StreamEvent::RepoSync {
+                repo: repo.clone(),
+                success: outcome.server.is_some(),
+                updated: outcome.updated,
+                server: outcome.server,
+                message: (!outcome.errors.is_empty()).then(|| outcome.errors.join("; ")),
+            },
+            Err(e) => StreamEvent::RepoSync {
+                repo: repo.clone(),
+                success: false,
+                updated: false,
+                server: None,
+                message: Some(format!("{:#}", e)),
+            },
+        };
+        if let StreamEvent::RepoSync { success: false, .. } = event {
+            failed.push(repo.clone());
+        }
+        emit_event(&event);
+    }
+
+    invalidate_repo_map_cache();
+    let check_result = check_cancel(&timeout);
+    if !matches!(check_result, CheckResult::Continue) {
+        emit_cancellation_complete(&check_result);
+        return Ok(());
+    }
+    if failed.is_empty() {
+        emit_event(&StreamEvent::Complete {
+            success: true,
+            message: None,
+        });
+        Ok(())
+    } else {
+        let message = format!("Failed to sync: {}", failed.join(", "));
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(message.clone()),
+        });
+        Err(anyhow::anyhow!(message))
+    }
+}
+
 pub fn run_upgrade(ignore_pkgs: &[String], timeout_secs: Option<u64>) -> Result<()> {
     setup_signal_handler();
     spawn_cancel_listener();
@@ -446,7 +605,35 @@ pub fn run_upgrade(ignore_pkgs: &[String], timeout_secs: Option<u64>) -> Result<
 
     let mut handle = get_handle()?;
 
-    for pkg_name in ignore_pkgs {
+    match crate::handlers::policy::prune_expired_holds(&handle) {
+        Ok(expired) => {
+            for message in expired {
+                emit_event(&StreamEvent::Log {
+                    level: "info".to_string(),
+                    message,
+                });
+            }
+        }
+        Err(e) => emit_event(&StreamEvent::Log {
+            level: "warning".to_string(),
+            message: format!("Could not drop expired holds: {:#}", e),
+        }),
+    }
+
+    // Holds are enforced here regardless of what the caller passes in.
+    let config = AppConfig::load().inspect_err(|e| {
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(format!("Failed to load update policies: {:#}", e)),
+        });
+    })?;
+    config.apply_ignores(&mut handle).inspect_err(|e| {
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(format!("Failed to apply ignored packages: {}", e)),
+        });
+    })?;
+    for pkg_name in ignore_pkgs.iter().chain(&held_updates(&handle, &config)) {
         handle.add_ignorepkg(pkg_name.as_str()).inspect_err(|e| {
             emit_event(&StreamEvent::Complete {
                 success: false,
@@ -499,25 +686,13 @@ pub fn run_upgrade(ignore_pkgs: &[String], timeout_secs: Option<u64>) -> Result<
     )
 }
 
-pub fn remove_orphans(timeout_secs: Option<u64>) -> Result<()> {
+pub fn remove_orphans(timeout_secs: Option<u64>, include_optional: bool) -> Result<()> {
     setup_signal_handler();
     let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_MUTATION_TIMEOUT_SECS));
 
     let mut handle = get_handle()?;
 
-    let orphan_names: Vec<String> = {
-        let localdb = handle.localdb();
-        localdb
-            .pkgs()
-            .iter()
-            .filter(|pkg| {
-                pkg.reason() == alpm::PackageReason::Depend
-                    && pkg.required_by().is_empty()
-                    && pkg.optional_for().is_empty()
-            })
-            .map(|pkg| pkg.name().to_string())
-            .collect()
-    };
+    let orphan_names = crate::handlers::footprint::find_orphans(&handle, include_optional)?;
 
     if orphan_names.is_empty() {
         emit_event(&StreamEvent::Complete {
@@ -533,7 +708,9 @@ pub fn remove_orphans(timeout_secs: Option<u64>) -> Result<()> {
 
     check_cancel_early!(&timeout);
 
-    let mut tx = TransactionGuard::new(&mut handle, TransFlag::RECURSE)?;
+    // The orphan set is already closed over dependencies. RECURSE would also
+    // take dependency-installed packages on the keep list.
+    let mut tx = TransactionGuard::new(&mut handle, TransFlag::NONE)?;
 
     for name in &orphan_names {
         if let Ok(pkg) = tx.localdb().pkg(name.as_str())
@@ -585,18 +762,56 @@ pub fn install_package(name: &str, timeout_secs: Option<u64>) -> Result<()> {
     let not_found = || {
         emit_event(&StreamEvent::Complete {
             success: false,
-            message: Some(format!("Package '{}' not found in any repository", name)),
+            message: Some(format!(
+                "Package or group '{}' not found in any repository",
+                name
+            )),
         });
-        anyhow::anyhow!("Package '{}' not found in any repository", name)
+        anyhow::anyhow!("Package or group '{}' not found in any repository", name)
     };
 
     // Resolved before taking the transaction (and the db lock), so a missing
     // package is reported as such even when another operation holds the lock.
-    if !handle.syncdbs().iter().any(|db| db.pkg(name).is_ok()) {
+    // A name that is not a package falls back to a group, like `pacman -S`.
+    AppConfig::load()
+        .unwrap_or_default()
+        .apply_ignores(&mut handle)?;
+    let Some(targets) = resolve_install_targets(&handle, name) else {
         return Err(not_found());
+    };
+    let is_group = !handle.syncdbs().iter().any(|db| db.pkg(name).is_ok());
+    if targets.is_empty() {
+        let err_msg = format!("All members of group '{}' are ignored", name);
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(err_msg.clone()),
+        });
+        return Err(anyhow::anyhow!(err_msg));
+    }
+    if is_group {
+        emit_event(&StreamEvent::Log {
+            level: "info".to_string(),
+            message: format!(
+                "Installing group '{}': {} package(s) ({})",
+                name,
+                targets.len(),
+                targets
+                    .iter()
+                    .map(|(_, p)| p.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
     }
 
-    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::NONE) {
+    // Group members that are already installed are skipped rather than
+    // reinstalled, matching `pacman -S --needed`.
+    let flags = if is_group {
+        TransFlag::NEEDED
+    } else {
+        TransFlag::NONE
+    };
+    let mut tx = match TransactionGuard::new(&mut handle, flags) {
         Ok(tx) => tx,
         Err(e) => {
             emit_event(&StreamEvent::Complete {
@@ -607,16 +822,352 @@ pub fn install_package(name: &str, timeout_secs: Option<u64>) -> Result<()> {
         }
     };
 
-    let Some(pkg) = tx.syncdbs().iter().find_map(|db| db.pkg(name).ok()) else {
-        return Err(not_found());
+    for (db_name, pkg_name) in &targets {
+        let Some(pkg) = tx
+            .syncdbs()
+            .iter()
+            .find(|db| db.name() == db_name)
+            .and_then(|db| db.pkg(pkg_name.as_str()).ok())
+        else {
+            return Err(not_found());
+        };
+        if let Err(e) = tx.add_pkg(pkg) {
+            let err_msg = format!("Failed to add '{}' to transaction: {}", pkg_name, e);
+            emit_event(&StreamEvent::Complete {
+                success: false,
+                message: Some(err_msg.clone()),
+            });
+            return Err(anyhow::anyhow!(err_msg));
+        }
+    }
+
+    check_cancel_early!(&timeout);
+
+    if let Some(err_msg) = tx.prepare().err().map(|e| e.to_string()) {
+        return Err(prepare_failure(&err_msg));
+    }
+
+    commit_and_complete(
+        &mut tx,
+        &timeout,
+        "Operation interrupted - package may be in inconsistent state",
+        Some(format!("Successfully installed {}", name)),
+    )
+}
+
+pub fn install_optdeps(name: &str, optdeps: &[String], timeout_secs: Option<u64>) -> Result<()> {
+    setup_signal_handler();
+    let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_MUTATION_TIMEOUT_SECS));
+
+    let mut handle = get_handle()?;
+
+    setup_log_cb(&mut handle);
+    setup_dl_cb(&mut handle);
+    setup_progress_cb(&mut handle);
+    setup_event_cb(&mut handle, EventScope::Install);
+    setup_question_cb(&mut handle, false);
+
+    check_cancel_early!(&timeout);
+
+    let fail = |msg: String| {
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(msg.clone()),
+        });
+        anyhow::anyhow!(msg)
     };
-    if let Err(e) = tx.add_pkg(pkg) {
-        let err_msg = format!("Failed to add '{}' to transaction: {}", name, e);
+
+    // Resolve every target up front so nothing is installed when one of the
+    // requested names is not an optdepend of the package or has no provider.
+    let mut targets: Vec<(String, String)> = Vec::new();
+    {
+        let Ok(pkg) = handle.localdb().pkg(name) else {
+            return Err(fail(format!("Package '{}' is not installed", name)));
+        };
+        for requested in optdeps {
+            let Some(dep) = pkg.optdepends().iter().find(|d| d.name() == requested) else {
+                return Err(fail(format!(
+                    "'{}' is not an optional dependency of {}",
+                    requested, name
+                )));
+            };
+            let resolved = crate::handlers::optdeps::resolve_optdepend(&handle, dep);
+            if let Some(by) = resolved.satisfied_by {
+                emit_event(&StreamEvent::Log {
+                    level: "info".to_string(),
+                    message: format!("{} is already satisfied by {}", requested, by),
+                });
+                continue;
+            }
+            match (resolved.provider_repository, resolved.provider) {
+                (Some(repo), Some(provider)) => {
+                    if !targets.iter().any(|(_, p)| *p == provider) {
+                        targets.push((repo, provider));
+                    }
+                }
+                _ => {
+                    return Err(fail(format!(
+                        "No package in the sync databases satisfies '{}'",
+                        resolved.depend
+                    )));
+                }
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        emit_event(&StreamEvent::Complete {
+            success: true,
+            message: Some("All requested optional dependencies are already installed".to_string()),
+        });
+        return Ok(());
+    }
+
+    // ALL_DEPS installs the targets with the Depend reason (pacman --asdeps), so
+    // they become orphans again once nothing lists them as optional.
+    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::ALL_DEPS | TransFlag::NEEDED) {
+        Ok(tx) => tx,
+        Err(e) => {
+            emit_event(&StreamEvent::Complete {
+                success: false,
+                message: Some(e.to_string()),
+            });
+            return Err(e);
+        }
+    };
+
+    for (db_name, pkg_name) in &targets {
+        let Some(pkg) = tx
+            .syncdbs()
+            .iter()
+            .find(|db| db.name() == db_name)
+            .and_then(|db| db.pkg(pkg_name.as_str()).ok())
+        else {
+            return Err(fail(format!(
+                "Package '{}' not found in any repository",
+                pkg_name
+            )));
+        };
+        if let Err(e) = tx.add_pkg(pkg) {
+            return Err(fail(format!(
+                "Failed to add '{}' to transaction: {}",
+                pkg_name, e
+            )));
+        }
+    }
+
+    check_cancel_early!(&timeout);
+
+    if let Some(err_msg) = tx.prepare().err().map(|e| e.to_string()) {
+        return Err(prepare_failure(&err_msg));
+    }
+
+    commit_and_complete(
+        &mut tx,
+        &timeout,
+        "Operation interrupted - package may be in inconsistent state",
+        Some(format!(
+            "Installed {} optional dependenc{} for {}",
+            targets.len(),
+            if targets.len() == 1 { "y" } else { "ies" },
+            name
+        )),
+    )
+}
+
+/// Install what makepkg needs before it will build, from the sync repos and
+/// with the Depend reason. Returns false once it has emitted a failed
+/// Complete.
+fn install_build_dependencies(deps: &[String], timeout: &TimeoutGuard) -> Result<bool> {
+    let mut handle = get_handle()?;
+    let mut targets: Vec<(String, String)> = Vec::new();
+    for dep in deps {
+        if handle
+            .localdb()
+            .pkgs()
+            .find_satisfier(dep.as_str())
+            .is_some()
+        {
+            continue;
+        }
+        let Some(pkg) = handle.syncdbs().find_satisfier(dep.as_str()) else {
+            emit_event(&StreamEvent::Complete {
+                success: false,
+                message: Some(format!(
+                    "No package in the sync databases satisfies build dependency '{}'",
+                    dep
+                )),
+            });
+            return Ok(false);
+        };
+        let target = (
+            pkg.db().map(|db| db.name().to_string()).unwrap_or_default(),
+            pkg.name().to_string(),
+        );
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    if targets.is_empty() {
+        return Ok(true);
+    }
+
+    emit_event(&StreamEvent::Log {
+        level: "info".to_string(),
+        message: format!(
+            "Installing build dependencies: {}",
+            targets
+                .iter()
+                .map(|(_, p)| p.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    });
+    setup_log_cb(&mut handle);
+    setup_dl_cb(&mut handle);
+    setup_progress_cb(&mut handle);
+    setup_event_cb(&mut handle, EventScope::Install);
+    setup_question_cb(&mut handle, false);
+
+    let fail = |msg: String| {
         emit_event(&StreamEvent::Complete {
             success: false,
-            message: Some(err_msg.clone()),
+            message: Some(msg),
         });
-        return Err(anyhow::anyhow!(err_msg));
+        Ok(false)
+    };
+    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::ALL_DEPS | TransFlag::NEEDED) {
+        Ok(tx) => tx,
+        Err(e) => return fail(e.to_string()),
+    };
+    for (db_name, pkg_name) in &targets {
+        if let Some(pkg) = tx
+            .syncdbs()
+            .iter()
+            .find(|db| db.name() == db_name)
+            .and_then(|db| db.pkg(pkg_name.as_str()).ok())
+            && let Err(e) = tx.add_pkg(pkg)
+        {
+            return fail(format!(
+                "Failed to add '{}' to transaction: {}",
+                pkg_name, e
+            ));
+        }
+    }
+    if let Some(err_msg) = tx.prepare().err().map(|e| e.to_string()) {
+        prepare_failure(&err_msg);
+        return Ok(false);
+    }
+    let _inhibitor = ShutdownInhibitor::take("Installing build dependencies");
+    if let Some(err_msg) = tx.commit().err().map(|e| e.to_string()) {
+        handle_commit_error(
+            &err_msg,
+            is_cancelled(),
+            timeout,
+            "Operation interrupted - build dependencies may be partially installed",
+        )?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// `build-package DIR`: build a PKGBUILD with makepkg as the unprivileged
+/// build user in a private copy of DIR, then install the result through
+/// libalpm. Runtime dependencies come from the sync repos as Depend-reason
+/// installs during prepare, like `pacman -U`.
+pub fn build_package(dir: &str, timeout_secs: Option<u64>) -> Result<()> {
+    use crate::handlers::build::{
+        BuildOutcome, DEFAULT_BUILD_TIMEOUT_SECS, Workspace, build_dependencies, lookup_build_user,
+        resolve_build_dir, run_makepkg,
+    };
+
+    setup_signal_handler();
+    spawn_cancel_listener();
+    let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_BUILD_TIMEOUT_SECS));
+
+    let fail = |msg: String| {
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(msg.clone()),
+        });
+        anyhow::anyhow!(msg)
+    };
+
+    let setup = resolve_build_dir(dir).and_then(|source| {
+        let user = lookup_build_user()?;
+        Ok((Workspace::create(&source, user)?, user))
+    });
+    let (workspace, user) = setup.map_err(|e| fail(format!("{:#}", e)))?;
+
+    check_cancel_early!(&timeout);
+
+    let carch = get_handle()?
+        .architectures()
+        .first()
+        .unwrap_or(std::env::consts::ARCH)
+        .to_string();
+    let deps =
+        build_dependencies(&workspace, user, &carch).map_err(|e| fail(format!("{:#}", e)))?;
+    if !install_build_dependencies(&deps, &timeout)? {
+        return Ok(());
+    }
+
+    check_cancel_early!(&timeout);
+
+    emit_event(&StreamEvent::Log {
+        level: "info".to_string(),
+        message: format!(
+            "Running makepkg as {} (timeout: {}s)",
+            crate::handlers::build::BUILD_USER,
+            timeout.timeout_secs()
+        ),
+    });
+    match run_makepkg(&workspace, user, &timeout).map_err(|e| fail(format!("{:#}", e)))? {
+        BuildOutcome::Succeeded => {}
+        BuildOutcome::Cancelled => {
+            emit_cancellation_complete(&CheckResult::Cancelled);
+            return Ok(());
+        }
+        BuildOutcome::TimedOut => {
+            emit_cancellation_complete(&CheckResult::TimedOut(timeout.timeout_secs()));
+            return Ok(());
+        }
+        BuildOutcome::Failed(status) => {
+            return Err(fail(format!(
+                "makepkg failed: exit code {}",
+                status.code().unwrap_or(-1)
+            )));
+        }
+    }
+
+    let packages = workspace.packages().map_err(|e| fail(format!("{:#}", e)))?;
+    if packages.is_empty() {
+        return Err(fail("makepkg produced no packages".to_string()));
+    }
+
+    let mut handle = get_handle()?;
+    setup_log_cb(&mut handle);
+    setup_dl_cb(&mut handle);
+    setup_progress_cb(&mut handle);
+    setup_event_cb(&mut handle, EventScope::Install);
+    setup_question_cb(&mut handle, false);
+
+    check_cancel_early!(&timeout);
+
+    let siglevel = handle.local_file_siglevel();
+    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::NONE) {
+        Ok(tx) => tx,
+        Err(e) => return Err(fail(e.to_string())),
+    };
+    let mut names = Vec::new();
+    for path in &packages {
+        let pkg = tx
+            .pkg_load(&path.to_string_lossy(), siglevel)
+            .map_err(|e| fail(format!("Failed to load {}: {}", path.display(), e)))?;
+        names.push(pkg.name().to_string());
+        if let Err(e) = tx.add_pkg(pkg) {
+            return Err(fail(format!("Failed to add package to transaction: {}", e)));
+        }
     }
 
     check_cancel_early!(&timeout);
@@ -629,10 +1180,295 @@ pub fn install_package(name: &str, timeout_secs: Option<u64>) -> Result<()> {
         &mut tx,
         &timeout,
         "Operation interrupted - package may be in inconsistent state",
-        Some(format!("Successfully installed {}", name)),
+        Some(format!("Built and installed {}", names.join(", "))),
+    )
+}
+
+pub fn import_packages(list: &str, timeout_secs: Option<u64>) -> Result<()> {
+    setup_signal_handler();
+    let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_MUTATION_TIMEOUT_SECS));
+
+    let list = crate::handlers::packagelist::parse_package_list(list)?;
+    let mut handle = get_handle()?;
+
+    setup_log_cb(&mut handle);
+    setup_dl_cb(&mut handle);
+    setup_progress_cb(&mut handle);
+    setup_event_cb(&mut handle, EventScope::Install);
+    setup_question_cb(&mut handle, false);
+
+    check_cancel_early!(&timeout);
+
+    let plan = crate::handlers::packagelist::plan_import(&handle, &list);
+    let report = |level: &str, label: &str, names: &[String]| {
+        if !names.is_empty() {
+            emit_event(&StreamEvent::Log {
+                level: level.to_string(),
+                message: format!("{} ({}): {}", label, names.len(), names.join(", ")),
+            });
+        }
+    };
+    report("info", "Already installed", &plan.already_installed);
+    report(
+        "warning",
+        "Foreign packages, not installable from the repositories",
+        &plan.foreign,
+    );
+    report("warning", "Not found in any repository", &plan.unknown);
+    for target in &plan.to_install {
+        if let Some(pinned) = target
+            .pinned_version
+            .as_deref()
+            .filter(|p| *p != target.version)
+        {
+            emit_event(&StreamEvent::Log {
+                level: "warning".to_string(),
+                message: format!(
+                    "{}: list pins {}, the repositories have {}",
+                    target.name, pinned, target.version
+                ),
+            });
+        }
+    }
+
+    if plan.to_install.is_empty() {
+        emit_event(&StreamEvent::Complete {
+            success: true,
+            message: Some("Nothing to install".to_string()),
+        });
+        return Ok(());
+    }
+
+    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::NONE) {
+        Ok(tx) => tx,
+        Err(e) => {
+            emit_event(&StreamEvent::Complete {
+                success: false,
+                message: Some(e.to_string()),
+            });
+            return Err(e);
+        }
+    };
+
+    for target in &plan.to_install {
+        let add_result = tx
+            .syncdbs()
+            .iter()
+            .find(|db| db.name() == target.repository)
+            .and_then(|db| db.pkg(target.name.as_str()).ok())
+            .ok_or_else(|| "not found in any repository".to_string())
+            .and_then(|pkg| tx.add_pkg(pkg).map_err(|e| e.to_string()));
+        if let Err(e) = add_result {
+            let err_msg = format!("Failed to add '{}' to transaction: {}", target.name, e);
+            emit_event(&StreamEvent::Complete {
+                success: false,
+                message: Some(err_msg.clone()),
+            });
+            return Err(anyhow::anyhow!(err_msg));
+        }
+    }
+
+    check_cancel_early!(&timeout);
+
+    if let Some(err_msg) = tx.prepare().err().map(|e| e.to_string()) {
+        return Err(prepare_failure(&err_msg));
+    }
+
+    commit_and_complete(
+        &mut tx,
+        &timeout,
+        "Operation interrupted - package may be in inconsistent state",
+        Some(format!("Installed {} package(s)", plan.to_install.len())),
     )
 }
 
+pub fn state_apply(source: Option<&str>, timeout_secs: Option<u64>) -> Result<()> {
+    setup_signal_handler();
+    let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_MUTATION_TIMEOUT_SECS));
+
+    let loaded = crate::handlers::state::load_desired_state(source)?;
+    let mut handle = get_handle()?;
+
+    setup_log_cb(&mut handle);
+    setup_dl_cb(&mut handle);
+    setup_progress_cb(&mut handle);
+    // Installs and removals in one transaction, which only the upgrade scope
+    // streams both of.
+    setup_event_cb(&mut handle, EventScope::Upgrade);
+    setup_question_cb(&mut handle, false);
+
+    check_cancel_early!(&timeout);
+
+    let plan = crate::handlers::state::plan_for(&handle, &loaded.state, &loaded.keep);
+    if !plan.unresolved.is_empty() {
+        emit_event(&StreamEvent::Log {
+            level: "warning".to_string(),
+            message: format!(
+                "Required but not found in any repository: {}",
+                plan.unresolved.join(", ")
+            ),
+        });
+    }
+
+    let has_packages = !plan.install.is_empty() || !plan.remove.is_empty();
+    if !has_packages && plan.mark_explicit.is_empty() && plan.mark_dependency.is_empty() {
+        emit_event(&StreamEvent::Complete {
+            success: true,
+            message: Some("System already matches the desired state".to_string()),
+        });
+        return Ok(());
+    }
+
+    // Reason changes need the db lock too, so the transaction is taken even
+    // when there is nothing to install or remove.
+    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::NONE) {
+        Ok(tx) => tx,
+        Err(e) => {
+            emit_event(&StreamEvent::Complete {
+                success: false,
+                message: Some(e.to_string()),
+            });
+            return Err(e);
+        }
+    };
+
+    let fail = |msg: String| {
+        emit_event(&StreamEvent::Complete {
+            success: false,
+            message: Some(msg.clone()),
+        });
+        anyhow::anyhow!(msg)
+    };
+
+    for name in &plan.install {
+        let add_result = tx
+            .syncdbs()
+            .iter()
+            .find_map(|db| db.pkg(name.as_str()).ok())
+            .ok_or_else(|| "not found in any repository".to_string())
+            .and_then(|pkg| tx.add_pkg(pkg).map_err(|e| e.to_string()));
+        if let Err(e) = add_result {
+            return Err(fail(format!(
+                "Failed to add '{}' to transaction: {}",
+                name, e
+            )));
+        }
+    }
+    for name in &plan.remove {
+        let mark_result = tx
+            .localdb()
+            .pkg(name.as_str())
+            .map_err(|e| e.to_string())
+            .and_then(|pkg| tx.remove_pkg(pkg).map_err(|e| e.to_string()));
+        if let Err(e) = mark_result {
+            return Err(fail(format!(
+                "Failed to mark '{}' for removal: {}",
+                name, e
+            )));
+        }
+    }
+
+    check_cancel_early!(&timeout);
+
+    if has_packages && let Some(err_msg) = tx.prepare().err().map(|e| e.to_string()) {
+        return Err(prepare_failure(&err_msg));
+    }
+
+    // Reason changes only once the packages are in place, so a failed or
+    // interrupted commit leaves install reasons untouched. The transaction
+    // still holds the lock, and the marks are local db writes, so they go
+    // before the success signal and their count into it.
+    if has_packages
+        && !commit_transaction(
+            &mut tx,
+            &timeout,
+            "Operation interrupted - package may be in inconsistent state",
+        )?
+    {
+        return Ok(());
+    }
+    let marked = apply_reason_marks(&tx, &plan);
+    emit_event(&StreamEvent::Complete {
+        success: true,
+        message: Some(format!(
+            "Desired state applied: {} installed, {} removed, {} reason change(s)",
+            plan.install.len(),
+            plan.remove.len(),
+            marked
+        )),
+    });
+    Ok(())
+}
+
+/// Set the install reasons `plan` asks for, logging each change. Returns how
+/// many were made.
+fn apply_reason_marks(tx: &TransactionGuard, plan: &crate::models::StatePlan) -> usize {
+    let marks = plan
+        .mark_explicit
+        .iter()
+        .map(|n| (n, alpm::PackageReason::Explicit))
+        .chain(
+            plan.mark_dependency
+                .iter()
+                .map(|n| (n, alpm::PackageReason::Depend)),
+        );
+    let mut marked = 0;
+    for (name, reason) in marks {
+        let result = tx
+            .localdb()
+            .pkg(name.as_str())
+            .and_then(|pkg| pkg.set_reason(reason));
+        let (level, message) = match result {
+            Ok(()) => {
+                marked += 1;
+                (
+                    "info",
+                    format!(
+                        "Marked {} as {}",
+                        name,
+                        crate::alpm::reason_to_string(reason)
+                    ),
+                )
+            }
+            Err(e) => (
+                "warning",
+                format!("Failed to change reason of {}: {}", name, e),
+            ),
+        };
+        emit_event(&StreamEvent::Log {
+            level: level.to_string(),
+            message,
+        });
+    }
+    marked
+}
+
+/// (repo, package) pairs to install for `name`: the package itself when a sync
+/// repo has it, otherwise the members of the group of that name. Group members
+/// ignored through pacman.conf or the app config (applied to `handle`), by
+/// name or by group, are left out, and a member in several repos is taken from
+/// the first one, as pacman does.
+fn resolve_install_targets(handle: &Alpm, name: &str) -> Option<Vec<(String, String)>> {
+    if let Some(db) = handle.syncdbs().iter().find(|db| db.pkg(name).is_ok()) {
+        return Some(vec![(db.name().to_string(), name.to_string())]);
+    }
+
+    let mut targets: Vec<(String, String)> = Vec::new();
+    let mut found = false;
+    for db in handle.syncdbs() {
+        let Ok(group) = db.group(name) else {
+            continue;
+        };
+        found = true;
+        for pkg in group.packages() {
+            if !pkg.should_ignore() && !targets.iter().any(|(_, n)| n == pkg.name()) {
+                targets.push((db.name().to_string(), pkg.name().to_string()));
+            }
+        }
+    }
+    found.then_some(targets)
+}
+
 pub fn remove_package(name: &str, timeout_secs: Option<u64>) -> Result<()> {
     setup_signal_handler();
     let timeout = TimeoutGuard::new(timeout_secs.unwrap_or(DEFAULT_MUTATION_TIMEOUT_SECS));
@@ -0,0 +1,784 @@
+use alpm::Alpm;
+use anyhow::{Context, Result, bail};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::alpm::get_handle;
+use crate::config::{
+    AppConfig, IgnoreOperationResponse, Notifier, NotifierEntry, NotifierSink, NotifiersResponse,
+    NotifyEvent, ScheduleMode,
+};
+use crate::handlers::pacnew::pacnew_files;
+use crate::handlers::reboot::reboot_status;
+use crate::handlers::security::load_advisories;
+use crate::util::{emit_json, output_with_input_timeout, with_file_lock, write_json_atomic};
+use crate::validation::validate_notifier_name;
+
+const STATE_DIR: &str = "/var/lib/cockpit-pacman";
+const STATE_PATH: &str = "/var/lib/cockpit-pacman/notify-state.json";
+const STATE_LOCK_PATH: &str = "/var/lib/cockpit-pacman/.notify-state.json.lock";
+const SENDMAIL_PATH: &str = "/usr/bin/sendmail";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What every sink receives; webhooks and commands get it as JSON.
+#[derive(Debug, Serialize)]
+pub(crate) struct Notification {
+    pub event: NotifyEvent,
+    pub host: String,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    pub title: String,
+    pub details: Vec<String>,
+}
+
+/// A finished scheduled run, as recorded in its log entry.
+pub(crate) struct RunOutcome<'a> {
+    pub timestamp: &'a str,
+    pub schedule: Option<&'a str>,
+    pub mode: &'a str,
+    pub status: &'a str,
+    pub packages_checked: usize,
+    pub error: Option<&'a str>,
+    pub details: &'a [String],
+}
+
+/// Alerts already sent, so a condition is reported when it appears rather
+/// than on every run while it lasts.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct NotifyState {
+    #[serde(default)]
+    advisories: BTreeSet<String>,
+    #[serde(default)]
+    pacnew: BTreeSet<String>,
+    #[serde(default)]
+    reboot_required: bool,
+}
+
+impl NotifyState {
+    /// `self` moved on to `current`, except for the alerts in `undelivered`:
+    /// no sink took those, so they stay unseen and are sent again next run.
+    fn advance(self, current: NotifyState, undelivered: &[NotifyEvent]) -> NotifyState {
+        let keep = |event| undelivered.contains(&event);
+        NotifyState {
+            advisories: if keep(NotifyEvent::SecurityAdvisories) {
+                self.advisories
+            } else {
+                current.advisories
+            },
+            pacnew: if keep(NotifyEvent::PacnewFiles) {
+                self.pacnew
+            } else {
+                current.pacnew
+            },
+            reboot_required: if keep(NotifyEvent::RebootRequired) {
+                self.reboot_required
+            } else {
+                current.reboot_required
+            },
+        }
+    }
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Notifications about the run itself.
+fn run_notifications(outcome: &RunOutcome, host: &str) -> Vec<Notification> {
+    let label = match outcome.schedule {
+        Some(name) => format!("Scheduled {} run ({})", outcome.mode, name),
+        None => format!("Scheduled {} run", outcome.mode),
+    };
+    let notification = |event, title: String, details: Vec<String>| Notification {
+        event,
+        host: host.to_string(),
+        timestamp: outcome.timestamp.to_string(),
+        schedule: outcome.schedule.map(|s| s.to_string()),
+        title,
+        details,
+    };
+
+    let mut notifications = Vec::new();
+    match outcome.status {
+        "failed" => notifications.push(notification(
+            NotifyEvent::RunFailed,
+            format!("{} failed", label),
+            outcome
+                .error
+                .map(|e| e.to_string())
+                .into_iter()
+                .chain(outcome.details.iter().cloned())
+                .collect(),
+        )),
+        "skipped" => notifications.push(notification(
+            NotifyEvent::RunSkipped,
+            format!("{} skipped", label),
+            outcome.details.to_vec(),
+        )),
+        _ => {}
+    }
+
+    let checks_updates = outcome
+        .mode
+        .parse::<ScheduleMode>()
+        .is_ok_and(ScheduleMode::checks_updates);
+    if checks_updates && outcome.status != "failed" && outcome.packages_checked > 0 {
+        notifications.push(notification(
+            NotifyEvent::UpdatesAvailable,
+            format!("{} update(s) available", outcome.packages_checked),
+            outcome
+                .details
+                .iter()
+                .filter(|d| d.contains(" -> "))
+                .cloned()
+                .collect(),
+        ));
+    }
+    notifications
+}
+
+/// Entries of `current` missing from `seen`.
+fn new_items(seen: &BTreeSet<String>, current: &BTreeSet<String>) -> Vec<String> {
+    current.difference(seen).cloned().collect()
+}
+
+/// Notifications for advisories, a pending reboot and pacnew files that have
+/// appeared since the last run, updating `state` to what is current. Only the
+/// events in `wanted` are looked at.
+fn alert_notifications(
+    handle: &Alpm,
+    wanted: impl Fn(NotifyEvent) -> bool,
+    state: &mut NotifyState,
+    host: &str,
+    timestamp: &str,
+) -> Vec<Notification> {
+    let notification = |event, title: String, details: Vec<String>| Notification {
+        event,
+        host: host.to_string(),
+        timestamp: timestamp.to_string(),
+        schedule: None,
+        title,
+        details,
+    };
+    let mut notifications = Vec::new();
+
+    if wanted(NotifyEvent::SecurityAdvisories) {
+        match load_advisories(handle) {
+            Ok(response) => {
+                let current: BTreeSet<String> = response
+                    .advisories
+                    .iter()
+                    .map(|a| format!("{} {}", a.avg_name, a.package))
+                    .collect();
+                let fresh = new_items(&state.advisories, &current);
+                if !fresh.is_empty() {
+                    let details = response
+                        .advisories
+                        .iter()
+                        .filter(|a| fresh.contains(&format!("{} {}", a.avg_name, a.package)))
+                        .map(|a| {
+                            format!(
+                                "{}: {} ({}, {})",
+                                a.package,
+                                a.avg_name,
+                                a.severity,
+                                a.fixed_version.as_deref().map_or(
+                                    "no fix yet".to_string(),
+                                    |v| format!("fixed in {}", v)
+                                )
+                            )
+                        })
+                        .collect();
+                    notifications.push(notification(
+                        NotifyEvent::SecurityAdvisories,
+                        format!("{} new security advisory(ies)", fresh.len()),
+                        details,
+                    ));
+                }
+                state.advisories = current;
+            }
+            Err(e) => eprintln!("Could not check security advisories: {:#}", e),
+        }
+    }
+
+    if wanted(NotifyEvent::RebootRequired) {
+        match reboot_status(handle) {
+            Ok(status) => {
+                if status.requires_reboot && !state.reboot_required {
+                    let mut details = vec![format!("Reason: {}", status.reason)];
+                    if let (Some(running), Some(installed)) =
+                        (&status.running_kernel, &status.installed_kernel)
+                    {
+                        details.push(format!(
+                            "Running kernel {}, installed {}",
+                            running, installed
+                        ));
+                    }
+                    details.extend(status.updated_packages);
+                    notifications.push(notification(
+                        NotifyEvent::RebootRequired,
+                        "Reboot required".to_string(),
+                        details,
+                    ));
+                }
+                state.reboot_required = status.requires_reboot;
+            }
+            Err(e) => eprintln!("Could not check reboot status: {:#}", e),
+        }
+    }
+
+    if wanted(NotifyEvent::PacnewFiles) {
+        let current: BTreeSet<String> = pacnew_files(handle).into_iter().map(|f| f.path).collect();
+        let fresh = new_items(&state.pacnew, &current);
+        if !fresh.is_empty() {
+            notifications.push(notification(
+                NotifyEvent::PacnewFiles,
+                format!("{} new .pacnew/.pacsave file(s)", fresh.len()),
+                fresh,
+            ));
+        }
+        state.pacnew = current;
+    }
+
+    notifications
+}
+
+fn load_state() -> NotifyState {
+    std::fs::read_to_string(STATE_PATH)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Notify about a recorded scheduled run and any alerts that appeared since
+/// the previous one. Delivery problems go to stderr (the journal); they never
+/// fail the run.
+pub(crate) fn notify_after_run(outcome: &RunOutcome) {
+    let config = match AppConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Notifications skipped, could not load config: {:#}", e);
+            return;
+        }
+    };
+    let notifiers: Vec<(&String, &Notifier)> =
+        config.notifiers.iter().filter(|(_, n)| n.enabled).collect();
+    if notifiers.is_empty() {
+        return;
+    }
+    let wanted = |event| notifiers.iter().any(|(_, n)| n.wants(event));
+    let host = hostname();
+
+    let mut notifications = run_notifications(outcome, &host);
+    notifications.retain(|n| wanted(n.event));
+
+    let alerts = [
+        NotifyEvent::SecurityAdvisories,
+        NotifyEvent::RebootRequired,
+        NotifyEvent::PacnewFiles,
+    ];
+    // The new alert state is saved once delivery is done, so an alert no
+    // sink took is sent again by the next run.
+    let mut alert_state = None;
+    if alerts.into_iter().any(wanted) {
+        let result = std::fs::create_dir_all(STATE_DIR)
+            .context("Failed to create state directory")
+            .and_then(|_| {
+                with_file_lock(Path::new(STATE_LOCK_PATH), || {
+                    let handle = get_handle()?;
+                    let mut state = load_state();
+                    let alerts =
+                        alert_notifications(&handle, wanted, &mut state, &host, outcome.timestamp);
+                    Ok((alerts, state))
+                })
+            });
+        match result {
+            Ok((alerts, state)) => {
+                notifications.extend(alerts);
+                alert_state = Some(state);
+            }
+            Err(e) => eprintln!("Could not check alerts: {:#}", e),
+        }
+    }
+
+    let mut undelivered = Vec::new();
+    for notification in &notifications {
+        let mut delivered = false;
+        for (name, notifier) in notifiers
+            .iter()
+            .filter(|(_, n)| n.wants(notification.event))
+        {
+            match deliver(notifier, notification) {
+                Ok(()) => delivered = true,
+                Err(e) => eprintln!("Notifier {} failed: {:#}", name, e),
+            }
+        }
+        if !delivered {
+            undelivered.push(notification.event);
+        }
+    }
+
+    if let Some(current) = alert_state {
+        let saved = with_file_lock(Path::new(STATE_LOCK_PATH), || {
+            let state = load_state().advance(current, &undelivered);
+            write_json_atomic(Path::new(STATE_PATH), &state)
+        });
+        if let Err(e) = saved {
+            eprintln!("Could not save alert state: {:#}", e);
+        }
+    }
+}
+
+fn deliver(notifier: &Notifier, notification: &Notification) -> Result<()> {
+    match &notifier.sink {
+        NotifierSink::Sendmail { to, from } => send_mail(to, from.as_deref(), notification),
+        NotifierSink::Webhook { url, secret } => post_webhook(url, secret.as_deref(), notification),
+        NotifierSink::Command { argv } => run_command(argv, notification),
+    }
+}
+
+/// Header values come from package metadata and run details; line breaks are
+/// flattened so none of it can start a header of its own.
+fn header_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+fn mail_message(to: &[String], from: Option<&str>, notification: &Notification) -> String {
+    let mut message = format!("To: {}\n", to.join(", "));
+    if let Some(from) = from {
+        message.push_str(&format!("From: {}\n", from));
+    }
+    message.push_str(&format!(
+        "Subject: [cockpit-pacman] {}: {}\n",
+        header_value(&notification.host),
+        header_value(&notification.title)
+    ));
+    message.push_str("MIME-Version: 1.0\n");
+    message.push_str("Content-Type: text/plain; charset=utf-8\n");
+    message.push_str(&format!(
+        "X-Cockpit-Pacman-Event: {}\n\n",
+        notification.event
+    ));
+
+    message.push_str(&notification.title);
+    message.push_str("\n\n");
+    for line in &notification.details {
+        message.push_str(line);
+        message.push('\n');
+    }
+    message.push_str(&format!(
+        "\nHost: {}\nTime: {}\n",
+        notification.host, notification.timestamp
+    ));
+    if let Some(schedule) = &notification.schedule {
+        message.push_str(&format!("Schedule: {}\n", schedule));
+    }
+    message
+}
+
+fn send_mail(to: &[String], from: Option<&str>, notification: &Notification) -> Result<()> {
+    let mut cmd = Command::new(SENDMAIL_PATH);
+    // -t takes the recipients from the headers; -oi keeps a lone "." line
+    // in the body from ending the message.
+    cmd.args(["-t", "-oi"]);
+    let message = mail_message(to, from, notification);
+    let output = output_with_input_timeout(cmd, Some(message.into_bytes()), COMMAND_TIMEOUT)
+        .context("Failed to run sendmail")?;
+    if !output.status.success() {
+        bail!(
+            "sendmail exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `X-Cockpit-Pacman-Signature` value: HMAC-SHA256 of the exact body bytes.
+fn signature(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    format!("sha256={}", to_hex(hmac::sign(&key, body).as_ref()))
+}
+
+fn post_webhook(url: &str, secret: Option<&str>, notification: &Notification) -> Result<()> {
+    let body = serde_json::to_vec(notification)?;
+    let agent = ureq::Agent::new_with_config(
+        ureq::Agent::config_builder()
+            .timeout_global(Some(WEBHOOK_TIMEOUT))
+            .ip_family(crate::util::detected_ip_family())
+            .build(),
+    );
+    let mut request = agent
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Cockpit-Pacman-Event", notification.event.to_string());
+    if let Some(secret) = secret {
+        request = request.header("X-Cockpit-Pacman-Signature", signature(secret, &body));
+    }
+    request
+        .send(&body[..])
+        .with_context(|| format!("Webhook POST to {} failed", url))?;
+    Ok(())
+}
+
+fn run_command(argv: &[String], notification: &Notification) -> Result<()> {
+    let Some((program, args)) = argv.split_first() else {
+        bail!("Command notifier has no program");
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .env("COCKPIT_PACMAN_EVENT", notification.event.to_string())
+        .env("COCKPIT_PACMAN_TITLE", &notification.title);
+    let body = serde_json::to_vec(notification)?;
+    let output = output_with_input_timeout(cmd, Some(body), COMMAND_TIMEOUT)
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+pub fn list_notifiers() -> Result<()> {
+    let config = AppConfig::load()?;
+    let notifiers = config
+        .notifiers
+        .iter()
+        .map(|(name, notifier)| NotifierEntry {
+            name: name.clone(),
+            notifier: notifier.redacted(),
+            has_secret: matches!(
+                &notifier.sink,
+                NotifierSink::Webhook {
+                    secret: Some(_),
+                    ..
+                }
+            ),
+        })
+        .collect();
+    emit_json(&NotifiersResponse { notifiers })
+}
+
+/// Add or replace a notifier. A webhook given without a secret keeps the
+/// stored one when its URL is unchanged, since listings never show secrets.
+pub fn set_notifier(name: &str, mut notifier: Notifier) -> Result<()> {
+    validate_notifier_name(name)?;
+    notifier.validate()?;
+
+    AppConfig::update(|config| {
+        if let (
+            NotifierSink::Webhook { url, secret: None },
+            Some(Notifier {
+                sink:
+                    NotifierSink::Webhook {
+                        url: old_url,
+                        secret: Some(old_secret),
+                    },
+                ..
+            }),
+        ) = (&mut notifier.sink, config.notifiers.get(name))
+            && url == old_url
+        {
+            notifier.sink = NotifierSink::Webhook {
+                url: url.clone(),
+                secret: Some(old_secret.clone()),
+            };
+        }
+        config.notifiers.insert(name.to_string(), notifier);
+        Ok(())
+    })?;
+
+    emit_json(&IgnoreOperationResponse {
+        success: true,
+        package: name.to_string(),
+        message: format!("Notifier '{}' saved", name),
+    })
+}
+
+pub fn remove_notifier(name: &str) -> Result<()> {
+    validate_notifier_name(name)?;
+    let removed = AppConfig::update(|config| Ok(config.notifiers.remove(name).is_some()))?;
+
+    emit_json(&IgnoreOperationResponse {
+        success: removed,
+        package: name.to_string(),
+        message: if removed {
+            format!("Notifier '{}' removed", name)
+        } else {
+            format!("No notifier named '{}'", name)
+        },
+    })
+}
+
+/// Send a test notification through one notifier, even a disabled one.
+pub fn test_notifier(name: &str) -> Result<()> {
+    validate_notifier_name(name)?;
+    let config = AppConfig::load()?;
+    let Some(notifier) = config.notifiers.get(name) else {
+        bail!("No notifier named '{}'", name);
+    };
+    let host = hostname();
+    deliver(
+        notifier,
+        &Notification {
+            event: NotifyEvent::Test,
+            title: format!("Test notification from {}", host),
+            host,
+            timestamp: chrono::Local::now()
+                .format("%Y-%m-%dT%H:%M:%S%z")
+                .to_string(),
+            schedule: None,
+            details: vec![format!("Sent by notifier '{}'", name)],
+        },
+    )?;
+
+    emit_json(&IgnoreOperationResponse {
+        success: true,
+        package: name.to_string(),
+        message: format!("Test notification sent through '{}'", name),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    fn notification(event: NotifyEvent) -> Notification {
+        Notification {
+            event,
+            host: "web1".to_string(),
+            timestamp: "2025-06-01T03:00:00+0000".to_string(),
+            schedule: Some("nightly".to_string()),
+            title: "Scheduled upgrade run failed".to_string(),
+            details: vec!["Failed to sync databases".to_string()],
+        }
+    }
+
+    fn outcome<'a>(mode: &'a str, status: &'a str, details: &'a [String]) -> RunOutcome<'a> {
+        RunOutcome {
+            timestamp: "t",
+            schedule: None,
+            mode,
+            status,
+            packages_checked: 2,
+            error: Some("boom"),
+            details,
+        }
+    }
+
+    fn events(notifications: &[Notification]) -> Vec<NotifyEvent> {
+        notifications.iter().map(|n| n.event).collect()
+    }
+
+    #[test]
+    fn undelivered_alerts_stay_unseen() {
+        let set = |xs: &[&str]| xs.iter().map(|s| s.to_string()).collect();
+        let previous = NotifyState {
+            advisories: set(&["AVG-1 openssl"]),
+            pacnew: set(&["/etc/a.pacnew"]),
+            reboot_required: false,
+        };
+        let current = NotifyState {
+            advisories: set(&["AVG-1 openssl", "AVG-2 curl"]),
+            pacnew: set(&["/etc/a.pacnew", "/etc/b.pacnew"]),
+            reboot_required: true,
+        };
+
+        let all_sent = previous.clone().advance(current.clone(), &[]);
+        assert_eq!(all_sent, current);
+
+        let state = previous.clone().advance(
+            current.clone(),
+            &[NotifyEvent::SecurityAdvisories, NotifyEvent::RebootRequired],
+        );
+        assert_eq!(state.advisories, previous.advisories);
+        assert!(!state.reboot_required);
+        assert_eq!(state.pacnew, current.pacnew);
+    }
+
+    #[test]
+    fn run_events_follow_status_and_mode() {
+        let details = vec!["linux -> 6.10-1".to_string(), "Held back: x".to_string()];
+
+        let ok = run_notifications(&outcome("upgrade", "ok", &details), "h");
+        assert_eq!(events(&ok), vec![NotifyEvent::UpdatesAvailable]);
+        assert_eq!(ok[0].details, vec!["linux -> 6.10-1"]);
+
+        let failed = run_notifications(&outcome("upgrade", "failed", &details), "h");
+        assert_eq!(events(&failed), vec![NotifyEvent::RunFailed]);
+        assert_eq!(failed[0].details[0], "boom");
+
+        let skipped = run_notifications(&outcome("security", "skipped", &details), "h");
+        assert_eq!(
+            events(&skipped),
+            vec![NotifyEvent::RunSkipped, NotifyEvent::UpdatesAvailable]
+        );
+
+        // Maintenance tasks never report updates.
+        assert!(run_notifications(&outcome("cache-clean", "ok", &details), "h").is_empty());
+    }
+
+    #[test]
+    fn only_unseen_items_are_new() {
+        let seen: BTreeSet<String> = ["a", "b"].iter().map(|s| s.to_string()).collect();
+        let current: BTreeSet<String> = ["b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(new_items(&seen, &current), vec!["c"]);
+        assert!(new_items(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn mail_headers_cannot_be_injected() {
+        let mut n = notification(NotifyEvent::RunFailed);
+        n.title = "oops\nBcc: victim@example.com".to_string();
+        let message = mail_message(&["root@example.com".to_string()], Some("pacman@web1"), &n);
+        let headers = message.split("\n\n").next().unwrap();
+        assert!(headers.contains("Subject: [cockpit-pacman] web1: oops Bcc: victim@example.com"));
+        assert!(!headers.lines().any(|l| l.starts_with("Bcc:")));
+        assert!(headers.contains("To: root@example.com\nFrom: pacman@web1\n"));
+    }
+
+    /// Accept one request on a local listener and return its head and body.
+    fn serve_once() -> (String, std::thread::JoinHandle<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let length: usize = head
+                .lines()
+                .find_map(|l| {
+                    let (name, value) = l.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse().unwrap())
+                })
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            (head, body)
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn webhook_posts_signed_json() {
+        let (url, server) = serve_once();
+        let n = notification(NotifyEvent::RunFailed);
+        post_webhook(&url, Some("s3cret"), &n).unwrap();
+
+        let (head, body) = server.join().unwrap();
+        let head = head.to_lowercase();
+        assert!(head.starts_with("post /hook "));
+        assert!(head.contains("x-cockpit-pacman-event: run_failed"));
+        assert!(head.contains(&format!(
+            "x-cockpit-pacman-signature: {}",
+            signature("s3cret", &body)
+        )));
+
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["event"], "run_failed");
+        assert_eq!(payload["host"], "web1");
+        assert_eq!(payload["schedule"], "nightly");
+        assert_eq!(payload["details"][0], "Failed to sync databases");
+    }
+
+    #[test]
+    fn webhook_without_secret_is_unsigned() {
+        let (url, server) = serve_once();
+        post_webhook(&url, None, &notification(NotifyEvent::RunSkipped)).unwrap();
+        let (head, _) = server.join().unwrap();
+        assert!(!head.to_lowercase().contains("x-cockpit-pacman-signature"));
+    }
+
+    #[test]
+    fn command_gets_payload_on_stdin_and_event_in_env() {
+        let argv: Vec<String> = [
+            "/bin/sh",
+            "-c",
+            r#"test "$COCKPIT_PACMAN_EVENT" = run_failed && grep -q '"host":"web1"'"#,
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        run_command(&argv, &notification(NotifyEvent::RunFailed)).unwrap();
+        assert!(run_command(&argv, &notification(NotifyEvent::RunSkipped)).is_err());
+    }
+
+    #[test]
+    fn notifier_config_shape_and_validation() {
+        let notifier: Notifier = serde_json::from_str(
+            r#"{"type":"webhook","url":"https://hooks.example.com/x","secret":"k","events":["run_failed"]}"#,
+        )
+        .unwrap();
+        assert!(notifier.enabled);
+        assert!(notifier.wants(NotifyEvent::RunFailed));
+        assert!(!notifier.wants(NotifyEvent::UpdatesAvailable));
+        assert!(notifier.wants(NotifyEvent::Test));
+        notifier.validate().unwrap();
+        assert_eq!(
+            serde_json::to_value(notifier.redacted()).unwrap(),
+            serde_json::json!({
+                "type": "webhook",
+                "url": "https://hooks.example.com/x",
+                "events": ["run_failed"],
+                "enabled": true
+            })
+        );
+
+        let bad = [
+            r#"{"type":"sendmail","to":[]}"#,
+            r#"{"type":"sendmail","to":["root@x\nBcc: y@z"]}"#,
+            r#"{"type":"webhook","url":"file:///etc/passwd"}"#,
+            r#"{"type":"command","argv":["notify-send"]}"#,
+        ];
+        for json in bad {
+            let notifier: Notifier = serde_json::from_str(json).unwrap();
+            assert!(notifier.validate().is_err(), "{json} should be rejected");
+        }
+    }
+}
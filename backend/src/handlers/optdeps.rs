@@ -0,0 +1,99 @@
+use alpm::{Alpm, Dep};
+use anyhow::Result;
+
+use crate::alpm::{dep_string, get_handle};
+use crate::db::get_repo_map;
+use crate::models::{
+    OptionalDependenciesResponse, OptionalDependency, OptionalOnlyPackage, OptionalOnlyResponse,
+};
+use crate::util::emit_json;
+
+/// Resolve one optdepends entry: the installed package satisfying it (which
+/// may be a provider rather than the named package) and, failing that, the
+/// sync package pacman would pick to install it.
+pub(crate) fn resolve_optdepend(handle: &Alpm, dep: &Dep) -> OptionalDependency {
+    let depend = dep_string(dep);
+    let satisfied_by = handle
+        .localdb()
+        .pkgs()
+        .find_satisfier(depend.as_str())
+        .map(|p| p.name().to_string());
+    let provider = handle.syncdbs().find_satisfier(depend.as_str());
+
+    OptionalDependency {
+        name: dep.name().to_string(),
+        description: dep.desc().map(|s| s.to_string()),
+        installed: satisfied_by.is_some(),
+        satisfied_by,
+        provider: provider.map(|p| p.name().to_string()),
+        provider_repository: provider
+            .and_then(|p| p.db())
+            .map(|db| db.name().to_string()),
+        depend,
+    }
+}
+
+pub fn optional_dependencies(name: &str) -> Result<()> {
+    let handle = get_handle()?;
+
+    // Installed packages take precedence; otherwise show what installing the
+    // sync package would offer.
+    let (pkg, installed) = match handle.localdb().pkg(name) {
+        Ok(pkg) => (pkg, true),
+        Err(_) => {
+            let pkg = handle
+                .syncdbs()
+                .iter()
+                .find_map(|db| db.pkg(name).ok())
+                .ok_or_else(|| anyhow::anyhow!("Package '{}' not found", name))?;
+            (pkg, false)
+        }
+    };
+
+    let optdepends = pkg
+        .optdepends()
+        .iter()
+        .map(|dep| resolve_optdepend(&handle, dep))
+        .collect();
+
+    let response = OptionalDependenciesResponse {
+        package: pkg.name().to_string(),
+        installed,
+        optdepends,
+    };
+    emit_json(&response)
+}
+
+/// Dependencies nothing requires any more but that some installed package
+/// still lists as optional. `list-orphans` deliberately leaves these out, so
+/// this is the other half of an orphan cleanup decision.
+pub fn list_optional_only() -> Result<()> {
+    let handle = get_handle()?;
+    let repo_map = get_repo_map(&handle);
+
+    let packages: Vec<OptionalOnlyPackage> = handle
+        .localdb()
+        .pkgs()
+        .iter()
+        .filter(|pkg| pkg.reason() == alpm::PackageReason::Depend && pkg.required_by().is_empty())
+        .filter_map(|pkg| {
+            let optional_for: Vec<String> = pkg.optional_for().into_iter().collect();
+            (!optional_for.is_empty()).then(|| OptionalOnlyPackage {
+                name: pkg.name().to_string(),
+                version: pkg.version().to_string(),
+                description: pkg.desc().map(|s| s.to_string()),
+                installed_size: pkg.isize(),
+                optional_for,
+                repository: repo_map.get(pkg.name()).map(|s| s.to_string()),
+            })
+        })
+        .collect();
+
+    let total_size: i64 = packages.iter().map(|p| p.installed_size).sum();
+
+    let response = OptionalOnlyResponse {
+        packages,
+        total_size,
+    };
+    emit_json(&response)
+}
@@ -0,0 +1,249 @@
+use alpm::Alpm;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::alpm::get_handle;
+use crate::db::get_repo_map;
+use crate::models::{ExportedPackage, ImportPlan, ImportTarget, PackageExport};
+use crate::util::emit_json;
+use crate::validation::validate_package_name;
+
+const FOREIGN_HEADER: &str = "# foreign";
+
+/// Explicitly installed packages, split by whether a sync repo carries them.
+/// Foreign packages (AUR builds, local installs) can't be reinstalled from the
+/// repos, so an import reports them instead of trying.
+pub(crate) fn collect_explicit(handle: &Alpm, with_versions: bool) -> PackageExport {
+    let repo_map = get_repo_map(handle);
+    let mut export = PackageExport::default();
+
+    for pkg in handle.localdb().pkgs() {
+        if pkg.reason() != alpm::PackageReason::Explicit {
+            continue;
+        }
+        let entry = ExportedPackage {
+            name: pkg.name().to_string(),
+            version: with_versions.then(|| pkg.version().to_string()),
+        };
+        if repo_map.contains_key(pkg.name()) {
+            export.native.push(entry);
+        } else {
+            export.foreign.push(entry);
+        }
+    }
+
+    export.native.sort_by(|a, b| a.name.cmp(&b.name));
+    export.foreign.sort_by(|a, b| a.name.cmp(&b.name));
+    export
+}
+
+/// Plain-text list in `pacman -Qqe` form: one name per line, `name=version`
+/// when pinned, foreign packages after a `# foreign` marker.
+pub(crate) fn format_text(export: &PackageExport) -> String {
+    let line = |p: &ExportedPackage| match &p.version {
+        Some(v) => format!("{}={}\n", p.name, v),
+        None => format!("{}\n", p.name),
+    };
+    let mut out: String = export.native.iter().map(line).collect();
+    if !export.foreign.is_empty() {
+        out.push_str(FOREIGN_HEADER);
+        out.push('\n');
+        out.extend(export.foreign.iter().map(line));
+    }
+    out
+}
+
+fn parse_text_line(line: &str) -> Result<ExportedPackage> {
+    // `name=version` from our own export, or `name version` as printed by
+    // `pacman -Qe`.
+    let (name, version) = match line.split_once('=') {
+        Some((n, v)) => (n.trim(), Some(v.trim())),
+        None => {
+            let mut parts = line.split_whitespace();
+            let name = parts.next().unwrap_or_default();
+            (name, parts.next())
+        }
+    };
+    validate_package_name(name)?;
+    Ok(ExportedPackage {
+        name: name.to_string(),
+        version: version.filter(|v| !v.is_empty()).map(|v| v.to_string()),
+    })
+}
+
+/// Accept either the JSON export or the text form (including bare
+/// `pacman -Qqe` output).
+pub(crate) fn parse_package_list(input: &str) -> Result<PackageExport> {
+    let trimmed = input.trim_start();
+    if trimmed.starts_with('{') {
+        let export: PackageExport = serde_json::from_str(trimmed)
+            .map_err(|e| anyhow::anyhow!("Invalid package list JSON: {}", e))?;
+        for p in export.native.iter().chain(&export.foreign) {
+            validate_package_name(&p.name)?;
+        }
+        return Ok(export);
+    }
+
+    let mut export = PackageExport::default();
+    let mut in_foreign = false;
+    for line in input.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            match comment.trim().to_lowercase().as_str() {
+                "foreign" => in_foreign = true,
+                "native" => in_foreign = false,
+                _ => {}
+            }
+            continue;
+        }
+        let entry = parse_text_line(line)?;
+        if in_foreign {
+            export.foreign.push(entry);
+        } else {
+            export.native.push(entry);
+        }
+    }
+    Ok(export)
+}
+
+/// Diff a package list against the local db. Each name lands in exactly one
+/// bucket; duplicates are folded.
+pub(crate) fn plan_import(handle: &Alpm, list: &PackageExport) -> ImportPlan {
+    let localdb = handle.localdb();
+    let mut seen = HashSet::new();
+    let mut plan = ImportPlan {
+        to_install: Vec::new(),
+        already_installed: Vec::new(),
+        foreign: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    let entries = list
+        .native
+        .iter()
+        .map(|p| (p, false))
+        .chain(list.foreign.iter().map(|p| (p, true)));
+    for (entry, listed_foreign) in entries {
+        if !seen.insert(entry.name.clone()) {
+            continue;
+        }
+        if localdb.pkg(entry.name.as_str()).is_ok() {
+            plan.already_installed.push(entry.name.clone());
+            continue;
+        }
+        let sync_pkg = handle
+            .syncdbs()
+            .iter()
+            .find_map(|db| db.pkg(entry.name.as_str()).ok());
+        match sync_pkg {
+            Some(pkg) => plan.to_install.push(ImportTarget {
+                name: entry.name.clone(),
+                version: pkg.version().to_string(),
+                repository: pkg.db().map(|db| db.name().to_string()).unwrap_or_default(),
+                pinned_version: entry.version.clone(),
+            }),
+            None if listed_foreign => plan.foreign.push(entry.name.clone()),
+            None => plan.unknown.push(entry.name.clone()),
+        }
+    }
+
+    plan
+}
+
+pub fn export_packages(format: Option<&str>, with_versions: bool) -> Result<()> {
+    let handle = get_handle()?;
+    let export = collect_explicit(&handle, with_versions);
+
+    match format.unwrap_or("json") {
+        "json" => emit_json(&export),
+        "text" => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(format_text(&export).as_bytes())?;
+            stdout.flush()?;
+            Ok(())
+        }
+        other => anyhow::bail!("Invalid export format '{}': expected json or text", other),
+    }
+}
+
+pub fn import_plan(list: &str) -> Result<()> {
+    let list = parse_package_list(list)?;
+    let handle = get_handle()?;
+    emit_json(&plan_import(&handle, &list))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, version: Option<&str>) -> ExportedPackage {
+        ExportedPackage {
+            name: name.to_string(),
+            version: version.map(|v| v.to_string()),
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let export = PackageExport {
+            native: vec![pkg("bash", None), pkg("linux", Some("6.9.1.arch1-1"))],
+            foreign: vec![pkg("yay-bin", Some("12.3.5-1"))],
+        };
+        let text = format_text(&export);
+        assert_eq!(
+            text,
+            "bash\nlinux=6.9.1.arch1-1\n# foreign\nyay-bin=12.3.5-1\n"
+        );
+        assert_eq!(parse_package_list(&text).ok(), Some(export));
+    }
+
+    #[test]
+    fn text_without_foreign_has_no_marker() {
+        let export = PackageExport {
+            native: vec![pkg("vim", None)],
+            foreign: Vec::new(),
+        };
+        assert_eq!(format_text(&export), "vim\n");
+    }
+
+    #[test]
+    fn parses_pacman_qe_output_and_comments() {
+        let list = parse_package_list("# my laptop\nbase 3-2\n\n  git 2.45.2-1  \nnano\n").unwrap();
+        assert_eq!(
+            list.native,
+            vec![
+                pkg("base", Some("3-2")),
+                pkg("git", Some("2.45.2-1")),
+                pkg("nano", None)
+            ]
+        );
+        assert!(list.foreign.is_empty());
+    }
+
+    #[test]
+    fn parses_json_export() {
+        let list = parse_package_list(
+            r#"{"native":[{"name":"bash"}],"foreign":[{"name":"paru","version":"2.0.3-1"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(list.native, vec![pkg("bash", None)]);
+        assert_eq!(list.foreign, vec![pkg("paru", Some("2.0.3-1"))]);
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(parse_package_list("good\nbad;rm -rf\n").is_err());
+        assert!(parse_package_list(r#"{"native":[{"name":"../etc"}]}"#).is_err());
+        assert!(parse_package_list("{not json").is_err());
+    }
+
+    #[test]
+    fn json_version_omitted_when_unpinned() {
+        let v = serde_json::to_value(pkg("bash", None)).unwrap();
+        assert!(v.get("version").is_none());
+    }
+}
@@ -1,3 +1,4 @@
+use alpm::Alpm;
 use anyhow::Result;
 use std::collections::HashSet;
 use std::path::Path;
@@ -12,13 +13,22 @@ fn merge_file_path(backup_name: &str, kind: &str) -> String {
     format!("/{}.{}", backup_name.trim_start_matches('/'), kind)
 }
 
+pub fn get_pacnew_status() -> Result<()> {
+    let handle = get_handle()?;
+    let files = pacnew_files(&handle);
+
+    emit_json(&PacnewStatus {
+        has_pacnew: !files.is_empty(),
+        files,
+    })
+}
+
 // Scans the backup arrays of installed packages, matching pacdiff's default
 // db-scan mode. A .pacsave left by a fully removed package is not attributable
 // here because the package is gone from the local db. Runs as the cockpit user,
 // so files under directories it cannot traverse (e.g. 0750 /etc/sudoers.d) are
 // skipped: exists() returns false on a stat permission error.
-pub fn get_pacnew_status() -> Result<()> {
-    let handle = get_handle()?;
+pub(crate) fn pacnew_files(handle: &Alpm) -> Vec<PacnewFile> {
     let localdb = handle.localdb();
 
     let mut seen: HashSet<String> = HashSet::new();
@@ -41,11 +51,7 @@ pub fn get_pacnew_status() -> Result<()> {
     }
 
     files.sort_by(|a, b| a.path.cmp(&b.path));
-
-    emit_json(&PacnewStatus {
-        has_pacnew: !files.is_empty(),
-        files,
-    })
+    files
 }
 
 #[cfg(test)]
@@ -0,0 +1,291 @@
+use alpm::Alpm;
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use crate::config::{
+    AppConfig, IgnoreOperationResponse, PoliciesResponse, PolicyEntry, UpdatePolicy,
+};
+use crate::util::emit_json;
+
+/// A hold that has run out, found by `expired_holds`.
+pub(crate) struct ExpiredHold {
+    pub name: String,
+    pub group: bool,
+    pub message: String,
+}
+
+fn vercmp(a: &str, b: &str) -> std::cmp::Ordering {
+    alpm::vercmp(a, b)
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+fn available_version(handle: &Alpm, name: &str) -> Option<String> {
+    handle
+        .syncdbs()
+        .iter()
+        .find_map(|db| db.pkg(name).ok())
+        .map(|p| p.version().to_string())
+}
+
+pub(crate) fn expired_holds(
+    config: &AppConfig,
+    today: NaiveDate,
+    available: impl Fn(&str) -> Option<String>,
+    vercmp: impl Fn(&str, &str) -> std::cmp::Ordering,
+) -> Vec<ExpiredHold> {
+    let packages = config.package_policies.iter().filter_map(|(name, policy)| {
+        let why = policy.hold_expiry(available(name).as_deref(), today, &vercmp)?;
+        Some(ExpiredHold {
+            name: name.clone(),
+            group: false,
+            message: format!("Hold on {} expired: {}", name, why),
+        })
+    });
+    let groups = config.group_policies.iter().filter_map(|(name, policy)| {
+        let why = policy.hold_expiry(None, today, &vercmp)?;
+        Some(ExpiredHold {
+            name: name.clone(),
+            group: true,
+            message: format!("Hold on group {} expired: {}", name, why),
+        })
+    });
+    packages.chain(groups).collect()
+}
+
+/// Messages for holds that have run out, for reporting without touching the
+/// config (check-updates may run unprivileged).
+pub(crate) fn expired_hold_messages(handle: &Alpm, config: &AppConfig) -> Vec<String> {
+    expired_holds(config, today(), |n| available_version(handle, n), vercmp)
+        .into_iter()
+        .map(|e| e.message)
+        .collect()
+}
+
+/// Drop holds that have run out from the app config, returning one message
+/// per hold removed so the caller can log it.
+pub(crate) fn prune_expired_holds(handle: &Alpm) -> Result<Vec<String>> {
+    let today = today();
+    let available = |n: &str| available_version(handle, n);
+    if expired_holds(&AppConfig::load()?, today, available, vercmp).is_empty() {
+        return Ok(Vec::new());
+    }
+    AppConfig::update(|config| {
+        let expired = expired_holds(config, today, available, vercmp);
+        for hold in &expired {
+            if hold.group {
+                config.group_policies.remove(&hold.name);
+            } else {
+                config.package_policies.remove(&hold.name);
+            }
+        }
+        Ok(expired.into_iter().map(|e| e.message).collect())
+    })
+}
+
+fn is_group(kind: &str) -> Result<bool> {
+    match kind {
+        "package" => Ok(false),
+        "group" => Ok(true),
+        other => anyhow::bail!("Invalid policy kind '{}': expected package or group", other),
+    }
+}
+
+pub fn list_policies() -> Result<()> {
+    let handle = crate::alpm::get_handle()?;
+    let config = AppConfig::load()?;
+    let today = today();
+
+    let entry = |name: &String, policy: &UpdatePolicy, group: bool| PolicyEntry {
+        name: name.clone(),
+        policy: policy.clone(),
+        description: policy.describe(None),
+        expired: policy.hold_expiry(
+            if group {
+                None
+            } else {
+                available_version(&handle, name)
+            }
+            .as_deref(),
+            today,
+            vercmp,
+        ),
+    };
+
+    emit_json(&PoliciesResponse {
+        packages: config
+            .package_policies
+            .iter()
+            .map(|(n, p)| entry(n, p, false))
+            .collect(),
+        groups: config
+            .group_policies
+            .iter()
+            .map(|(n, p)| entry(n, p, true))
+            .collect(),
+    })
+}
+
+pub fn set_policy(kind: &str, name: &str, policy: UpdatePolicy) -> Result<()> {
+    let group = is_group(kind)?;
+    policy.validate(group)?;
+    let description = policy.describe(None);
+    AppConfig::update(|config| {
+        let policies = if group {
+            &mut config.group_policies
+        } else {
+            &mut config.package_policies
+        };
+        policies.insert(name.to_string(), policy);
+        Ok(())
+    })?;
+
+    emit_json(&IgnoreOperationResponse {
+        success: true,
+        package: name.to_string(),
+        message: format!("Policy for {} '{}' set: {}", kind, name, description),
+    })
+}
+
+pub fn remove_policy(kind: &str, name: &str) -> Result<()> {
+    let group = is_group(kind)?;
+    let removed = AppConfig::update(|config| {
+        let policies = if group {
+            &mut config.group_policies
+        } else {
+            &mut config.package_policies
+        };
+        Ok(policies.remove(name).is_some())
+    })?;
+
+    emit_json(&IgnoreOperationResponse {
+        success: removed,
+        package: name.to_string(),
+        message: if removed {
+            format!("Policy for {} '{}' removed", kind, name)
+        } else {
+            format!("No policy set for {} '{}'", kind, name)
+        },
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::config::PolicyAction;
+    use std::cmp::Ordering;
+
+    fn hold(until_version: Option<&str>, until_date: Option<&str>) -> UpdatePolicy {
+        UpdatePolicy {
+            action: PolicyAction::Hold,
+            until_version: until_version.map(|s| s.to_string()),
+            until_date: until_date.map(|s| s.to_string()),
+            reason: Some("regression".to_string()),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    // Plain string order is enough for the single-digit versions used here.
+    fn cmp(a: &str, b: &str) -> Ordering {
+        a.cmp(b)
+    }
+
+    #[test]
+    fn holds_expire_by_version_or_date() {
+        let by_version = hold(Some("2-1"), None);
+        assert_eq!(
+            by_version.hold_expiry(Some("1-5"), date("2030-01-01"), cmp),
+            None
+        );
+        assert_eq!(
+            by_version
+                .hold_expiry(Some("2-1"), date("2030-01-01"), cmp)
+                .as_deref(),
+            Some("version 2-1 is available")
+        );
+
+        let by_date = hold(None, Some("2025-07-01"));
+        assert_eq!(by_date.hold_expiry(None, date("2025-06-30"), cmp), None);
+        assert!(by_date.hold_expiry(None, date("2025-07-01"), cmp).is_some());
+
+        assert_eq!(
+            hold(None, None).hold_expiry(Some("9"), date("2099-01-01"), cmp),
+            None
+        );
+    }
+
+    #[test]
+    fn package_policies_beat_group_policies() {
+        let mut config = AppConfig::default();
+        config
+            .group_policies
+            .insert("plasma".to_string(), hold(None, None));
+        let mut auto = hold(None, None);
+        auto.action = PolicyAction::Auto;
+        auto.reason = None;
+        config
+            .package_policies
+            .insert("kwin".to_string(), auto.clone());
+
+        let (policy, group) = config.policy_for("kwin", &["plasma"]).unwrap();
+        assert_eq!((policy, group), (&auto, None));
+        let (policy, group) = config.policy_for("plasma-desktop", &["plasma"]).unwrap();
+        assert_eq!(policy.action, PolicyAction::Hold);
+        assert_eq!(group, Some("plasma"));
+        assert!(config.policy_for("bash", &[]).is_none());
+    }
+
+    #[test]
+    fn expired_holds_are_named() {
+        let mut config = AppConfig::default();
+        config
+            .package_policies
+            .insert("linux".to_string(), hold(Some("6.10-1"), None));
+        config
+            .group_policies
+            .insert("plasma".to_string(), hold(None, Some("2025-01-01")));
+        let expired = expired_holds(
+            &config,
+            date("2025-02-01"),
+            |_| Some("6.10-1".to_string()),
+            cmp,
+        );
+        let messages: Vec<&str> = expired.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Hold on linux expired: version 6.10-1 is available",
+                "Hold on group plasma expired: held until 2025-01-01",
+            ]
+        );
+    }
+
+    #[test]
+    fn validation_rejects_inconsistent_policies() {
+        assert!(
+            hold(Some("2-1"), Some("2025-07-01"))
+                .validate(false)
+                .is_ok()
+        );
+        assert!(hold(Some("2-1"), None).validate(true).is_err());
+        assert!(hold(None, Some("next week")).validate(false).is_err());
+        let mut notify = hold(None, Some("2025-07-01"));
+        notify.action = PolicyAction::NotifyOnly;
+        assert!(notify.validate(false).is_err());
+    }
+
+    #[test]
+    fn descriptions_read_naturally() {
+        assert_eq!(
+            hold(None, Some("2025-07-01")).describe(Some("plasma")),
+            "held until 2025-07-01 (group plasma): regression"
+        );
+    }
+}
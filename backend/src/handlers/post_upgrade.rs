@@ -0,0 +1,308 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::alpm::get_handle;
+use crate::config::{PostUpgradePolicy, RebootWindow, ScheduleSetResponse};
+use crate::handlers::reboot::reboot_status;
+use crate::handlers::services::{restart_service, services_status};
+use crate::inhibit::{cancel_scheduled_shutdown, schedule_reboot, scheduled_shutdown};
+use crate::models::{RestartBlocked, ServiceRestart};
+use crate::util::emit_json;
+
+/// A reboot is never scheduled sooner than this after the upgrade, so logged-in
+/// users get logind's warning.
+const REBOOT_DELAY_MINS: i64 = 5;
+/// When the reboot a run scheduled is due, in CLOCK_REALTIME microseconds, so
+/// cancel-pending-reboot leaves other scheduled reboots alone.
+const REBOOT_RECORD_PATH: &str = "/var/lib/cockpit-pacman/scheduled-reboot";
+
+/// Restart services and schedule a reboot as `policy` asks, after a committed
+/// scheduled upgrade. Returns what was done, for the run's details; nothing
+/// here fails the run.
+pub(crate) fn post_upgrade_actions(policy: &PostUpgradePolicy) -> Vec<String> {
+    let mut details = Vec::new();
+    if policy.restart_services {
+        match services_status() {
+            Ok(status) => details.extend(restart_services(&status.services, restart_service)),
+            Err(e) => details.push(format!("Could not check services to restart: {:#}", e)),
+        }
+    }
+    if let Some(window) = &policy.reboot_window {
+        details.extend(reboot_in_window(window));
+    }
+    details
+}
+
+fn blocked_reason(blocked: RestartBlocked) -> &'static str {
+    match blocked {
+        RestartBlocked::SessionCritical => "needed by the graphical session",
+        RestartBlocked::CockpitSession => "needed by the Cockpit session",
+        RestartBlocked::CockpitTransport => "may carry the Cockpit connection",
+    }
+}
+
+fn restart_services(
+    services: &[ServiceRestart],
+    restart: impl Fn(&str) -> Result<()>,
+) -> Vec<String> {
+    services
+        .iter()
+        .map(|service| match service.restart_blocked {
+            Some(blocked) => format!(
+                "Restart skipped: {} ({})",
+                service.name,
+                blocked_reason(blocked)
+            ),
+            None => match restart(&service.name) {
+                // RestartUnit returns once the job is queued, not done.
+                Ok(()) => format!("Restart queued for {}", service.name),
+                Err(e) => format!("Failed to restart {}: {:#}", service.name, e),
+            },
+        })
+        .collect()
+}
+
+fn format_time(time: &DateTime<Local>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%z").to_string()
+}
+
+/// When to reboot: the first moment inside `window` at least
+/// `REBOOT_DELAY_MINS` from `now`.
+fn reboot_time(window: &RebootWindow, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let earliest = now + chrono::Duration::minutes(REBOOT_DELAY_MINS);
+    first_valid_time(window, earliest.naive_local(), |at| {
+        Local.from_local_datetime(at).earliest()
+    })
+}
+
+/// The first local time from `from` inside `window` that `resolve` maps to an
+/// instant. Times skipped by a DST change map to none; the search steps past
+/// them, on to the next day's window if they cover the rest of this one.
+fn first_valid_time<T>(
+    window: &RebootWindow,
+    from: NaiveDateTime,
+    resolve: impl Fn(&NaiveDateTime) -> Option<T>,
+) -> Option<T> {
+    let mut at = window.next_from(from);
+    // A week of minutes, far longer than any real clock change.
+    for _ in 0..7 * 24 * 60 {
+        if let Some(instant) = resolve(&at) {
+            return Some(instant);
+        }
+        at = window.next_from(at + chrono::Duration::minutes(1));
+    }
+    None
+}
+
+fn reboot_in_window(window: &str) -> Vec<String> {
+    let window: RebootWindow = match window.parse() {
+        Ok(window) => window,
+        Err(e) => return vec![format!("Reboot not scheduled: {:#}", e)],
+    };
+    let status = match get_handle().and_then(|handle| reboot_status(&handle)) {
+        Ok(status) => status,
+        Err(e) => {
+            return vec![format!(
+                "Could not check whether a reboot is needed: {:#}",
+                e
+            )];
+        }
+    };
+    if !status.requires_reboot {
+        return Vec::new();
+    }
+
+    match scheduled_shutdown() {
+        Ok(Some((kind, usec))) => {
+            let at = DateTime::from_timestamp_micros(usec as i64)
+                .map(|t| format_time(&t.with_timezone(&Local)))
+                .unwrap_or_default();
+            return vec![format!(
+                "Reboot needed ({}) but not scheduled: a {} is already scheduled for {}",
+                status.reason, kind, at
+            )];
+        }
+        Ok(None) => {}
+        Err(e) => return vec![format!("Reboot not scheduled: {:#}", e)],
+    }
+
+    let Some(at) = reboot_time(&window, Local::now()) else {
+        return vec![format!(
+            "Reboot needed ({}) but not scheduled: no valid local time in the window",
+            status.reason
+        )];
+    };
+    let usec = at.timestamp_micros() as u64;
+    if let Err(e) = schedule_reboot(usec) {
+        return vec![format!("Failed to schedule reboot: {:#}", e)];
+    }
+    let mut details = vec![format!(
+        "Reboot scheduled for {} ({}); cancel with cancel-pending-reboot",
+        format_time(&at),
+        status.reason
+    )];
+    if let Err(e) = record_reboot(usec) {
+        details.push(format!(
+            "Could not record the scheduled reboot, so cancel-pending-reboot won't cancel it: {:#}",
+            e
+        ));
+    }
+    details
+}
+
+fn record_reboot(usec: u64) -> Result<()> {
+    let path = std::path::Path::new(REBOOT_RECORD_PATH);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, usec.to_string())?;
+    Ok(())
+}
+
+fn recorded_reboot() -> Option<u64> {
+    std::fs::read_to_string(REBOOT_RECORD_PATH)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+}
+
+/// The reboot to cancel: the scheduled shutdown, if it is the reboot a run
+/// recorded. Otherwise why nothing is cancelled.
+fn reboot_to_cancel(
+    scheduled: Option<(String, u64)>,
+    recorded: Option<u64>,
+) -> std::result::Result<u64, String> {
+    match scheduled {
+        Some((kind, usec)) if kind == "reboot" && recorded == Some(usec) => Ok(usec),
+        Some((kind, _)) if kind == "reboot" => {
+            Err("The scheduled reboot was not set by a scheduled run; left in place".to_string())
+        }
+        Some((kind, _)) => Err(format!(
+            "A {} is scheduled, not a reboot; left in place",
+            kind
+        )),
+        None => Err("No reboot is scheduled".to_string()),
+    }
+}
+
+/// Cancel the reboot a scheduled run set up through logind. Reboots and other
+/// shutdowns scheduled by anything else are left alone.
+pub fn cancel_pending_reboot() -> Result<()> {
+    let (success, message) = match reboot_to_cancel(scheduled_shutdown()?, recorded_reboot()) {
+        Ok(usec) => {
+            cancel_scheduled_shutdown()?;
+            let _ = std::fs::remove_file(REBOOT_RECORD_PATH);
+            let at = DateTime::from_timestamp_micros(usec as i64)
+                .map(|t| format_time(&t.with_timezone(&Local)))
+                .unwrap_or_default();
+            (true, format!("Cancelled the reboot scheduled for {}", at))
+        }
+        Err(message) => (false, message),
+    };
+    emit_json(&ScheduleSetResponse { success, message })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, restart_blocked: Option<RestartBlocked>) -> ServiceRestart {
+        ServiceRestart {
+            name: name.to_string(),
+            pid: 1,
+            affected_packages: vec!["openssl".to_string()],
+            reason: "deleted_mappings".to_string(),
+            restart_blocked,
+        }
+    }
+
+    #[test]
+    fn blocked_services_are_never_restarted() {
+        let services = [
+            service("nginx.service", None),
+            service("cockpit.service", Some(RestartBlocked::CockpitSession)),
+            service("postgresql.service", None),
+        ];
+        let restarted = std::cell::RefCell::new(Vec::new());
+        let details = restart_services(&services, |unit| {
+            restarted.borrow_mut().push(unit.to_string());
+            if unit == "postgresql.service" {
+                anyhow::bail!("job failed");
+            }
+            Ok(())
+        });
+
+        assert_eq!(
+            restarted.into_inner(),
+            vec!["nginx.service", "postgresql.service"]
+        );
+        assert_eq!(
+            details,
+            vec![
+                "Restart queued for nginx.service",
+                "Restart skipped: cockpit.service (needed by the Cockpit session)",
+                "Failed to restart postgresql.service: job failed",
+            ]
+        );
+    }
+
+    #[test]
+    fn reboot_waits_for_the_window_and_the_delay() {
+        let window: RebootWindow = "02:00-05:00".parse().unwrap();
+        let local = |s: &str| {
+            Local
+                .from_local_datetime(&s.parse().unwrap())
+                .earliest()
+                .unwrap()
+        };
+
+        // Inside the window: after the warning delay.
+        assert_eq!(
+            reboot_time(&window, local("2025-06-01T03:00:00")),
+            Some(local("2025-06-01T03:05:00"))
+        );
+        // Before it: at its start.
+        assert_eq!(
+            reboot_time(&window, local("2025-06-01T00:30:00")),
+            Some(local("2025-06-01T02:00:00"))
+        );
+        // Too close to its end: the next night.
+        assert_eq!(
+            reboot_time(&window, local("2025-06-01T04:58:00")),
+            Some(local("2025-06-02T02:00:00"))
+        );
+    }
+
+    #[test]
+    fn reboot_skips_times_a_dst_change_leaves_out() {
+        // Clocks jump from 02:00 to 03:00 on the 30th.
+        let resolve = |at: &NaiveDateTime| {
+            let skipped = at.to_string().starts_with("2025-03-30 02:");
+            (!skipped).then_some(*at)
+        };
+        let time = |s: &str| s.parse::<NaiveDateTime>().unwrap();
+
+        // The window's start is skipped: its first real minute.
+        let night: RebootWindow = "02:00-05:00".parse().unwrap();
+        assert_eq!(
+            first_valid_time(&night, time("2025-03-30T00:30:00"), resolve),
+            Some(time("2025-03-30T03:00:00"))
+        );
+        // All of it is skipped: the next night, never outside the window.
+        let short: RebootWindow = "02:00-03:00".parse().unwrap();
+        assert_eq!(
+            first_valid_time(&short, time("2025-03-30T00:30:00"), resolve),
+            Some(time("2025-03-31T02:00:00"))
+        );
+    }
+
+    #[test]
+    fn only_the_recorded_reboot_is_cancelled() {
+        let reboot = |usec| Some(("reboot".to_string(), usec));
+        assert_eq!(reboot_to_cancel(reboot(100), Some(100)), Ok(100));
+        assert!(reboot_to_cancel(reboot(100), Some(200)).is_err());
+        assert!(reboot_to_cancel(reboot(100), None).is_err());
+        assert!(reboot_to_cancel(Some(("poweroff".to_string(), 100)), Some(100)).is_err());
+        assert!(reboot_to_cancel(None, Some(100)).is_err());
+    }
+}
@@ -1,11 +1,15 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use pacman_log::LogReader;
 use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 use std::time::SystemTime;
 
-use crate::alpm::{find_available_updates, get_handle, reason_to_string};
+use super::footprint::find_orphans;
+use super::policy::expired_hold_messages;
+use super::query_lang::{Candidate, Relevance, SearchQuery};
+use super::syncdb::{stale_databases, stale_message};
+use crate::alpm::{find_available_updates, get_handle, private_sync_handle, reason_to_string};
 use crate::db::{find_package_repo, get_repo_map};
 use crate::models::{
     LogEntry, OrphanPackage, OrphanResponse, Package, PackageDetails, PackageListResponse,
@@ -137,18 +141,46 @@ pub fn list_installed(
     emit_json(&response)
 }
 
-pub fn check_updates() -> Result<()> {
-    let handle = get_handle()?;
+/// `fresh` refreshes a private copy of the sync dbs first (see
+/// `private_sync_handle`), like `checkupdates`, instead of comparing against
+/// whatever root last synced. If that refresh fails the check falls back to
+/// the system dbs and says so in `warnings`.
+pub fn check_updates(fresh: bool) -> Result<()> {
+    let mut warnings = Vec::new();
+    let mut handle = if fresh {
+        match refreshed_private_handle() {
+            Ok(handle) => handle,
+            Err(e) => {
+                warnings.push(format!(
+                    "Could not refresh a private database copy ({:#}); results are from the last system sync",
+                    e
+                ));
+                get_handle()?
+            }
+        }
+    } else {
+        get_handle()?
+    };
     let config = crate::config::AppConfig::load().unwrap_or_default();
-    let updates = find_available_updates(&handle, &config.ignored_packages);
+    config.apply_ignores(&mut handle)?;
+    let updates = find_available_updates(&handle, &config);
+    let stale = stale_databases(&handle, config.stale_db_days());
+    warnings.extend(stale_message(&stale, config.stale_db_days()));
+    warnings.extend(expired_hold_messages(&handle, &config));
 
-    let response = UpdatesResponse {
-        updates,
-        warnings: Vec::new(),
-    };
+    let response = UpdatesResponse { updates, warnings };
     emit_json(&response)
 }
 
+fn refreshed_private_handle() -> Result<alpm::Alpm> {
+    let mut handle = private_sync_handle()?;
+    handle
+        .syncdbs_mut()
+        .update(false)
+        .context("Failed to refresh databases")?;
+    Ok(handle)
+}
+
 fn split_licenses(license: &str) -> Vec<String> {
     license
         .split(" AND ")
@@ -331,65 +363,68 @@ pub fn search(
     sort_by: Option<&str>,
     sort_dir: Option<&str>,
 ) -> Result<()> {
+    let parsed = SearchQuery::parse(query)?;
     let handle = get_handle()?;
     let localdb = handle.localdb();
     let mut repo_set: HashSet<String> = HashSet::new();
-    let query_lower = query.to_lowercase();
 
     let mut total_installed = 0usize;
     let mut total_not_installed = 0usize;
-    let mut filtered: Vec<SearchResult> = Vec::new();
+    let mut ranked: Vec<(Relevance, SearchResult)> = Vec::new();
 
     for syncdb in handle.syncdbs() {
         for pkg in syncdb.pkgs() {
-            let name_match = pkg.name().to_lowercase().contains(&query_lower);
-            let desc_match = pkg
-                .desc()
-                .map(|d| d.to_lowercase().contains(&query_lower))
-                .unwrap_or(false);
-
-            if name_match || desc_match {
-                let repo_name = syncdb.name().to_string();
-                repo_set.insert(repo_name.clone());
-                let local_pkg = localdb.pkg(pkg.name()).ok();
-                let is_installed = local_pkg.is_some();
-
-                if is_installed {
-                    total_installed += 1;
-                } else {
-                    total_not_installed += 1;
-                }
+            let candidate = Candidate::from_pkg(pkg, syncdb.name());
+            if !parsed.matches(&candidate) {
+                continue;
+            }
 
-                let should_include = match installed_filter {
-                    Some(filter) => is_installed == filter,
-                    None => true,
-                };
+            let repo_name = syncdb.name().to_string();
+            repo_set.insert(repo_name.clone());
+            let local_pkg = localdb.pkg(pkg.name()).ok();
+            let is_installed = local_pkg.is_some();
 
-                if should_include {
-                    filtered.push(SearchResult {
+            if is_installed {
+                total_installed += 1;
+            } else {
+                total_not_installed += 1;
+            }
+
+            let should_include = match installed_filter {
+                Some(filter) => is_installed == filter,
+                None => true,
+            };
+
+            if should_include {
+                ranked.push((
+                    parsed.relevance(&candidate),
+                    SearchResult {
                         name: pkg.name().to_string(),
                         version: pkg.version().to_string(),
                         description: pkg.desc().map(|s| s.to_string()),
                         repository: repo_name,
                         installed: is_installed,
                         installed_version: local_pkg.map(|p| p.version().to_string()),
-                    });
-                }
+                    },
+                ));
             }
         }
     }
 
     let ascending = sort_dir != Some("desc");
     match sort_by {
-        Some("name") => sort_with_direction(&mut filtered, ascending, |a, b| a.name.cmp(&b.name)),
-        Some("repository") => sort_with_direction(&mut filtered, ascending, |a, b| {
-            a.repository.cmp(&b.repository)
+        Some("name") => sort_with_direction(&mut ranked, ascending, |a, b| a.1.name.cmp(&b.1.name)),
+        Some("repository") => sort_with_direction(&mut ranked, ascending, |a, b| {
+            a.1.repository.cmp(&b.1.repository)
         }),
-        Some("status") => sort_with_direction(&mut filtered, ascending, |a, b| {
-            a.installed.cmp(&b.installed)
+        Some("status") => sort_with_direction(&mut ranked, ascending, |a, b| {
+            a.1.installed.cmp(&b.1.installed)
         }),
-        _ => {}
+        // Relevance is the default: best tier first, then by name. The
+        // direction doesn't apply; worst matches first is never wanted.
+        _ => ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name))),
     }
+    let filtered: Vec<SearchResult> = ranked.into_iter().map(|(_, r)| r).collect();
 
     let total = filtered.len();
     let results: Vec<SearchResult> = filtered.into_iter().skip(offset).take(limit).collect();
@@ -465,19 +500,15 @@ pub fn sync_package_info(name: &str, repo: Option<&str>) -> Result<()> {
     emit_json(&details)
 }
 
-pub fn list_orphans() -> Result<()> {
+pub fn list_orphans(include_optional: bool) -> Result<()> {
     let handle = get_handle()?;
     let localdb = handle.localdb();
     let repo_map = get_repo_map(&handle);
+    let names = find_orphans(&handle, include_optional)?;
 
-    let orphans: Vec<OrphanPackage> = localdb
-        .pkgs()
+    let orphans: Vec<OrphanPackage> = names
         .iter()
-        .filter(|pkg| {
-            pkg.reason() == alpm::PackageReason::Depend
-                && pkg.required_by().is_empty()
-                && pkg.optional_for().is_empty()
-        })
+        .filter_map(|name| localdb.pkg(name.as_str()).ok())
         .map(|pkg| OrphanPackage {
             name: pkg.name().to_string(),
             version: pkg.version().to_string(),
@@ -0,0 +1,524 @@
+//! Search query language for `search`.
+//!
+//! A query is whitespace-separated terms. Bare terms match a package's name or
+//! description; `field:value` terms filter on one field. Every term must match.
+//!
+//! - fields: name, desc, provides, depends, groups, packager, license (or
+//!   licence), repo, builddate
+//! - `value` is a case-insensitive substring, `=value` an exact match and
+//!   `/pattern/` a case-insensitive regex
+//! - builddate takes `YYYY-MM-DD` with an optional `<`, `<=`, `>` or `>=`
+//! - a leading `-` negates a field filter (`-repo:multilib`)
+//! - double quotes keep spaces inside one term (`desc:"window manager"`)
+//!
+//! A query with no fields and no regex behaves like the old substring search.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Desc,
+    Provides,
+    Depends,
+    Groups,
+    Packager,
+    License,
+    Repo,
+    BuildDate,
+}
+
+impl Field {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "name" => Some(Field::Name),
+            "desc" | "description" => Some(Field::Desc),
+            "provides" => Some(Field::Provides),
+            "depends" => Some(Field::Depends),
+            "group" | "groups" => Some(Field::Groups),
+            "packager" => Some(Field::Packager),
+            "license" | "licence" => Some(Field::License),
+            "repo" | "repository" => Some(Field::Repo),
+            "builddate" | "built" => Some(Field::BuildDate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Substring(String),
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn parse(value: &str) -> Result<Self> {
+        if value.len() >= 2 && value.starts_with('/') && value.ends_with('/') {
+            let pattern = &value[1..value.len() - 1];
+            let re = RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Invalid regex '{}': {}", pattern, e))?;
+            return Ok(Matcher::Regex(re));
+        }
+        if let Some(exact) = value.strip_prefix('=') {
+            if exact.is_empty() {
+                anyhow::bail!("Exact match needs a value after '='");
+            }
+            return Ok(Matcher::Exact(exact.to_lowercase()));
+        }
+        if value.is_empty() {
+            anyhow::bail!("Search term cannot be empty");
+        }
+        Ok(Matcher::Substring(value.to_lowercase()))
+    }
+
+    fn is_match(&self, haystack: &str) -> bool {
+        match self {
+            Matcher::Substring(s) => haystack.to_lowercase().contains(s.as_str()),
+            Matcher::Exact(s) => haystack.eq_ignore_ascii_case(s),
+            Matcher::Regex(re) => re.is_match(haystack),
+        }
+    }
+
+    fn any_match<'a>(&self, mut values: impl Iterator<Item = &'a str>) -> bool {
+        values.any(|v| self.is_match(v))
+    }
+
+    /// How well this term matches a package name, best first. None when the
+    /// name doesn't match at all.
+    fn name_relevance(&self, name: &str) -> Option<Relevance> {
+        match self {
+            Matcher::Substring(s) | Matcher::Exact(s) if name.eq_ignore_ascii_case(s) => {
+                Some(Relevance::ExactName)
+            }
+            Matcher::Substring(s) => {
+                let lower = name.to_lowercase();
+                if lower.starts_with(s.as_str()) {
+                    Some(Relevance::NamePrefix)
+                } else if lower.contains(s.as_str()) {
+                    Some(Relevance::NameContains)
+                } else {
+                    None
+                }
+            }
+            Matcher::Exact(_) => None,
+            Matcher::Regex(re) => re.is_match(name).then_some(Relevance::NameContains),
+        }
+    }
+}
+
+/// Build-date comparison as a half-open range of unix seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateRange {
+    from: Option<i64>,
+    until: Option<i64>,
+}
+
+impl DateRange {
+    fn parse(value: &str) -> Result<Self> {
+        let (op, date) = if let Some(d) = value.strip_prefix(">=") {
+            (">=", d)
+        } else if let Some(d) = value.strip_prefix("<=") {
+            ("<=", d)
+        } else if let Some(d) = value.strip_prefix('>') {
+            (">", d)
+        } else if let Some(d) = value.strip_prefix('<') {
+            ("<", d)
+        } else {
+            ("=", value.strip_prefix('=').unwrap_or(value))
+        };
+        let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("Invalid build date '{}': expected YYYY-MM-DD", date))?;
+        let start = day
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc().timestamp())
+            .ok_or_else(|| anyhow::anyhow!("Invalid build date '{}'", date))?;
+        let next = start + 86_400;
+        Ok(match op {
+            ">=" => DateRange {
+                from: Some(start),
+                until: None,
+            },
+            ">" => DateRange {
+                from: Some(next),
+                until: None,
+            },
+            "<=" => DateRange {
+                from: None,
+                until: Some(next),
+            },
+            "<" => DateRange {
+                from: None,
+                until: Some(start),
+            },
+            _ => DateRange {
+                from: Some(start),
+                until: Some(next),
+            },
+        })
+    }
+
+    fn contains(&self, ts: i64) -> bool {
+        self.from.is_none_or(|f| ts >= f) && self.until.is_none_or(|u| ts < u)
+    }
+}
+
+#[derive(Debug)]
+enum Condition {
+    Text(Matcher),
+    Date(DateRange),
+}
+
+#[derive(Debug)]
+struct Filter {
+    field: Field,
+    negate: bool,
+    condition: Condition,
+}
+
+/// The searchable fields of one package, borrowed from alpm so matching can be
+/// tested without a handle.
+pub(crate) struct Candidate<'a> {
+    pub name: &'a str,
+    pub desc: Option<&'a str>,
+    pub provides: Vec<&'a str>,
+    pub depends: Vec<&'a str>,
+    pub groups: Vec<&'a str>,
+    pub packager: Option<&'a str>,
+    pub licenses: Vec<&'a str>,
+    pub repo: &'a str,
+    pub build_date: i64,
+}
+
+impl<'a> Candidate<'a> {
+    pub fn from_pkg(pkg: &'a alpm::Package, repo: &'a str) -> Self {
+        Candidate {
+            name: pkg.name(),
+            desc: pkg.desc(),
+            provides: pkg.provides().iter().map(|d| d.name()).collect(),
+            depends: pkg.depends().iter().map(|d| d.name()).collect(),
+            groups: pkg.groups().iter().collect(),
+            packager: pkg.packager(),
+            licenses: pkg.licenses().iter().collect(),
+            repo,
+            build_date: pkg.build_date(),
+        }
+    }
+}
+
+/// Ranking tiers, best first: exact name, name prefix, name substring,
+/// provides, description, then anything only a field filter selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Relevance {
+    ExactName,
+    NamePrefix,
+    NameContains,
+    Provides,
+    Description,
+    Other,
+}
+
+#[derive(Debug)]
+pub(crate) struct SearchQuery {
+    terms: Vec<Matcher>,
+    filters: Vec<Filter>,
+}
+
+/// Split on whitespace, keeping double-quoted runs (quotes removed) together.
+fn tokenize(query: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if in_quotes {
+        anyhow::bail!("Unterminated quote in search query");
+    }
+    if has_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut terms = Vec::new();
+        let mut filters = Vec::new();
+
+        for token in tokenize(query)? {
+            // A regex term may itself contain ':', so only split off a field
+            // name that precedes any '/'.
+            let field_split = token
+                .split_once(':')
+                .filter(|(f, _)| !f.contains('/') && !f.is_empty());
+            let Some((raw_field, value)) = field_split else {
+                terms.push(Matcher::parse(&token)?);
+                continue;
+            };
+
+            let (negate, name) = match raw_field.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, raw_field),
+            };
+            // Not a field: a term that happens to contain ':', such as
+            // `lib32:foo` or a URL, is searched for literally.
+            let Some(field) = Field::parse(name) else {
+                terms.push(Matcher::parse(&token)?);
+                continue;
+            };
+            let condition = if field == Field::BuildDate {
+                Condition::Date(DateRange::parse(value)?)
+            } else {
+                Condition::Text(Matcher::parse(value)?)
+            };
+            filters.push(Filter {
+                field,
+                negate,
+                condition,
+            });
+        }
+
+        if terms.is_empty() && filters.is_empty() {
+            anyhow::bail!("Search query cannot be empty");
+        }
+        Ok(SearchQuery { terms, filters })
+    }
+
+    pub fn matches(&self, c: &Candidate) -> bool {
+        let terms_match = self
+            .terms
+            .iter()
+            .all(|t| t.is_match(c.name) || c.desc.is_some_and(|d| t.is_match(d)));
+        terms_match
+            && self
+                .filters
+                .iter()
+                .all(|f| filter_matches(f, c) != f.negate)
+    }
+
+    /// Best tier any free-text term (or name/provides filter when there are no
+    /// free-text terms) reaches for this candidate.
+    pub fn relevance(&self, c: &Candidate) -> Relevance {
+        let ranked: Vec<&Matcher> = if self.terms.is_empty() {
+            self.filters
+                .iter()
+                .filter(|f| !f.negate && matches!(f.field, Field::Name | Field::Provides))
+                .filter_map(|f| match &f.condition {
+                    Condition::Text(m) => Some(m),
+                    Condition::Date(_) => None,
+                })
+                .collect()
+        } else {
+            self.terms.iter().collect()
+        };
+
+        ranked
+            .into_iter()
+            .map(|m| {
+                m.name_relevance(c.name)
+                    .or_else(|| {
+                        m.any_match(c.provides.iter().copied())
+                            .then_some(Relevance::Provides)
+                    })
+                    .or_else(|| {
+                        c.desc
+                            .is_some_and(|d| m.is_match(d))
+                            .then_some(Relevance::Description)
+                    })
+                    .unwrap_or(Relevance::Other)
+            })
+            .min()
+            .unwrap_or(Relevance::Other)
+    }
+}
+
+fn filter_matches(filter: &Filter, c: &Candidate) -> bool {
+    match &filter.condition {
+        Condition::Date(range) => range.contains(c.build_date),
+        Condition::Text(m) => match filter.field {
+            Field::Name => m.is_match(c.name),
+            Field::Desc => c.desc.is_some_and(|d| m.is_match(d)),
+            Field::Provides => m.any_match(c.provides.iter().copied()),
+            Field::Depends => m.any_match(c.depends.iter().copied()),
+            Field::Groups => m.any_match(c.groups.iter().copied()),
+            Field::Packager => c.packager.is_some_and(|p| m.is_match(p)),
+            Field::License => m.any_match(c.licenses.iter().copied()),
+            Field::Repo => m.is_match(c.repo),
+            Field::BuildDate => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate<'a>(name: &'a str, desc: &'a str) -> Candidate<'a> {
+        Candidate {
+            name,
+            desc: Some(desc),
+            provides: Vec::new(),
+            depends: Vec::new(),
+            groups: Vec::new(),
+            packager: None,
+            licenses: Vec::new(),
+            repo: "extra",
+            build_date: 0,
+        }
+    }
+
+    fn parse(q: &str) -> SearchQuery {
+        match SearchQuery::parse(q) {
+            Ok(query) => query,
+            Err(e) => panic!("{q}: {e}"),
+        }
+    }
+
+    #[test]
+    fn bare_terms_match_name_or_description() {
+        let q = parse("VIM");
+        assert!(q.matches(&candidate("vim", "Vi Improved")));
+        assert!(q.matches(&candidate("neovim", "Fork of Vim")));
+        assert!(!q.matches(&candidate("emacs", "The extensible editor")));
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let q = parse("text editor");
+        assert!(q.matches(&candidate(
+            "nano",
+            "Pico editor clone with text enhancements"
+        )));
+        assert!(!q.matches(&candidate("nano", "Pico editor clone")));
+    }
+
+    #[test]
+    fn provides_filter_with_exact_value() {
+        let mut jre = candidate("jre17-openjdk", "OpenJDK Java 17 runtime");
+        jre.provides = vec!["java-runtime", "java-runtime-openjdk"];
+        let mut headless = candidate("jre17-openjdk-headless", "headless runtime");
+        headless.provides = vec!["java-runtime-headless"];
+
+        let q = parse("provides:=java-runtime");
+        assert!(q.matches(&jre));
+        assert!(!q.matches(&headless));
+        assert!(parse("provides:java-runtime").matches(&headless));
+    }
+
+    #[test]
+    fn packager_repo_and_negation() {
+        let mut c = candidate("foo", "bar");
+        c.packager = Some("Jane Doe <jane@archlinux.org>");
+        assert!(parse("packager:jane").matches(&c));
+        assert!(parse("packager:jane repo:extra").matches(&c));
+        assert!(!parse("packager:jane -repo:extra").matches(&c));
+        assert!(parse("-repo:multilib").matches(&c));
+    }
+
+    #[test]
+    fn regex_terms_and_fields() {
+        let mut c = candidate("python-requests", "HTTP for Humans");
+        c.licenses = vec!["Apache-2.0"];
+        assert!(parse("/^python-.*s$/").matches(&c));
+        assert!(parse("license:/^apache/").matches(&c));
+        assert!(!parse("name:/^requests/").matches(&c));
+    }
+
+    #[test]
+    fn regex_may_contain_colon() {
+        let c = candidate("foo", "listens on port 8080:tcp");
+        assert!(parse("/8080:tcp/").matches(&c));
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces() {
+        let c = candidate("i3-wm", "Improved dynamic tiling window manager");
+        assert!(parse("desc:\"tiling window\"").matches(&c));
+        assert!(!parse("desc:\"window tiling\"").matches(&c));
+    }
+
+    #[test]
+    fn builddate_comparisons() {
+        let mut c = candidate("foo", "bar");
+        // 2024-03-15 12:00:00 UTC
+        c.build_date = 1_710_504_000;
+        assert!(parse("builddate:2024-03-15").matches(&c));
+        assert!(parse("builddate:>=2024-03-15").matches(&c));
+        assert!(parse("builddate:>2024-03-14").matches(&c));
+        assert!(!parse("builddate:>2024-03-15").matches(&c));
+        assert!(parse("builddate:<=2024-03-15").matches(&c));
+        assert!(!parse("builddate:<2024-03-15").matches(&c));
+    }
+
+    #[test]
+    fn unknown_field_prefix_is_a_literal_term() {
+        let c = candidate("qt5-base", "See https://www.qt.io/ for docs");
+        assert!(parse("https://www.qt.io").matches(&c));
+        assert!(!parse("lib32:foo").matches(&c));
+        assert!(parse("lib32:foo").filters.is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert!(SearchQuery::parse("name:/[unclosed/").is_err());
+        assert!(SearchQuery::parse("builddate:yesterday").is_err());
+        assert!(SearchQuery::parse("desc:\"open").is_err());
+        assert!(SearchQuery::parse("   ").is_err());
+        assert!(SearchQuery::parse("name:").is_err());
+    }
+
+    #[test]
+    fn relevance_tiers_order() {
+        let q = parse("vim");
+        let exact = candidate("vim", "Vi Improved");
+        let prefix = candidate("vim-airline", "status line");
+        let contains = candidate("neovim", "editor");
+        let mut provides = candidate("gvim", "GUI");
+        provides.name = "gui-editor";
+        provides.provides = vec!["vim"];
+        let desc = candidate("kakoune", "modal editor inspired by vim");
+
+        assert_eq!(q.relevance(&exact), Relevance::ExactName);
+        assert_eq!(q.relevance(&prefix), Relevance::NamePrefix);
+        assert_eq!(q.relevance(&contains), Relevance::NameContains);
+        assert_eq!(q.relevance(&provides), Relevance::Provides);
+        assert_eq!(q.relevance(&desc), Relevance::Description);
+        assert!(Relevance::ExactName < Relevance::NamePrefix);
+        assert!(Relevance::Provides < Relevance::Description);
+    }
+
+    #[test]
+    fn relevance_falls_back_to_name_and_provides_filters() {
+        let mut c = candidate("jre-openjdk", "runtime");
+        c.provides = vec!["java-runtime"];
+        assert_eq!(
+            parse("provides:java-runtime").relevance(&c),
+            Relevance::Provides
+        );
+        assert_eq!(parse("repo:extra").relevance(&c), Relevance::Other);
+    }
+}
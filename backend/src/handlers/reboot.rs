@@ -1,3 +1,4 @@
+use alpm::Alpm;
 use anyhow::{Context, Result};
 use std::fs;
 use std::process::Command;
@@ -92,10 +93,14 @@ fn detect_kernel_package(running_kernel: &str) -> Option<&'static str> {
 }
 
 pub fn get_reboot_status() -> Result<()> {
+    let handle = get_handle()?;
+    emit_json(&reboot_status(&handle)?)
+}
+
+pub(crate) fn reboot_status(handle: &Alpm) -> Result<RebootStatus> {
     let running_kernel = get_running_kernel()?;
     let boot_time = get_boot_time()?;
 
-    let handle = get_handle()?;
     let localdb = handle.localdb();
 
     let mut status = RebootStatus {
@@ -137,7 +142,7 @@ pub fn get_reboot_status() -> Result<()> {
         }
     }
 
-    emit_json(&status)
+    Ok(status)
 }
 
 #[cfg(test)]
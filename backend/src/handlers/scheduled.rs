@@ -1,23 +1,43 @@
-use alpm::{AnyQuestion, Question, TransFlag};
+use alpm::{AnyEvent, AnyQuestion, Event, Question, TransFlag};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::fs::OpenOptionsExt;
 use std::path::Path;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use crate::alpm::{
     TransactionGuard, find_available_updates, get_handle, setup_dl_cb, setup_log_cb,
 };
-use crate::config::{AppConfig, ScheduleConfigResponse, ScheduleMode, ScheduleSetResponse};
+use crate::config::{
+    AppConfig, HookCommand, PolicyAction, PostUpgradePolicy, ScheduleConfig,
+    ScheduleConfigResponse, ScheduleHooks, ScheduleMode, ScheduleSetResponse, SchedulesResponse,
+    apply_timer_to_systemd,
+};
+use crate::handlers::cache::{format_bytes, remove_old_versions};
+use crate::handlers::footprint::find_orphans;
+use crate::handlers::holdback::{age_candidates, age_holdbacks};
+use crate::handlers::hooks::run_hooks;
+use crate::handlers::mirrors::{rank_mirrors, write_mirrorlist};
+use crate::handlers::notify::{RunOutcome, notify_after_run};
+use crate::handlers::post_upgrade::post_upgrade_actions;
+use crate::handlers::security::{fixed_advisories, load_advisories, security_selection};
 use crate::inhibit::ShutdownInhibitor;
-use crate::models::{ScheduledRunEntry, ScheduledRunsResponse};
+use crate::models::{
+    BackupSource, PackageResult, RunDurations, ScheduledPackage, ScheduledRunEntry,
+    ScheduledRunStats, ScheduledRunsResponse, SkipReason, UpdateInfo,
+};
 use crate::util::{
-    CheckResult, TimeoutGuard, check_cancel, emit_json, setup_signal_handler, with_file_lock,
+    CheckResult, TimeoutGuard, check_cancel, emit_json, get_cache_dir, setup_signal_handler,
+    with_file_lock, write_json_atomic,
+};
+use crate::validation::{
+    validate_date, validate_max_packages, validate_min_age_days, validate_run_status,
+    validate_schedule, validate_schedule_name,
 };
-use crate::validation::{validate_max_packages, validate_schedule};
 
 const LOG_DIR: &str = "/var/log/cockpit-pacman";
 const LOG_PATH: &str = "/var/log/cockpit-pacman/scheduled.jsonl";
@@ -25,6 +45,10 @@ const LOG_LOCK_PATH: &str = "/var/log/cockpit-pacman/.scheduled.jsonl.lock";
 const MAX_LOG_SIZE_BYTES: u64 = 1024 * 1024; // 1MB max log size
 const MAX_LOG_ENTRIES: usize = 1000;
 const SCHEDULED_TIMEOUT_SECS: u64 = 1800;
+const HOOK_STATE_DIR: &str = "/var/lib/cockpit-pacman";
+/// Largest set of updates a security run applies when `max_packages` is unset.
+/// A bigger closure is close to a full upgrade and is left to a regular run.
+const SECURITY_CLOSURE_LIMIT: usize = 25;
 
 #[derive(Serialize, Deserialize)]
 struct LogEntry {
@@ -37,14 +61,96 @@ struct LogEntry {
     packages_upgraded: usize,
     error: Option<String>,
     details: Vec<String>,
+    /// Name of the schedule that ran; absent for the default schedule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schedule: Option<String>,
+    // Structured outcome; absent from entries written by older versions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    packages: Vec<ScheduledPackage>,
+    #[serde(default, skip_serializing_if = "RunDurations::is_empty")]
+    durations: RunDurations,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    download_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    skip_reason: Option<SkipReason>,
+}
+
+/// When a run started, what it does and which schedule fired it.
+struct RunContext {
+    timestamp: String,
+    mode: ScheduleMode,
+    schedule: Option<String>,
+    /// Run by log_run() once the outcome is known.
+    post_hooks: Vec<HookCommand>,
+    /// Set once an upgrade commits; log_run() applies it after the post
+    /// hooks, so a reboot never lands in the middle of one.
+    post_upgrade: Option<PostUpgradePolicy>,
+    /// Pre-run hook results, put ahead of the run's own details.
+    hook_details: Vec<String>,
+    /// Filled in as the run goes, and copied into whichever entry ends it.
+    packages: Vec<ScheduledPackage>,
+    durations: RunDurations,
+    download_bytes: Option<u64>,
+}
+
+impl RunContext {
+    fn new(mode: ScheduleMode, schedule: Option<&str>) -> Self {
+        RunContext {
+            timestamp: get_timestamp(),
+            mode,
+            schedule: schedule.map(|s| s.to_string()),
+            post_hooks: Vec::new(),
+            post_upgrade: None,
+            hook_details: Vec::new(),
+            packages: Vec::new(),
+            durations: RunDurations::default(),
+            download_bytes: None,
+        }
+    }
+
+    /// Record every update as found; later steps refine the results.
+    fn found_updates(&mut self, updates: &[UpdateInfo]) {
+        self.packages = updates
+            .iter()
+            .map(|u| ScheduledPackage {
+                name: u.name.clone(),
+                old_version: Some(u.current_version.clone()),
+                new_version: u.new_version.clone(),
+                repository: u.repository.clone(),
+                download_size: u.download_size,
+                installed_size: u.new_size,
+                result: PackageResult::Available,
+            })
+            .collect();
+    }
+
+    fn set_result<'a>(&mut self, names: impl IntoIterator<Item = &'a str>, result: PackageResult) {
+        for name in names {
+            if let Some(package) = self.packages.iter_mut().find(|p| p.name == name) {
+                package.result = result;
+            }
+        }
+    }
+
+    /// Environment shared by pre- and post-run hooks.
+    fn hook_env(&self, stage: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("COCKPIT_PACMAN_HOOK", stage.to_string()),
+            ("COCKPIT_PACMAN_MODE", self.mode.to_string()),
+            (
+                "COCKPIT_PACMAN_SCHEDULE",
+                self.schedule.clone().unwrap_or_default(),
+            ),
+            ("COCKPIT_PACMAN_TIMESTAMP", self.timestamp.clone()),
+        ]
+    }
 }
 
 impl LogEntry {
     /// `status` is "ok" | "skipped" | "failed"; `success` is kept in sync so old
     /// readers and the wire `success` flag stay correct.
     fn new(
-        timestamp: String,
-        mode: ScheduleMode,
+        run: &RunContext,
         status: &str,
         packages_checked: usize,
         packages_upgraded: usize,
@@ -52,16 +158,89 @@ impl LogEntry {
         details: Vec<String>,
     ) -> Self {
         LogEntry {
-            timestamp,
-            mode: mode.to_string(),
+            timestamp: run.timestamp.clone(),
+            mode: run.mode.to_string(),
             success: status != "failed",
             status: status.to_string(),
             packages_checked,
             packages_upgraded,
             error,
             details,
+            schedule: run.schedule.clone(),
+            packages: run.packages.clone(),
+            durations: run.durations.clone(),
+            download_bytes: run.download_bytes,
+            skip_reason: None,
         }
     }
+
+    fn skipped(
+        run: &RunContext,
+        reason: SkipReason,
+        packages_checked: usize,
+        details: Vec<String>,
+    ) -> Self {
+        LogEntry {
+            skip_reason: Some(reason),
+            ..LogEntry::new(run, "skipped", packages_checked, 0, None, details)
+        }
+    }
+}
+
+/// Mark the packages a committed transaction carried as upgraded, adding the
+/// ones that were not updates (new dependencies, replacements).
+fn record_upgraded(packages: &mut Vec<ScheduledPackage>, upgraded: Vec<ScheduledPackage>) {
+    for package in upgraded {
+        match packages.iter_mut().find(|p| p.name == package.name) {
+            Some(existing) => *existing = package,
+            None => packages.push(package),
+        }
+    }
+}
+
+/// Updates a scheduled run reports but never applies: those ignored through
+/// pacman.conf or the app config, and those policy holds or only notifies
+/// about.
+fn kept_back(update: &UpdateInfo) -> bool {
+    update.ignored
+        || matches!(
+            update.policy,
+            Some(PolicyAction::Hold) | Some(PolicyAction::NotifyOnly)
+        )
+}
+
+/// `min_age_days` as it applies to a run. A security run applies only fixes
+/// and the updates they must move with, which should not wait for age.
+fn holdback_min_age(mode: ScheduleMode, min_age_days: u32) -> u32 {
+    if mode == ScheduleMode::Security {
+        0
+    } else {
+        min_age_days
+    }
+}
+
+/// Skip reason for entries logged before it was recorded, from the detail
+/// line each kind of skip has always written.
+fn legacy_skip_reason(details: &[String]) -> Option<SkipReason> {
+    let line = details.iter().find(|d| d.starts_with("Skipped: "))?;
+    let reason = if line.contains("database locked") {
+        SkipReason::DbLocked
+    } else if line.contains("pre-run hook") {
+        SkipReason::PreHookFailed
+    } else if line.contains("security fixes need") {
+        SkipReason::SecurityLimit
+    } else if line.contains("safety limit") {
+        SkipReason::SafetyLimit
+    } else if line.contains("manual intervention") {
+        SkipReason::ManualIntervention
+    } else {
+        return None;
+    };
+    Some(reason)
+}
+
+fn elapsed_ms(since: Instant) -> Option<u64> {
+    Some(since.elapsed().as_millis() as u64)
 }
 
 /// Status for a run record, deriving from `success` when an older log entry has
@@ -82,7 +261,70 @@ fn get_timestamp() -> String {
         .to_string()
 }
 
-fn log_run(entry: &LogEntry) -> Result<()> {
+/// Where post-run hooks find the run's record as JSON.
+fn run_file_path(schedule: Option<&str>) -> String {
+    match schedule {
+        None => format!("{}/last-run.json", HOOK_STATE_DIR),
+        Some(name) => format!("{}/last-run@{}.json", HOOK_STATE_DIR, name),
+    }
+}
+
+/// Run the post-run hooks against `entry`, returning their details.
+fn run_post_hooks(run: &RunContext, entry: &LogEntry) -> Vec<String> {
+    let run_file = run_file_path(run.schedule.as_deref());
+    let written = fs::create_dir_all(HOOK_STATE_DIR)
+        .context("Failed to create state directory")
+        .and_then(|_| write_json_atomic(Path::new(&run_file), entry));
+    let mut env = run.hook_env("post");
+    env.extend([
+        ("COCKPIT_PACMAN_STATUS", entry.status.clone()),
+        (
+            "COCKPIT_PACMAN_PACKAGES_CHECKED",
+            entry.packages_checked.to_string(),
+        ),
+        (
+            "COCKPIT_PACMAN_PACKAGES_UPGRADED",
+            entry.packages_upgraded.to_string(),
+        ),
+        (
+            "COCKPIT_PACMAN_PACKAGES",
+            entry
+                .packages
+                .iter()
+                .filter(|p| p.result == PackageResult::Upgraded)
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        (
+            "COCKPIT_PACMAN_ERROR",
+            entry.error.clone().unwrap_or_default(),
+        ),
+    ]);
+    let mut details = Vec::new();
+    match written {
+        Ok(()) => env.push(("COCKPIT_PACMAN_RUN_FILE", run_file)),
+        Err(e) => details.push(format!("Could not write run file for hooks: {:#}", e)),
+    }
+    details.extend(run_hooks("Post-run", &run.post_hooks, &env, false).1);
+    details
+}
+
+fn log_run(run: &RunContext, mut entry: LogEntry) -> Result<()> {
+    let mut post_details = if run.post_hooks.is_empty() {
+        Vec::new()
+    } else {
+        let started = Instant::now();
+        let details = run_post_hooks(run, &entry);
+        let pre_ms = entry.durations.hooks_ms.unwrap_or_default();
+        entry.durations.hooks_ms = elapsed_ms(started).map(|ms| ms + pre_ms);
+        details
+    };
+    if let Some(policy) = &run.post_upgrade {
+        post_details.extend(post_upgrade_actions(policy));
+    }
+    entry.details = [run.hook_details.clone(), entry.details, post_details].concat();
+
     fs::create_dir_all(LOG_DIR).context("Failed to create log directory")?;
     fs::set_permissions(LOG_DIR, fs::Permissions::from_mode(0o750))
         .context("Failed to set log directory permissions")?;
@@ -100,10 +342,21 @@ fn log_run(entry: &LogEntry) -> Result<()> {
             .open(LOG_PATH)
             .context("Failed to open log file")?;
 
-        let json = serde_json::to_string(entry)?;
+        let json = serde_json::to_string(&entry)?;
         writeln!(file, "{}", json)?;
         Ok(())
-    })
+    })?;
+
+    notify_after_run(&RunOutcome {
+        timestamp: &entry.timestamp,
+        schedule: entry.schedule.as_deref(),
+        mode: &entry.mode,
+        status: &entry.status,
+        packages_checked: entry.packages_checked,
+        error: entry.error.as_deref(),
+        details: &entry.details,
+    });
+    Ok(())
 }
 
 fn rotate_if_needed() -> Result<()> {
@@ -166,33 +419,73 @@ pub fn get_schedule_config() -> Result<()> {
     emit_json(&response)
 }
 
-pub fn set_schedule_config(
+/// Validate and apply the given changes to one schedule.
+fn update_schedule(
+    target: &mut ScheduleConfig,
     enabled: Option<bool>,
     mode: Option<&str>,
     schedule: Option<&str>,
     max_packages: Option<usize>,
+    min_age_days: Option<u32>,
 ) -> Result<()> {
-    // Validate inputs before modifying config
+    // Validate everything before touching the target
+    let mode: Option<ScheduleMode> = mode.map(str::parse).transpose()?;
     if let Some(s) = schedule {
         validate_schedule(s)?;
     }
     if let Some(mp) = max_packages {
         validate_max_packages(mp)?;
     }
+    if let Some(days) = min_age_days {
+        validate_min_age_days(days)?;
+    }
+
+    if let Some(e) = enabled {
+        target.enabled = e;
+    }
+    if let Some(m) = mode {
+        target.mode = m;
+    }
+    if let Some(s) = schedule {
+        target.schedule = s.to_string();
+    }
+    if let Some(mp) = max_packages {
+        target.max_packages = mp;
+    }
+    if let Some(days) = min_age_days {
+        target.min_age_days = days;
+    }
+    Ok(())
+}
+
+fn schedule_set_response(name: Option<&str>, schedule: &ScheduleConfig) -> ScheduleSetResponse {
+    let label = name.map_or("Schedule".to_string(), |n| format!("Schedule {}", n));
+    ScheduleSetResponse {
+        success: true,
+        message: if schedule.enabled {
+            format!("{} enabled with {} mode", label, schedule.mode)
+        } else {
+            format!("{} disabled", label)
+        },
+    }
+}
 
+pub fn set_schedule_config(
+    enabled: Option<bool>,
+    mode: Option<&str>,
+    schedule: Option<&str>,
+    max_packages: Option<usize>,
+    min_age_days: Option<u32>,
+) -> Result<()> {
     let config = AppConfig::update(|config| {
-        if let Some(e) = enabled {
-            config.schedule.enabled = e;
-        }
-        if let Some(m) = mode {
-            config.schedule.mode = m.parse()?;
-        }
-        if let Some(s) = schedule {
-            config.schedule.schedule = s.to_string();
-        }
-        if let Some(mp) = max_packages {
-            config.schedule.max_packages = mp;
-        }
+        update_schedule(
+            &mut config.schedule,
+            enabled,
+            mode,
+            schedule,
+            max_packages,
+            min_age_days,
+        )?;
         // Must run before update() writes: on failure the closure returns Err
         // and config.json is left untouched, never claiming a timer state that
         // didn't take.
@@ -200,18 +493,139 @@ pub fn set_schedule_config(
         Ok(config.clone())
     })?;
 
-    let response = ScheduleSetResponse {
+    emit_json(&schedule_set_response(None, &config.schedule))
+}
+
+pub fn list_schedules() -> Result<()> {
+    let config = AppConfig::load()?;
+    let schedules = std::iter::once(ScheduleConfigResponse::from_config(&config.schedule))
+        .chain(
+            config
+                .schedules
+                .iter()
+                .map(|(name, schedule)| ScheduleConfigResponse::for_schedule(Some(name), schedule)),
+        )
+        .collect();
+    emit_json(&SchedulesResponse { schedules })
+}
+
+/// Create or change a named schedule. A new one starts from the defaults
+/// (disabled, upgrade, weekly) before the given changes apply.
+pub fn set_named_schedule(
+    name: &str,
+    enabled: Option<bool>,
+    mode: Option<&str>,
+    schedule: Option<&str>,
+    max_packages: Option<usize>,
+    min_age_days: Option<u32>,
+) -> Result<()> {
+    validate_schedule_name(name)?;
+    if name == "all" {
+        anyhow::bail!(
+            "Schedule name 'all' is reserved: list-scheduled-runs uses it for every schedule"
+        );
+    }
+
+    let updated = AppConfig::update(|config| {
+        let target = config.schedules.entry(name.to_string()).or_default();
+        update_schedule(target, enabled, mode, schedule, max_packages, min_age_days)?;
+        // As with the default schedule, a timer that fails to apply leaves
+        // config.json untouched.
+        apply_timer_to_systemd(Some(name), target)?;
+        Ok(target.clone())
+    })?;
+
+    emit_json(&schedule_set_response(Some(name), &updated))
+}
+
+/// Replace the pre- and post-run hooks of the default schedule, or of a named
+/// one that already exists.
+pub fn set_schedule_hooks(name: Option<&str>, hooks: ScheduleHooks) -> Result<()> {
+    if let Some(name) = name {
+        validate_schedule_name(name)?;
+    }
+    hooks.validate()?;
+
+    AppConfig::update(|config| {
+        let target = match name {
+            None => &mut config.schedule,
+            Some(name) => match config.schedules.get_mut(name) {
+                Some(target) => target,
+                None => anyhow::bail!("No schedule named {}", name),
+            },
+        };
+        target.hooks = hooks;
+        Ok(())
+    })?;
+
+    let label = name.map_or("Schedule".to_string(), |n| format!("Schedule {}", n));
+    emit_json(&ScheduleSetResponse {
         success: true,
-        message: if config.schedule.enabled {
-            format!("Schedule enabled with {} mode", config.schedule.mode)
-        } else {
-            "Schedule disabled".to_string()
-        },
-    };
-    emit_json(&response)
+        message: format!("{} hooks updated", label),
+    })
+}
+
+/// Replace what runs after a committed upgrade of the default schedule, or of
+/// a named one that already exists.
+pub fn set_post_upgrade(name: Option<&str>, policy: PostUpgradePolicy) -> Result<()> {
+    if let Some(name) = name {
+        validate_schedule_name(name)?;
+    }
+    policy.validate()?;
+
+    AppConfig::update(|config| {
+        let target = match name {
+            None => &mut config.schedule,
+            Some(name) => match config.schedules.get_mut(name) {
+                Some(target) => target,
+                None => anyhow::bail!("No schedule named {}", name),
+            },
+        };
+        target.post_upgrade = policy;
+        Ok(())
+    })?;
+
+    let label = name.map_or("Schedule".to_string(), |n| format!("Schedule {}", n));
+    emit_json(&ScheduleSetResponse {
+        success: true,
+        message: format!("{} post-upgrade policy updated", label),
+    })
+}
+
+pub fn remove_schedule(name: &str) -> Result<()> {
+    validate_schedule_name(name)?;
+
+    AppConfig::update(|config| {
+        if config.schedules.remove(name).is_none() {
+            anyhow::bail!("No schedule named {}", name);
+        }
+        apply_timer_to_systemd(Some(name), &ScheduleConfig::default())
+    })?;
+
+    emit_json(&ScheduleSetResponse {
+        success: true,
+        message: format!("Schedule {} removed", name),
+    })
 }
 
-pub fn get_scheduled_runs(offset: usize, limit: usize) -> Result<()> {
+/// Run history, newest first, with totals over every matching run.
+/// `schedule` keeps only the runs of that named schedule, or of the default
+/// one when it is empty; `since` and `until` are inclusive `YYYY-MM-DD` dates.
+pub fn get_scheduled_runs(
+    offset: usize,
+    limit: usize,
+    schedule: Option<&str>,
+    status: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<()> {
+    if let Some(status) = status {
+        validate_run_status(status)?;
+    }
+    for date in since.iter().chain(&until) {
+        validate_date(date)?;
+    }
+
     let mut runs = Vec::new();
 
     if Path::new(LOG_PATH).exists() {
@@ -220,31 +634,89 @@ pub fn get_scheduled_runs(offset: usize, limit: usize) -> Result<()> {
 
         for line in reader.lines().map_while(Result::ok) {
             if let Ok(entry) = serde_json::from_str::<LogEntry>(&line) {
-                runs.push(ScheduledRunEntry {
-                    timestamp: entry.timestamp,
-                    mode: entry.mode,
-                    success: entry.success,
-                    status: derive_status(&entry.status, entry.success),
-                    packages_checked: entry.packages_checked,
-                    packages_upgraded: entry.packages_upgraded,
-                    error: entry.error,
-                    details: entry.details,
-                });
+                let run = run_entry(entry);
+                if run_matches(&run, schedule, status, since, until) {
+                    runs.push(run);
+                }
             }
         }
     }
 
     runs.reverse();
     let total = runs.len();
+    let stats = run_stats(&runs);
     let paginated: Vec<_> = runs.into_iter().skip(offset).take(limit).collect();
 
     let response = ScheduledRunsResponse {
         runs: paginated,
         total,
+        stats,
     };
     emit_json(&response)
 }
 
+fn run_entry(entry: LogEntry) -> ScheduledRunEntry {
+    let status = derive_status(&entry.status, entry.success);
+    let skip_reason = match entry.skip_reason {
+        Some(reason) => Some(reason),
+        None if status == "skipped" => legacy_skip_reason(&entry.details),
+        None => None,
+    };
+    ScheduledRunEntry {
+        timestamp: entry.timestamp,
+        mode: entry.mode,
+        success: entry.success,
+        status,
+        packages_checked: entry.packages_checked,
+        packages_upgraded: entry.packages_upgraded,
+        error: entry.error,
+        details: entry.details,
+        schedule: entry.schedule,
+        packages: entry.packages,
+        durations: (!entry.durations.is_empty()).then_some(entry.durations),
+        download_bytes: entry.download_bytes,
+        skip_reason,
+    }
+}
+
+fn run_matches(
+    run: &ScheduledRunEntry,
+    schedule: Option<&str>,
+    status: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> bool {
+    // Timestamps start with the run's local date, so dates compare as text.
+    let date = run.timestamp.get(..10).unwrap_or_default();
+    schedule.is_none_or(|wanted| run.schedule.as_deref().unwrap_or_default() == wanted)
+        && status.is_none_or(|wanted| run.status == wanted)
+        && since.is_none_or(|since| date >= since)
+        && until.is_none_or(|until| date <= until)
+}
+
+/// `runs` must be newest first.
+fn run_stats(runs: &[ScheduledRunEntry]) -> ScheduledRunStats {
+    let mut stats = ScheduledRunStats::default();
+    for run in runs {
+        match run.status.as_str() {
+            "ok" => stats.ok += 1,
+            "skipped" => stats.skipped += 1,
+            _ => stats.failed += 1,
+        }
+        stats.packages_upgraded += run.packages_upgraded;
+        stats.download_bytes += run.download_bytes.unwrap_or_default();
+        let last = match run.status.as_str() {
+            "ok" => &mut stats.last_ok,
+            "failed" => &mut stats.last_failed,
+            _ => continue,
+        };
+        if last.is_none() {
+            *last = Some(run.timestamp.clone());
+        }
+    }
+    stats
+}
+
 /// True only for outcomes where the process died without running its own
 /// logging. `timeout` is excluded on purpose: SendSIGKILL=no lets a timed-out
 /// run finish and self-report (or wedge forever, in which case ExecStopPost
@@ -255,170 +727,384 @@ fn is_kill_result(result: &str) -> bool {
 
 /// Called from the unit's ExecStopPost. Records a failed run only for an abrupt
 /// kill, the one outcome scheduled_run() cannot report itself.
-pub fn record_interrupted() -> Result<()> {
+pub fn record_interrupted(name: Option<&str>) -> Result<()> {
     let result = std::env::var("SERVICE_RESULT").unwrap_or_default();
     if !is_kill_result(&result) {
         return Ok(());
     }
 
-    let mode = AppConfig::load()
-        .map(|c| c.schedule.mode)
+    let schedule = AppConfig::load()
+        .ok()
+        .and_then(|c| c.schedule_named(name).cloned())
         .unwrap_or_default();
+    // Post-run hooks still get to undo whatever the pre-run hooks set up.
+    let mut run = RunContext::new(schedule.mode, name);
+    run.post_hooks = schedule.hooks.post;
     let entry = LogEntry::new(
-        get_timestamp(),
-        mode,
+        &run,
         "failed",
         0,
         0,
         Some(format!("Run killed by systemd (result={result})")),
         Vec::new(),
     );
-    log_run(&entry)
+    log_run(&run, entry)
 }
 
-pub fn scheduled_run() -> Result<()> {
+/// Entry point of the timer units: the default schedule when `name` is None,
+/// otherwise the named one.
+pub fn scheduled_run(name: Option<&str>) -> Result<()> {
     let config = AppConfig::load()?;
+    let Some(schedule) = config.schedule_named(name) else {
+        anyhow::bail!("No schedule named {}", name.unwrap_or_default());
+    };
 
-    if !config.schedule.enabled {
-        eprintln!("Scheduled upgrades not enabled, exiting");
+    if !schedule.enabled {
+        eprintln!("Schedule not enabled, exiting");
         return Ok(());
     }
 
     // Set up signal handler and timeout guard
     setup_signal_handler();
-    let _timeout_guard = TimeoutGuard::new(SCHEDULED_TIMEOUT_SECS);
+    let mut timeout_guard = TimeoutGuard::new(SCHEDULED_TIMEOUT_SECS);
 
-    let ignored_packages = config.ignored_packages.clone();
-    let mode = config.schedule.mode;
-    let max_packages = config.schedule.max_packages;
+    let mode = schedule.mode;
+    let max_packages = schedule.max_packages;
+    let min_age_days = schedule.min_age_days;
 
     let mut details = Vec::new();
-    let timestamp = get_timestamp();
+    let mut run = RunContext::new(mode, name);
 
-    eprintln!("[{}] Starting scheduled {} run", timestamp, mode);
+    eprintln!("[{}] Starting scheduled {} run", run.timestamp, mode);
 
     // A locked db is logged as a skipped run; the unit intentionally has no
     // ConditionPathExists so the skip is recorded rather than silent.
     if crate::handlers::lock::is_db_locked() {
         eprintln!("pacman database is locked, skipping scheduled run");
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "skipped",
+        let entry = LogEntry::skipped(
+            &run,
+            SkipReason::DbLocked,
             0,
-            0,
-            None,
             vec!["Skipped: pacman database locked".to_string()],
         );
-        log_run(&entry)?;
+        log_run(&run, entry)?;
         return Ok(());
     }
 
     // Check for cancellation before starting
-    if let CheckResult::Cancelled | CheckResult::TimedOut(_) = check_cancel(&_timeout_guard) {
+    if let CheckResult::Cancelled | CheckResult::TimedOut(_) = check_cancel(&timeout_guard) {
         let entry = LogEntry::new(
-            timestamp,
-            mode,
+            &run,
             "failed",
             0,
             0,
             Some("Operation cancelled or timed out before starting".to_string()),
             details,
         );
-        log_run(&entry)?;
+        log_run(&run, entry)?;
         anyhow::bail!("Operation cancelled or timed out");
     }
 
-    let mut handle = get_handle()?;
+    if !schedule.hooks.is_empty() {
+        // From here on every outcome, including a skip by a failing pre-run
+        // hook, goes through the post-run hooks.
+        run.post_hooks = schedule.hooks.post.clone();
+        let started = Instant::now();
+        let (ok, hook_details) =
+            run_hooks("Pre-run", &schedule.hooks.pre, &run.hook_env("pre"), true);
+        run.hook_details = hook_details;
+        run.durations.hooks_ms = elapsed_ms(started);
+        if !ok {
+            eprintln!("Pre-run hook failed, skipping scheduled run");
+            let entry = LogEntry::skipped(
+                &run,
+                SkipReason::PreHookFailed,
+                0,
+                vec!["Skipped: pre-run hook failed".to_string()],
+            );
+            log_run(&run, entry)?;
+            return Ok(());
+        }
+        // The hooks have their own timeouts; the run's starts after them.
+        timeout_guard = TimeoutGuard::new(SCHEDULED_TIMEOUT_SECS);
+    }
 
-    for pkg_name in &ignored_packages {
-        handle.add_ignorepkg(pkg_name.as_str())?;
+    if !mode.checks_updates() {
+        return maintenance_run(&run);
     }
 
+    let mut handle = get_handle()?;
+    config.apply_ignores(&mut handle)?;
+
     setup_log_cb(&mut handle);
     setup_dl_cb(&mut handle);
 
     eprintln!("Syncing package databases...");
-    if let Err(e) = handle.syncdbs_mut().update(false) {
+    let started = Instant::now();
+    let synced = handle.syncdbs_mut().update(false);
+    run.durations.sync_ms = elapsed_ms(started);
+    if let Err(e) = synced {
         let entry = LogEntry::new(
-            timestamp,
-            mode,
+            &run,
             "failed",
             0,
             0,
             Some(format!("Failed to sync databases: {}", e)),
             details,
         );
-        log_run(&entry)?;
+        log_run(&run, entry)?;
         return Err(e.into());
     }
 
     // Check for cancellation after database sync
-    if let CheckResult::Cancelled | CheckResult::TimedOut(_) = check_cancel(&_timeout_guard) {
+    if let CheckResult::Cancelled | CheckResult::TimedOut(_) = check_cancel(&timeout_guard) {
         let entry = LogEntry::new(
-            timestamp,
-            mode,
+            &run,
             "failed",
             0,
             0,
             Some("Operation cancelled or timed out after database sync".to_string()),
             details,
         );
-        log_run(&entry)?;
+        log_run(&run, entry)?;
         anyhow::bail!("Operation cancelled or timed out");
     }
 
-    let updates = find_available_updates(&handle, &ignored_packages);
+    // Divergence from the desired-state manifest is recorded on every run so
+    // the history shows when a machine drifted from its baseline.
+    let drift = if config.desired_state.is_empty() {
+        Vec::new()
+    } else {
+        let plan =
+            crate::handlers::state::plan_for(&handle, &config.desired_state, &config.orphan_keep);
+        crate::handlers::state::drift_summary(&plan)
+    };
+    details.extend(drift.iter().cloned());
+
+    // Holds that ran out are dropped from the config and noted in the run.
+    let expired = match crate::handlers::policy::prune_expired_holds(&handle) {
+        Ok(expired) => expired,
+        Err(e) => vec![format!("Could not drop expired holds: {:#}", e)],
+    };
+    details.extend(expired.iter().cloned());
+
+    let updates = find_available_updates(&handle, &config);
     let packages_checked = updates.len();
 
     if updates.is_empty() {
         eprintln!("No updates available");
         let entry = LogEntry::new(
-            timestamp,
-            mode,
+            &run,
             "ok",
             0,
             0,
             None,
-            vec!["No updates available".to_string()],
+            [vec!["No updates available".to_string()], expired, drift].concat(),
         );
-        log_run(&entry)?;
+        log_run(&run, entry)?;
         return Ok(());
     }
 
     eprintln!("Found {} package(s) with updates", packages_checked);
+    run.found_updates(&updates);
     for update in &updates {
         details.push(format!("{} -> {}", update.name, update.new_version));
     }
 
+    // Security runs narrow the updates to advisory fixes and the updates each
+    // fix must move with; everything else is only reported.
+    let mut deferred = Vec::new();
+    let updates = if mode == ScheduleMode::Security {
+        let advisories = match load_advisories(&handle) {
+            Ok(response) => {
+                if response.stale {
+                    details
+                        .push("Security tracker unreachable, using cached advisories".to_string());
+                }
+                response.advisories
+            }
+            Err(e) => {
+                let entry = LogEntry::new(
+                    &run,
+                    "failed",
+                    packages_checked,
+                    0,
+                    Some(format!("Failed to load security advisories: {:#}", e)),
+                    details,
+                );
+                log_run(&run, entry)?;
+                return Err(e);
+            }
+        };
+        let fixes = fixed_advisories(&advisories, &updates, |a, b| alpm::vercmp(a, b));
+        let selection = security_selection(
+            &age_candidates(&handle, &updates),
+            &fixes.iter().map(Option::is_some).collect::<Vec<_>>(),
+            &updates.iter().map(kept_back).collect::<Vec<_>>(),
+        );
+        for (update, advisory) in updates.iter().zip(&fixes) {
+            if let Some(advisory) = advisory {
+                details.push(format!(
+                    "Security fix: {} {} ({}, {})",
+                    update.name, update.new_version, advisory.avg_name, advisory.severity
+                ));
+            }
+        }
+        for &(fix, by) in &selection.blocked {
+            details.push(if fix == by {
+                format!("Security fix not applied by policy: {}", updates[fix].name)
+            } else {
+                format!(
+                    "Security fix not applied: {} must move with {}, which policy keeps back",
+                    updates[fix].name, updates[by].name
+                )
+            });
+        }
+
+        if selection.apply.is_empty() {
+            eprintln!("No security fixes to apply");
+            details.push("No security fixes to apply".to_string());
+            let entry = LogEntry::new(&run, "ok", packages_checked, 0, None, details);
+            log_run(&run, entry)?;
+            return Ok(());
+        }
+
+        let limit = if max_packages > 0 {
+            max_packages
+        } else {
+            SECURITY_CLOSURE_LIMIT
+        };
+        if selection.apply.len() > limit {
+            eprintln!(
+                "Security fixes need {} updates, over the limit of {}, skipping upgrade",
+                selection.apply.len(),
+                limit
+            );
+            let entry = LogEntry::skipped(
+                &run,
+                SkipReason::SecurityLimit,
+                packages_checked,
+                [
+                    vec![format!(
+                        "Skipped: security fixes need {} updates, over the limit of {}",
+                        selection.apply.len(),
+                        limit
+                    )],
+                    details,
+                ]
+                .concat(),
+            );
+            log_run(&run, entry)?;
+            return Ok(());
+        }
+
+        let mut selected = Vec::new();
+        for (i, update) in updates.into_iter().enumerate() {
+            if selection.apply.contains(&i) {
+                selected.push(update);
+            } else {
+                deferred.push(update);
+            }
+        }
+        for update in &deferred {
+            details.push(format!(
+                "Reported only: {} {}",
+                update.name, update.new_version
+            ));
+        }
+        run.set_result(
+            deferred.iter().map(|u| u.name.as_str()),
+            PackageResult::Deferred,
+        );
+        selected
+    } else {
+        updates
+    };
+
+    // Ignored, held and notify-only packages are reported but never applied
+    // here.
+    let (skipped, applicable): (Vec<_>, Vec<_>) = updates.into_iter().partition(kept_back);
+    for update in &skipped {
+        details.push(format!(
+            "Not applied by policy: {} {} ({})",
+            update.name,
+            update.new_version,
+            update.policy_note.as_deref().unwrap_or("ignored")
+        ));
+    }
+
+    let held = age_holdbacks(
+        &age_candidates(&handle, &applicable),
+        chrono::Utc::now().timestamp(),
+        holdback_min_age(mode, min_age_days),
+    );
+    details.extend(held.iter().map(|h| h.detail(min_age_days)));
+    run.set_result(
+        skipped.iter().map(|u| u.name.as_str()),
+        PackageResult::HeldByPolicy,
+    );
+    run.set_result(held.iter().map(|h| h.name.as_str()), PackageResult::TooNew);
+    let packages_to_apply = applicable.len() - held.len();
+
     if mode == ScheduleMode::Check {
         eprintln!("Check mode: logging updates without applying");
-        let entry = LogEntry::new(timestamp, mode, "ok", packages_checked, 0, None, details);
-        log_run(&entry)?;
+        let entry = LogEntry::new(&run, "ok", packages_checked, 0, None, details);
+        log_run(&run, entry)?;
+        return Ok(());
+    }
+
+    if packages_to_apply == 0 {
+        eprintln!("All updates held back by policy or minimum age");
+        let entry = LogEntry::new(&run, "ok", packages_checked, 0, None, details);
+        log_run(&run, entry)?;
         return Ok(());
     }
 
-    if max_packages > 0 && packages_checked > max_packages {
+    if max_packages > 0 && packages_to_apply > max_packages {
         eprintln!(
             "Safety limit: {} updates exceed max_packages ({}), skipping upgrade",
-            packages_checked, max_packages
+            packages_to_apply, max_packages
         );
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "skipped",
+        let entry = LogEntry::skipped(
+            &run,
+            SkipReason::SafetyLimit,
             packages_checked,
-            0,
-            None,
-            vec![format!(
-                "Skipped: {} updates exceed safety limit of {}",
-                packages_checked, max_packages
-            )],
+            [
+                vec![format!(
+                    "Skipped: {} updates exceed safety limit of {}",
+                    packages_to_apply, max_packages
+                )],
+                drift,
+            ]
+            .concat(),
         );
-        log_run(&entry)?;
+        log_run(&run, entry)?;
         return Ok(());
     }
 
+    // Held and deferred packages are skipped by sysupgrade the same way
+    // IgnorePkg ones are.
+    for name in skipped
+        .iter()
+        .chain(&deferred)
+        .map(|u| u.name.as_str())
+        .chain(held.iter().map(|h| h.name.as_str()))
+    {
+        handle.add_ignorepkg(name)?;
+    }
+
+    // A commit downloads first and installs after; the end of the download
+    // splits its time between the two phases.
+    let retrieved_at: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
+    let retrieved_cb = Arc::clone(&retrieved_at);
+    handle.set_event_cb((), move |event: AnyEvent, _: &mut ()| {
+        if let Event::RetrieveDone | Event::RetrieveFailed = event.event()
+            && let Ok(mut at) = retrieved_cb.lock()
+        {
+            *at = Some(Instant::now());
+        }
+    });
+
     let has_conflicts = Arc::new(AtomicBool::new(false));
     let has_removals = Arc::new(AtomicBool::new(false));
     let has_import_keys = Arc::new(AtomicBool::new(false));
@@ -438,157 +1124,312 @@ pub fn scheduled_run() -> Result<()> {
         },
     );
 
-    let mut tx = match TransactionGuard::new(&mut handle, TransFlag::NONE) {
-        Ok(tx) => tx,
-        Err(e) => {
+    // The transaction, and with it the db lock and the shutdown inhibitor,
+    // ends with this block, so post hooks and notifications run without them.
+    let (entry, outcome) = 'tx: {
+        let prepare_started = Instant::now();
+        let mut tx = match TransactionGuard::new(&mut handle, TransFlag::NONE) {
+            Ok(tx) => tx,
+            Err(e) => {
+                let entry = LogEntry::new(
+                    &run,
+                    "failed",
+                    packages_checked,
+                    0,
+                    Some(format!("{:#}", e)),
+                    details,
+                );
+                break 'tx (entry, Err(e));
+            }
+        };
+
+        if let Err(e) = tx.sync_sysupgrade(false) {
             let entry = LogEntry::new(
-                timestamp,
-                mode,
+                &run,
                 "failed",
                 packages_checked,
                 0,
-                Some(format!("{:#}", e)),
+                Some(format!("Failed to prepare upgrade: {}", e)),
                 details,
             );
-            log_run(&entry)?;
-            return Err(e);
+            break 'tx (entry, Err(e.into()));
         }
-    };
 
-    if let Err(e) = tx.sync_sysupgrade(false) {
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "failed",
-            packages_checked,
-            0,
-            Some(format!("Failed to prepare upgrade: {}", e)),
-            details,
-        );
-        log_run(&entry)?;
-        return Err(e.into());
-    }
+        let conflicts_detected = has_conflicts.load(Ordering::SeqCst);
+        let removals_detected = has_removals.load(Ordering::SeqCst);
+        let imports_detected = has_import_keys.load(Ordering::SeqCst);
 
-    let conflicts_detected = has_conflicts.load(Ordering::SeqCst);
-    let removals_detected = has_removals.load(Ordering::SeqCst);
-    let imports_detected = has_import_keys.load(Ordering::SeqCst);
+        let prepare_failed = tx.prepare().is_err();
+        run.durations.prepare_ms = elapsed_ms(prepare_started);
 
-    let prepare_failed = tx.prepare().is_err();
+        if prepare_failed {
+            eprintln!("Failed to prepare upgrade transaction");
+            let entry = LogEntry::new(
+                &run,
+                "failed",
+                packages_checked,
+                0,
+                Some("Failed to prepare upgrade transaction".to_string()),
+                details,
+            );
+            break 'tx (
+                entry,
+                Err(anyhow::anyhow!("Failed to prepare upgrade transaction")),
+            );
+        }
 
-    if prepare_failed {
-        eprintln!("Failed to prepare upgrade transaction");
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "failed",
-            packages_checked,
-            0,
-            Some("Failed to prepare upgrade transaction".to_string()),
-            details,
+        if conflicts_detected || removals_detected || imports_detected {
+            eprintln!("Manual intervention required, skipping");
+            let mut reasons = Vec::new();
+            if conflicts_detected {
+                reasons.push("conflicts detected");
+            }
+            if removals_detected {
+                reasons.push("package removals required");
+            }
+            if imports_detected {
+                reasons.push("key imports required");
+            }
+            let entry = LogEntry::skipped(
+                &run,
+                SkipReason::ManualIntervention,
+                packages_checked,
+                vec![format!(
+                    "Skipped: manual intervention required ({})",
+                    reasons.join(", ")
+                )],
+            );
+            break 'tx (entry, Ok(()));
+        }
+
+        let localdb = tx.localdb();
+        let transaction: Vec<ScheduledPackage> = tx
+            .add()
+            .iter()
+            .map(|p| ScheduledPackage {
+                name: p.name().to_string(),
+                old_version: localdb
+                    .pkg(p.name())
+                    .ok()
+                    .map(|old| old.version().to_string()),
+                new_version: p.version().to_string(),
+                repository: p.db().map(|db| db.name().to_string()).unwrap_or_default(),
+                download_size: p.download_size(),
+                installed_size: p.isize(),
+                result: PackageResult::Upgraded,
+            })
+            .collect();
+        let packages_to_upgrade = transaction.len();
+        run.download_bytes = Some(
+            transaction
+                .iter()
+                .map(|p| p.download_size.max(0) as u64)
+                .sum(),
         );
-        log_run(&entry)?;
-        anyhow::bail!("Failed to prepare upgrade transaction");
-    }
 
-    if conflicts_detected || removals_detected || imports_detected {
-        eprintln!("Manual intervention required, skipping");
-        let mut reasons = Vec::new();
-        if conflicts_detected {
-            reasons.push("conflicts detected");
-        }
-        if removals_detected {
-            reasons.push("package removals required");
+        if packages_to_upgrade == 0 {
+            eprintln!("No packages to upgrade after preparation");
+            let entry = LogEntry::new(
+                &run,
+                "ok",
+                packages_checked,
+                0,
+                None,
+                vec!["No packages to upgrade after preparation".to_string()],
+            );
+            break 'tx (entry, Ok(()));
         }
-        if imports_detected {
-            reasons.push("key imports required");
+
+        // Final check before committing - this is the point of no return
+        if let CheckResult::Cancelled | CheckResult::TimedOut(_) = check_cancel(&timeout_guard) {
+            let entry = LogEntry::new(
+                &run,
+                "failed",
+                packages_checked,
+                0,
+                Some("Operation cancelled or timed out before commit".to_string()),
+                details,
+            );
+            break 'tx (
+                entry,
+                Err(anyhow::anyhow!("Operation cancelled or timed out")),
+            );
         }
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "skipped",
-            packages_checked,
-            0,
-            None,
-            vec![format!(
-                "Skipped: manual intervention required ({})",
-                reasons.join(", ")
-            )],
+
+        eprintln!(
+            "Committing upgrade of {} package(s)...",
+            packages_to_upgrade
         );
-        log_run(&entry)?;
-        return Ok(());
-    }
 
-    let packages_to_upgrade = tx.add().len();
+        let _inhibitor = ShutdownInhibitor::take("Applying scheduled package upgrade");
 
-    if packages_to_upgrade == 0 {
-        eprintln!("No packages to upgrade after preparation");
+        let commit_started = Instant::now();
+        let committed = tx.commit();
+        let retrieved = retrieved_at.lock().ok().and_then(|at| *at);
+        run.durations.download_ms =
+            retrieved.map(|at| at.duration_since(commit_started).as_millis() as u64);
+        run.durations.commit_ms = elapsed_ms(retrieved.unwrap_or(commit_started));
+        if let Err(e) = committed {
+            let entry = LogEntry::new(
+                &run,
+                "failed",
+                packages_checked,
+                0,
+                Some(format!("Failed to commit upgrade: {}", e)),
+                details,
+            );
+            break 'tx (entry, Err(e.into()));
+        }
+
+        eprintln!("Upgrade completed successfully");
+        if !schedule.post_upgrade.is_empty() {
+            run.post_upgrade = Some(schedule.post_upgrade.clone());
+        }
+        record_upgraded(&mut run.packages, transaction);
         let entry = LogEntry::new(
-            timestamp,
-            mode,
+            &run,
             "ok",
             packages_checked,
-            0,
+            packages_to_upgrade,
             None,
-            vec!["No packages to upgrade after preparation".to_string()],
+            details,
         );
-        log_run(&entry)?;
-        return Ok(());
+        (entry, Ok(()))
+    };
+    log_run(&run, entry)?;
+    outcome
+}
+
+/// Cached versions kept per package by a cache-clean run, as paccache does.
+const CACHE_KEEP_VERSIONS: u32 = 3;
+/// Mirrors written by a mirror-refresh run.
+const MIRROR_REFRESH_COUNT: usize = 10;
+const KEYRING_REFRESH_TIMEOUT_SECS: u64 = 600;
+
+/// Run a task that doesn't touch updates and record its outcome.
+fn maintenance_run(run: &RunContext) -> Result<()> {
+    let result = match run.mode {
+        ScheduleMode::CacheClean => clean_cache_task(),
+        ScheduleMode::OrphanReport => orphan_report_task(),
+        ScheduleMode::MirrorRefresh => mirror_refresh_task(),
+        ScheduleMode::KeyringRefresh => {
+            crate::handlers::keyring::refresh_keys_quietly(KEYRING_REFRESH_TIMEOUT_SECS)
+                .map(|()| vec!["Keyring refreshed".to_string()])
+        }
+        ScheduleMode::Check | ScheduleMode::Upgrade | ScheduleMode::Security => {
+            unreachable!("update modes run through scheduled_run")
+        }
+    };
+
+    match result {
+        Ok(details) => {
+            eprintln!("Scheduled {} run completed", run.mode);
+            log_run(run, LogEntry::new(run, "ok", 0, 0, None, details))
+        }
+        Err(e) => {
+            let entry = LogEntry::new(run, "failed", 0, 0, Some(format!("{:#}", e)), Vec::new());
+            log_run(run, entry)?;
+            Err(e)
+        }
     }
+}
 
-    // Final check before committing - this is the point of no return
-    if let CheckResult::Cancelled | CheckResult::TimedOut(_) = check_cancel(&_timeout_guard) {
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "failed",
-            packages_checked,
-            0,
-            Some("Operation cancelled or timed out before commit".to_string()),
-            details,
-        );
-        log_run(&entry)?;
-        anyhow::bail!("Operation cancelled or timed out");
+fn clean_cache_task() -> Result<Vec<String>> {
+    let handle = get_handle()?;
+    let cache_dir = get_cache_dir();
+    let cache_path = Path::new(&cache_dir);
+    if !cache_path.exists() {
+        return Ok(vec!["Cache directory does not exist".to_string()]);
     }
 
-    eprintln!(
-        "Committing upgrade of {} package(s)...",
-        packages_to_upgrade
+    let mut details = Vec::new();
+    let (removed, freed) = remove_old_versions(
+        &handle,
+        cache_path,
+        CACHE_KEEP_VERSIONS,
+        &[],
+        |level, message| {
+            if level != "info" {
+                details.push(message);
+            }
+        },
+    );
+    details.insert(
+        0,
+        format!(
+            "Removed {} cached package(s), freed {}",
+            removed,
+            format_bytes(freed)
+        ),
     );
+    Ok(details)
+}
 
-    let _inhibitor = ShutdownInhibitor::take("Applying scheduled package upgrade");
+fn orphan_report_task() -> Result<Vec<String>> {
+    let handle = get_handle()?;
+    let orphans = find_orphans(&handle, true)?;
+    let mut details = vec![format!("{} orphan package(s)", orphans.len())];
+    details.extend(orphans);
+    Ok(details)
+}
 
-    if let Err(e) = tx.commit() {
-        let entry = LogEntry::new(
-            timestamp,
-            mode,
-            "failed",
-            packages_checked,
-            0,
-            Some(format!("Failed to commit upgrade: {}", e)),
-            details,
-        );
-        log_run(&entry)?;
-        return Err(e.into());
+fn mirror_refresh_task() -> Result<Vec<String>> {
+    let (mirrors, _) = rank_mirrors(MIRROR_REFRESH_COUNT, None, "https", "score")?;
+    // An empty ranking means the status feed was unusable; keep the old list.
+    if mirrors.is_empty() {
+        anyhow::bail!("Mirror status returned no usable mirrors");
     }
+    let backup = write_mirrorlist(&mirrors, BackupSource::Auto)?;
 
-    eprintln!("Upgrade completed successfully");
-    let entry = LogEntry::new(
-        timestamp,
-        mode,
-        "ok",
-        packages_checked,
-        packages_to_upgrade,
-        None,
-        details,
-    );
-    log_run(&entry)?;
-
-    Ok(())
+    let mut details = vec![format!("Wrote {} mirrors to the mirrorlist", mirrors.len())];
+    if let Some(backup) = backup {
+        details.push(format!("Previous mirrorlist saved to {}", backup));
+    }
+    details.extend(mirrors.into_iter().map(|m| m.url));
+    Ok(details)
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
-    use super::{derive_status, is_kill_result};
+    use super::{
+        LogEntry, PackageResult, RunContext, ScheduleMode, ScheduledPackage, SkipReason,
+        derive_status, holdback_min_age, is_kill_result, kept_back, legacy_skip_reason,
+        record_upgraded, run_entry, run_matches, run_stats,
+    };
+    use crate::config::PolicyAction;
+    use crate::handlers::holdback::{AgeCandidate, age_holdbacks};
+    use crate::handlers::security::security_selection;
+    use crate::models::UpdateInfo;
+
+    const BUILT_AT: i64 = 1_750_000_000;
+
+    fn age_candidate(name: &str, base: Option<&str>) -> AgeCandidate {
+        AgeCandidate {
+            name: name.to_string(),
+            version: "3.2-1".to_string(),
+            pkgbase: base.map(|b| b.to_string()),
+            build_date: BUILT_AT,
+            exempt: false,
+            depends: Vec::new(),
+        }
+    }
+
+    fn package(name: &str, old_version: Option<&str>, result: PackageResult) -> ScheduledPackage {
+        ScheduledPackage {
+            name: name.to_string(),
+            old_version: old_version.map(|v| v.to_string()),
+            new_version: "2.0-1".to_string(),
+            repository: "core".to_string(),
+            download_size: 1000,
+            installed_size: 4000,
+            result,
+        }
+    }
+
+    fn logged(line: &str) -> super::ScheduledRunEntry {
+        run_entry(serde_json::from_str::<LogEntry>(line).unwrap())
+    }
 
     #[test]
     fn is_kill_result_matches_only_abrupt_kills() {
@@ -615,42 +1456,271 @@ mod tests {
     }
 
     #[test]
-    fn service_unit_never_sigkills_and_outlives_internal_guard() {
-        const UNIT_PATH: &str = concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/../systemd/cockpit-pacman-scheduled.service"
+    fn log_entries_carry_the_schedule_name_only_when_named() {
+        let named = LogEntry::new(
+            &RunContext::new(ScheduleMode::Security, Some("nightly")),
+            "ok",
+            3,
+            1,
+            None,
+            Vec::new(),
+        );
+        let line = serde_json::to_string(&named).unwrap();
+        assert!(line.contains(r#""schedule":"nightly""#));
+        assert!(line.contains(r#""mode":"security""#));
+
+        let default = LogEntry::new(
+            &RunContext::new(ScheduleMode::Upgrade, None),
+            "ok",
+            0,
+            0,
+            None,
+            Vec::new(),
+        );
+        assert!(
+            !serde_json::to_string(&default)
+                .unwrap()
+                .contains("schedule")
+        );
+
+        // Lines written before named schedules existed still parse.
+        let old = r#"{"timestamp":"t","mode":"upgrade","success":true,"packages_checked":1,"packages_upgraded":1,"error":null,"details":[]}"#;
+        assert_eq!(
+            serde_json::from_str::<LogEntry>(old).unwrap().schedule,
+            None
+        );
+    }
+
+    #[test]
+    fn ignored_updates_are_kept_back_like_held_ones() {
+        let update = |ignored, policy| UpdateInfo {
+            name: "openssl".to_string(),
+            current_version: "3.1-1".to_string(),
+            new_version: "3.2-1".to_string(),
+            download_size: 0,
+            current_size: 0,
+            new_size: 0,
+            repository: "core".to_string(),
+            ignored,
+            policy,
+            policy_note: None,
+        };
+        assert!(kept_back(&update(true, None)));
+        assert!(kept_back(&update(false, Some(PolicyAction::Hold))));
+        assert!(kept_back(&update(false, Some(PolicyAction::NotifyOnly))));
+        assert!(!kept_back(&update(false, Some(PolicyAction::Auto))));
+        assert!(!kept_back(&update(false, None)));
+
+        // An ignored member keeps its whole update group, fix included, back.
+        let candidates = [
+            age_candidate("openssl", Some("openssl")),
+            age_candidate("lib32-openssl", Some("openssl")),
+        ];
+        let selection = security_selection(
+            &candidates,
+            &[true, false],
+            &[
+                kept_back(&update(false, None)),
+                kept_back(&update(true, None)),
+            ],
         );
-        let unit =
-            std::fs::read_to_string(UNIT_PATH).unwrap_or_else(|e| panic!("read {UNIT_PATH}: {e}"));
+        assert!(selection.apply.is_empty());
+        assert_eq!(selection.blocked, vec![(0, 1)]);
+    }
 
-        // systemd honors the last occurrence of a directive.
-        let last_value = |key: &str| unit.lines().rev().find_map(|l| l.trim().strip_prefix(key));
+    #[test]
+    fn security_runs_do_not_hold_back_young_fixes() {
+        let candidates = [age_candidate("openssl", Some("openssl"))];
+        let held = |mode| age_holdbacks(&candidates, BUILT_AT, holdback_min_age(mode, 7));
+        assert!(held(ScheduleMode::Security).is_empty());
+        assert_eq!(held(ScheduleMode::Upgrade).len(), 1);
+    }
 
+    #[test]
+    fn upgraded_packages_replace_found_ones_and_add_new_dependencies() {
+        let mut packages = vec![
+            package("linux", Some("1.0-1"), PackageResult::Available),
+            package("firefox", Some("1.0-1"), PackageResult::HeldByPolicy),
+        ];
+        record_upgraded(
+            &mut packages,
+            vec![
+                package("linux", Some("1.0-1"), PackageResult::Upgraded),
+                package("libnew", None, PackageResult::Upgraded),
+            ],
+        );
+
+        let results: Vec<_> = packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.result))
+            .collect();
         assert_eq!(
-            last_value("SendSIGKILL="),
-            Some("no"),
-            "unit must set SendSIGKILL=no"
+            results,
+            [
+                ("linux", PackageResult::Upgraded),
+                ("firefox", PackageResult::HeldByPolicy),
+                ("libnew", PackageResult::Upgraded),
+            ]
         );
+    }
 
-        let stop_timeout: u64 = last_value("TimeoutStopSec=")
-            .expect("unit must set TimeoutStopSec")
-            .parse()
-            .expect("TimeoutStopSec must be plain seconds");
-        assert!(
-            stop_timeout >= 300,
-            "TimeoutStopSec ({stop_timeout}s) must give a commit real shutdown grace"
+    #[test]
+    fn structured_fields_round_trip_and_stay_out_of_old_shaped_lines() {
+        let mut run = RunContext::new(ScheduleMode::Upgrade, None);
+        run.packages = vec![package("linux", Some("1.0-1"), PackageResult::Upgraded)];
+        run.durations.sync_ms = Some(1500);
+        run.download_bytes = Some(1000);
+        let line = serde_json::to_string(&LogEntry::new(&run, "ok", 1, 1, None, vec![])).unwrap();
+        let entry = logged(&line);
+        assert_eq!(entry.packages, run.packages);
+        assert_eq!(entry.durations.unwrap().sync_ms, Some(1500));
+        assert_eq!(entry.download_bytes, Some(1000));
+
+        let skipped = LogEntry::skipped(
+            &RunContext::new(ScheduleMode::Upgrade, None),
+            SkipReason::DbLocked,
+            0,
+            vec![],
         );
+        let line = serde_json::to_string(&skipped).unwrap();
+        assert!(!line.contains("packages\""));
+        assert!(!line.contains("durations"));
+        assert_eq!(logged(&line).skip_reason, Some(SkipReason::DbLocked));
+    }
+
+    #[test]
+    fn old_entries_read_with_an_inferred_skip_reason() {
+        let old = r#"{"timestamp":"t","mode":"upgrade","success":true,"status":"skipped","packages_checked":40,"packages_upgraded":0,"error":null,"details":["Skipped: 40 updates exceed safety limit of 20"]}"#;
+        let entry = logged(old);
+        assert!(entry.packages.is_empty());
+        assert_eq!(entry.durations, None);
+        assert_eq!(entry.download_bytes, None);
+        assert_eq!(entry.skip_reason, Some(SkipReason::SafetyLimit));
+
+        let cases = [
+            ("Skipped: pacman database locked", SkipReason::DbLocked),
+            ("Skipped: pre-run hook failed", SkipReason::PreHookFailed),
+            (
+                "Skipped: security fixes need 30 updates, over the limit of 20",
+                SkipReason::SecurityLimit,
+            ),
+            (
+                "Skipped: manual intervention required (conflicts)",
+                SkipReason::ManualIntervention,
+            ),
+        ];
+        for (line, reason) in cases {
+            assert_eq!(legacy_skip_reason(&[line.to_string()]), Some(reason));
+        }
+        assert_eq!(legacy_skip_reason(&["linux 1 -> 2".to_string()]), None);
 
-        // Absent TimeoutStartSec is safe: Type=oneshot defaults it to infinity.
-        if let Some(value) = last_value("TimeoutStartSec=") {
-            let start_timeout: u64 = value
+        // Only skipped runs get a reason.
+        let ok = r#"{"timestamp":"t","mode":"upgrade","success":true,"packages_checked":1,"packages_upgraded":1,"error":null,"details":["Skipped: pacman database locked"]}"#;
+        assert_eq!(logged(ok).skip_reason, None);
+    }
+
+    #[test]
+    fn runs_filter_by_schedule_status_and_inclusive_dates() {
+        let run = logged(
+            r#"{"timestamp":"2025-06-01T03:00:00+0200","mode":"upgrade","success":false,"status":"failed","packages_checked":0,"packages_upgraded":0,"error":"x","details":[],"schedule":"nightly"}"#,
+        );
+        assert!(run_matches(&run, None, None, None, None));
+        assert!(run_matches(
+            &run,
+            Some("nightly"),
+            Some("failed"),
+            None,
+            None
+        ));
+        assert!(!run_matches(&run, Some(""), None, None, None));
+        assert!(!run_matches(&run, None, Some("ok"), None, None));
+        assert!(run_matches(
+            &run,
+            None,
+            None,
+            Some("2025-06-01"),
+            Some("2025-06-01")
+        ));
+        assert!(!run_matches(&run, None, None, Some("2025-06-02"), None));
+        assert!(!run_matches(&run, None, None, None, Some("2025-05-31")));
+    }
+
+    #[test]
+    fn stats_total_runs_and_keep_the_newest_times() {
+        let runs: Vec<_> = [
+            ("2025-06-03", "ok", 2, Some(5000)),
+            ("2025-06-02", "failed", 0, None),
+            ("2025-06-01", "ok", 3, Some(7000)),
+            ("2025-05-31", "skipped", 0, None),
+        ]
+        .into_iter()
+        .map(|(timestamp, status, upgraded, bytes)| {
+            let mut run = logged(&format!(
+                r#"{{"timestamp":"{timestamp}","mode":"upgrade","success":true,"status":"{status}","packages_checked":5,"packages_upgraded":{upgraded},"error":null,"details":[]}}"#
+            ));
+            run.download_bytes = bytes;
+            run
+        })
+        .collect();
+
+        let stats = run_stats(&runs);
+        assert_eq!((stats.ok, stats.skipped, stats.failed), (2, 1, 1));
+        assert_eq!(stats.packages_upgraded, 5);
+        assert_eq!(stats.download_bytes, 12000);
+        assert_eq!(stats.last_ok.as_deref(), Some("2025-06-03"));
+        assert_eq!(stats.last_failed.as_deref(), Some("2025-06-02"));
+        assert_eq!(run_stats(&[]), Default::default());
+    }
+
+    #[test]
+    fn service_units_never_sigkill_and_outlive_internal_guard() {
+        // The plain unit runs the default schedule, the template named ones.
+        for name in [
+            "cockpit-pacman-scheduled.service",
+            "cockpit-pacman-scheduled@.service",
+        ] {
+            let path = format!("{}/../systemd/{}", env!("CARGO_MANIFEST_DIR"), name);
+            let unit =
+                std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("read {path}: {e}"));
+
+            // systemd honors the last occurrence of a directive.
+            let last_value =
+                |key: &str| unit.lines().rev().find_map(|l| l.trim().strip_prefix(key));
+
+            assert_eq!(
+                last_value("SendSIGKILL="),
+                Some("no"),
+                "{name} must set SendSIGKILL=no"
+            );
+
+            let stop_timeout: u64 = last_value("TimeoutStopSec=")
+                .unwrap_or_else(|| panic!("{name} must set TimeoutStopSec"))
                 .parse()
-                .expect("TimeoutStartSec must be plain seconds");
+                .expect("TimeoutStopSec must be plain seconds");
             assert!(
-                start_timeout >= 2 * super::SCHEDULED_TIMEOUT_SECS,
-                "TimeoutStartSec ({start_timeout}s) must be at least 2x SCHEDULED_TIMEOUT_SECS ({}s)",
-                super::SCHEDULED_TIMEOUT_SECS
+                stop_timeout >= 300,
+                "TimeoutStopSec ({stop_timeout}s) must give a commit real shutdown grace"
             );
+
+            // Absent TimeoutStartSec is safe: Type=oneshot defaults it to infinity.
+            if let Some(value) = last_value("TimeoutStartSec=") {
+                let start_timeout: u64 = value
+                    .parse()
+                    .expect("TimeoutStartSec must be plain seconds");
+                assert!(
+                    start_timeout >= 2 * super::SCHEDULED_TIMEOUT_SECS,
+                    "TimeoutStartSec ({start_timeout}s) must be at least 2x SCHEDULED_TIMEOUT_SECS ({}s)",
+                    super::SCHEDULED_TIMEOUT_SECS
+                );
+                // Pre- and post-run hooks run outside the guard, each stage
+                // up to its full budget.
+                let budget =
+                    2 * super::SCHEDULED_TIMEOUT_SECS + 2 * crate::config::MAX_HOOK_STAGE_SECS;
+                assert!(
+                    start_timeout >= budget,
+                    "TimeoutStartSec ({start_timeout}s) must cover 2x SCHEDULED_TIMEOUT_SECS plus both hook stages ({budget}s)"
+                );
+            }
         }
     }
 }
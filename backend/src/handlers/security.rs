@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use alpm::Alpm;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
@@ -6,7 +8,8 @@ use arch_security_client::SecurityClient;
 use arch_security_client::models::{AvgStatus, Severity};
 
 use crate::alpm::get_handle;
-use crate::models::{PackageSecurityAdvisory, SecurityInfoResponse, SecurityResponse};
+use crate::handlers::holdback::{AgeCandidate, update_groups};
+use crate::models::{PackageSecurityAdvisory, SecurityInfoResponse, SecurityResponse, UpdateInfo};
 use crate::util::{config_path, emit_json, write_json_atomic};
 
 fn security_cache_path() -> Result<PathBuf> {
@@ -26,17 +29,17 @@ fn write_security_cache(path: &Path, response: &SecurityResponse) -> Result<()>
     write_json_atomic(path, response)
 }
 
-pub fn check_security() -> Result<()> {
+/// Open advisories affecting installed packages, most severe first. When the
+/// tracker is unreachable the cached list is returned with `stale` set. With no
+/// cache the error propagates: an empty list would be indistinguishable from
+/// "no known vulnerabilities", a false sense of safety.
+pub(crate) fn load_advisories(handle: &Alpm) -> Result<SecurityResponse> {
     let client = SecurityClient::new(crate::util::detected_ip_family());
     let avgs = match client.fetch_vulnerable() {
         Ok(v) => v,
         Err(e) => {
-            // Serve cached advisories (marked stale) if we have them. With no
-            // cache, propagate the error: an empty list would be
-            // indistinguishable from "no known vulnerabilities", a false
-            // sense of safety. The frontend renders this as "unavailable".
             if let Some(advisories) = security_cache_path().ok().and_then(read_security_cache) {
-                return emit_json(&SecurityResponse {
+                return Ok(SecurityResponse {
                     advisories,
                     stale: true,
                 });
@@ -45,7 +48,6 @@ pub fn check_security() -> Result<()> {
         }
     };
 
-    let handle = get_handle()?;
     let localdb = handle.localdb();
 
     let mut pkg_map: HashMap<&str, Vec<&arch_security_client::models::Avg>> = HashMap::new();
@@ -112,7 +114,75 @@ pub fn check_security() -> Result<()> {
     if let Ok(path) = security_cache_path() {
         let _ = write_security_cache(&path, &response);
     }
-    emit_json(&response)
+    Ok(response)
+}
+
+pub fn check_security() -> Result<()> {
+    let handle = get_handle()?;
+    emit_json(&load_advisories(&handle)?)
+}
+
+/// For each update, the advisory it fixes: the first open advisory on that
+/// package whose fixed version the update reaches. Updates that don't fix
+/// anything, or advisories with no fix yet, give `None`.
+pub(crate) fn fixed_advisories<'a>(
+    advisories: &'a [PackageSecurityAdvisory],
+    updates: &[UpdateInfo],
+    vercmp: impl Fn(&str, &str) -> Ordering,
+) -> Vec<Option<&'a PackageSecurityAdvisory>> {
+    updates
+        .iter()
+        .map(|update| {
+            advisories.iter().find(|a| {
+                a.package == update.name
+                    && a.fixed_version
+                        .as_deref()
+                        .is_some_and(|fixed| vercmp(&update.new_version, fixed) != Ordering::Less)
+            })
+        })
+        .collect()
+}
+
+/// Updates a security-only run applies.
+#[derive(Debug, PartialEq)]
+pub(crate) struct SecuritySelection {
+    /// Candidate indices to apply: each fix together with its whole update
+    /// group, so the run is never a partial upgrade.
+    pub apply: Vec<usize>,
+    /// Fixes left out, each with the update in its group that policy keeps back.
+    pub blocked: Vec<(usize, usize)>,
+}
+
+/// Pick the update groups (see `update_groups`) containing a fix. A group with
+/// an update that policy keeps back can't move as a whole, so its fixes are
+/// reported as blocked instead.
+pub(crate) fn security_selection(
+    candidates: &[AgeCandidate],
+    fixes: &[bool],
+    kept_back: &[bool],
+) -> SecuritySelection {
+    let groups = update_groups(candidates);
+    let mut blocker: HashMap<usize, usize> = HashMap::new();
+    for i in (0..candidates.len()).filter(|&i| kept_back[i]) {
+        blocker.entry(groups[i]).or_insert(i);
+    }
+    let fixed: HashSet<usize> = (0..candidates.len())
+        .filter(|&i| fixes[i])
+        .map(|i| groups[i])
+        .collect();
+
+    let mut selection = SecuritySelection {
+        apply: Vec::new(),
+        blocked: Vec::new(),
+    };
+    for i in (0..candidates.len()).filter(|&i| fixed.contains(&groups[i])) {
+        match blocker.get(&groups[i]) {
+            Some(&by) if fixes[i] => selection.blocked.push((i, by)),
+            Some(_) => {}
+            None => selection.apply.push(i),
+        }
+    }
+    selection
 }
 
 pub fn security_info(name: &str) -> Result<()> {
@@ -168,3 +238,102 @@ fn parse_severity(s: &str) -> Severity {
         _ => Severity::Unknown,
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn advisory(package: &str, fixed: Option<&str>) -> PackageSecurityAdvisory {
+        PackageSecurityAdvisory {
+            package: package.to_string(),
+            severity: "High".to_string(),
+            advisory_type: "arbitrary code execution".to_string(),
+            avg_name: format!("AVG-{}", package.len()),
+            cve_ids: vec![],
+            fixed_version: fixed.map(|f| f.to_string()),
+            status: "Fixed".to_string(),
+        }
+    }
+
+    fn update(name: &str, new_version: &str) -> UpdateInfo {
+        UpdateInfo {
+            name: name.to_string(),
+            current_version: "1".to_string(),
+            new_version: new_version.to_string(),
+            download_size: 0,
+            current_size: 0,
+            new_size: 0,
+            repository: "core".to_string(),
+            ignored: false,
+            policy: None,
+            policy_note: None,
+        }
+    }
+
+    fn candidate(name: &str, base: Option<&str>, depends: &[usize]) -> AgeCandidate {
+        AgeCandidate {
+            name: name.to_string(),
+            version: "2".to_string(),
+            pkgbase: base.map(|b| b.to_string()),
+            build_date: 0,
+            exempt: false,
+            depends: depends.to_vec(),
+        }
+    }
+
+    #[test]
+    fn update_must_reach_the_fixed_version() {
+        let advisories = [
+            advisory("openssl", Some("3")),
+            advisory("curl", Some("9")),
+            advisory("zlib", None),
+        ];
+        let updates = [
+            update("openssl", "3"),
+            update("curl", "8"),
+            update("zlib", "2"),
+            update("bash", "5"),
+        ];
+        let fixed: Vec<bool> = fixed_advisories(&advisories, &updates, |a, b| a.cmp(b))
+            .iter()
+            .map(Option::is_some)
+            .collect();
+        assert_eq!(fixed, vec![true, false, false, false]);
+    }
+
+    #[test]
+    fn fixes_pull_in_their_update_group() {
+        let candidates = [
+            candidate("openssl", None, &[1]),
+            candidate("glibc", None, &[]),
+            candidate("curl", Some("curl"), &[]),
+            candidate("libcurl-compat", Some("curl"), &[]),
+            candidate("bash", None, &[]),
+        ];
+        let selection =
+            security_selection(&candidates, &[true, false, true, false, false], &[false; 5]);
+        assert_eq!(selection.apply, vec![0, 1, 2, 3]);
+        assert!(selection.blocked.is_empty());
+    }
+
+    #[test]
+    fn kept_back_update_blocks_its_group() {
+        let candidates = [
+            candidate("openssl", None, &[1]),
+            candidate("glibc", None, &[]),
+            candidate("sudo", None, &[]),
+        ];
+        let selection =
+            security_selection(&candidates, &[true, false, true], &[false, true, false]);
+        assert_eq!(selection.apply, vec![2]);
+        assert_eq!(selection.blocked, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn no_fixes_selects_nothing() {
+        let candidates = [candidate("bash", None, &[])];
+        let selection = security_selection(&candidates, &[false], &[false]);
+        assert!(selection.apply.is_empty());
+    }
+}
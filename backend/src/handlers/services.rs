@@ -202,17 +202,30 @@ impl SystemdGraph for ZbusSystemdGraph {
 }
 
 pub fn get_services_status() -> Result<()> {
-    let status = match ZbusSystemdGraph::connect() {
-        Ok(graph) => services_status_with_graph(&graph)?,
+    emit_json(&services_status()?)
+}
+
+pub(crate) fn services_status() -> Result<ServicesStatus> {
+    match ZbusSystemdGraph::connect() {
+        Ok(graph) => services_status_with_graph(&graph),
         Err(e) => {
             eprintln!("services-status: zbus connect failed ({e}); returning empty");
-            ServicesStatus {
+            Ok(ServicesStatus {
                 restart_required: false,
                 services: Vec::new(),
-            }
+            })
         }
-    };
-    emit_json(&status)
+    }
+}
+
+/// Queue a restart of `unit`, replacing any job already pending for it.
+pub(crate) fn restart_service(unit: &str) -> Result<()> {
+    let graph = ZbusSystemdGraph::connect()?;
+    let _job: OwnedObjectPath = graph
+        .manager_proxy()?
+        .call("RestartUnit", &(unit, "replace"))
+        .with_context(|| format!("RestartUnit {} failed", unit))?;
+    Ok(())
 }
 
 fn services_status_with_graph<G: SystemdGraph>(graph: &G) -> Result<ServicesStatus> {
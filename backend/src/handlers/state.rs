@@ -0,0 +1,282 @@
+use alpm::{Alpm, PackageReason};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+
+use crate::alpm::get_handle;
+use crate::config::{AppConfig, DesiredState};
+use crate::models::{StatePlan, StatePlanResponse};
+use crate::util::emit_json;
+
+/// An installed package as the planner sees it.
+pub(crate) struct InstalledEntry<'a> {
+    pub name: &'a str,
+    pub explicit: bool,
+}
+
+/// Diff a desired state against what's installed. `keep` lists the explicit
+/// packages strict mode leaves alone (the config's `orphan_keep`). `in_repos`
+/// says whether a sync repo can provide a package name. Pure so it can be
+/// tested without a handle.
+pub(crate) fn compute_plan(
+    state: &DesiredState,
+    keep: &[String],
+    installed: &[InstalledEntry],
+    in_repos: impl Fn(&str) -> bool,
+) -> StatePlan {
+    let mut plan = StatePlan::default();
+    let required: HashSet<&str> = state.required.iter().map(|s| s.as_str()).collect();
+    let forbidden: HashSet<&str> = state.forbidden.iter().map(|s| s.as_str()).collect();
+    let keep: HashSet<&str> = keep.iter().map(|s| s.as_str()).collect();
+
+    let mut seen = HashSet::new();
+    for name in &state.required {
+        if !seen.insert(name.as_str()) {
+            continue;
+        }
+        match installed.iter().find(|p| p.name == name) {
+            Some(p) if !p.explicit => plan.mark_explicit.push(name.clone()),
+            Some(_) => {}
+            None if in_repos(name) => plan.install.push(name.clone()),
+            None => plan.unresolved.push(name.clone()),
+        }
+    }
+
+    for pkg in installed {
+        if forbidden.contains(pkg.name) {
+            plan.remove.push(pkg.name.to_string());
+        } else if state.strict
+            && pkg.explicit
+            && !required.contains(pkg.name)
+            && !keep.contains(pkg.name)
+        {
+            plan.mark_dependency.push(pkg.name.to_string());
+        }
+    }
+
+    plan.remove.sort();
+    plan.mark_dependency.sort();
+    plan
+}
+
+pub(crate) fn plan_is_empty(plan: &StatePlan) -> bool {
+    plan.install.is_empty()
+        && plan.remove.is_empty()
+        && plan.mark_explicit.is_empty()
+        && plan.mark_dependency.is_empty()
+        && plan.unresolved.is_empty()
+}
+
+pub(crate) fn plan_for(handle: &Alpm, state: &DesiredState, keep: &[String]) -> StatePlan {
+    let localdb = handle.localdb();
+    let installed: Vec<InstalledEntry> = localdb
+        .pkgs()
+        .iter()
+        .map(|p| InstalledEntry {
+            name: p.name(),
+            explicit: p.reason() == PackageReason::Explicit,
+        })
+        .collect();
+    let syncdbs = handle.syncdbs();
+    compute_plan(state, keep, &installed, |name| {
+        syncdbs.iter().any(|db| db.pkg(name).is_ok())
+    })
+}
+
+/// A manifest to plan against, with the config's `orphan_keep` and a label
+/// naming where the manifest came from.
+pub(crate) struct LoadedState {
+    pub state: DesiredState,
+    pub keep: Vec<String>,
+    pub source: String,
+}
+
+/// Load the manifest from stdin when `source` is `-`, otherwise from the app
+/// config. These run as root, so no other path is read.
+pub(crate) fn load_desired_state(source: Option<&str>) -> Result<LoadedState> {
+    if let Some(other) = source.filter(|s| *s != "-") {
+        anyhow::bail!(
+            "Invalid desired state source '{}': expected '-' for stdin",
+            other
+        );
+    }
+    let config = AppConfig::load()?;
+    let (state, source) = match source {
+        None => (config.desired_state, "config"),
+        Some(_) => {
+            use std::io::Read;
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .context("Failed to read desired state from stdin")?;
+            let state = serde_json::from_str(&content)
+                .context("Failed to parse desired state from stdin")?;
+            (state, "stdin")
+        }
+    };
+    state.validate()?;
+    Ok(LoadedState {
+        state,
+        keep: config.orphan_keep,
+        source: source.to_string(),
+    })
+}
+
+/// One-line summary of a non-empty plan, used for drift details in the
+/// scheduled run log.
+pub(crate) fn drift_summary(plan: &StatePlan) -> Vec<String> {
+    let mut details = Vec::new();
+    let mut push = |label: &str, names: &[String]| {
+        if !names.is_empty() {
+            details.push(format!("Drift: {}: {}", label, names.join(", ")));
+        }
+    };
+    push("missing", &plan.install);
+    push("forbidden installed", &plan.remove);
+    push("should be explicit", &plan.mark_explicit);
+    push("should be dependencies", &plan.mark_dependency);
+    push("unresolvable", &plan.unresolved);
+    details
+}
+
+pub fn state_plan(source: Option<&str>) -> Result<()> {
+    let loaded = load_desired_state(source)?;
+    let handle = get_handle()?;
+    let plan = plan_for(&handle, &loaded.state, &loaded.keep);
+
+    let response = StatePlanResponse {
+        source: loaded.source,
+        configured: !loaded.state.is_empty(),
+        in_sync: plan_is_empty(&plan),
+        plan,
+    };
+    emit_json(&response)
+}
+
+pub fn get_desired_state() -> Result<()> {
+    let config = AppConfig::load()?;
+    emit_json(&config.desired_state)
+}
+
+pub fn set_desired_state(state: DesiredState) -> Result<()> {
+    state.validate()?;
+    let state = AppConfig::update(|config| {
+        // Keep unknown manifest keys a newer version wrote.
+        let extra = std::mem::take(&mut config.desired_state.extra);
+        config.desired_state = DesiredState { extra, ..state };
+        Ok(config.desired_state.clone())
+    })?;
+    emit_json(&state)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn names(xs: &[&str]) -> Vec<String> {
+        xs.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn state(required: &[&str], forbidden: &[&str], strict: bool) -> DesiredState {
+        DesiredState {
+            required: names(required),
+            forbidden: names(forbidden),
+            strict,
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    fn installed<'a>(pkgs: &[(&'a str, bool)]) -> Vec<InstalledEntry<'a>> {
+        pkgs.iter()
+            .map(|&(name, explicit)| InstalledEntry { name, explicit })
+            .collect()
+    }
+
+    #[test]
+    fn plans_installs_removals_and_reason_changes() {
+        let local = installed(&[("base", true), ("git", false), ("telnet", true)]);
+        let plan = compute_plan(
+            &state(&["base", "git", "vim", "mystery"], &["telnet"], false),
+            &[],
+            &local,
+            |name| name != "mystery",
+        );
+        assert_eq!(plan.install, vec!["vim"]);
+        assert_eq!(plan.remove, vec!["telnet"]);
+        assert_eq!(plan.mark_explicit, vec!["git"]);
+        assert!(plan.mark_dependency.is_empty());
+        assert_eq!(plan.unresolved, vec!["mystery"]);
+    }
+
+    #[test]
+    fn strict_demotes_unlisted_explicit_packages() {
+        let local = installed(&[
+            ("base", true),
+            ("htop", true),
+            ("firefox", true),
+            ("zlib", false),
+        ]);
+        let plan = compute_plan(
+            &state(&["base"], &[], true),
+            &names(&["htop"]),
+            &local,
+            |_| true,
+        );
+        assert_eq!(plan.mark_dependency, vec!["firefox"]);
+        assert!(plan.remove.is_empty());
+
+        let lenient = compute_plan(&state(&["base"], &[], false), &[], &local, |_| true);
+        assert!(plan_is_empty(&lenient));
+    }
+
+    #[test]
+    fn forbidden_wins_over_strict_demotion() {
+        let local = installed(&[("telnet", true)]);
+        let plan = compute_plan(
+            &state(&[], &["telnet"], true),
+            &names(&["telnet"]),
+            &local,
+            |_| true,
+        );
+        assert_eq!(plan.remove, vec!["telnet"]);
+        assert!(plan.mark_dependency.is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_overlap_and_bad_names() {
+        assert!(state(&["vim"], &["vim"], false).validate().is_err());
+        assert!(state(&["bad name"], &[], false).validate().is_err());
+        assert!(state(&["vim"], &["nano"], true).validate().is_ok());
+    }
+
+    #[test]
+    fn legacy_keep_key_round_trips_as_extra() {
+        let state: DesiredState =
+            serde_json::from_str(r#"{"required":["vim"],"keep":["htop"]}"#).unwrap();
+        assert_eq!(state.extra.get("keep"), Some(&serde_json::json!(["htop"])));
+        let v = serde_json::to_value(&state).unwrap();
+        assert_eq!(v["keep"], serde_json::json!(["htop"]));
+    }
+
+    #[test]
+    fn rejects_manifest_paths() {
+        let err = load_desired_state(Some("/etc/shadow")).err().unwrap();
+        assert!(err.to_string().contains("expected '-'"));
+    }
+
+    #[test]
+    fn drift_summary_lists_only_nonempty_buckets() {
+        let plan = StatePlan {
+            install: vec!["vim".into()],
+            remove: vec!["telnet".into(), "rsh".into()],
+            ..StatePlan::default()
+        };
+        assert_eq!(
+            drift_summary(&plan),
+            vec![
+                "Drift: missing: vim".to_string(),
+                "Drift: forbidden installed: telnet, rsh".to_string()
+            ]
+        );
+    }
+}
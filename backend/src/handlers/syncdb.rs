@@ -0,0 +1,219 @@
+use alpm::{Alpm, Db, SigLevel};
+use anyhow::Result;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::alpm::get_handle;
+use crate::config::AppConfig;
+use crate::models::{PreflightWarning, SyncDbStatus, SyncDbStatusResponse, WarningSeverity};
+use crate::util::emit_json;
+
+const SECS_PER_DAY: i64 = 86_400;
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn mtime_secs(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs() as i64)
+}
+
+fn db_file(handle: &Alpm, db: &Db) -> std::path::PathBuf {
+    Path::new(handle.dbpath())
+        .join("sync")
+        .join(format!("{}.db", db.name()))
+}
+
+/// A db counts as stale once its file is older than `threshold_days`, or when
+/// it was never downloaded at all. A threshold of 0 disables the check.
+pub(crate) fn is_stale(mtime: Option<i64>, now: i64, threshold_days: u32) -> bool {
+    if threshold_days == 0 {
+        return false;
+    }
+    match mtime {
+        Some(t) => now.saturating_sub(t) > i64::from(threshold_days) * SECS_PER_DAY,
+        None => true,
+    }
+}
+
+/// pacman.conf-style names for the SigLevel bits in effect. alpm already
+/// resolves `USE_DEFAULT` to the global level when a db is asked.
+pub(crate) fn siglevel_names(level: SigLevel) -> Vec<String> {
+    fn part(
+        names: &mut Vec<String>,
+        level: SigLevel,
+        scope: &str,
+        required: SigLevel,
+        optional: SigLevel,
+        marginal: SigLevel,
+        unknown: SigLevel,
+    ) {
+        if level.contains(optional) {
+            names.push(format!("{scope}Optional"));
+        } else if level.contains(required) {
+            names.push(format!("{scope}Required"));
+        } else {
+            names.push(format!("{scope}Never"));
+            return;
+        }
+        names.push(if level.contains(marginal) && level.contains(unknown) {
+            format!("{scope}TrustAll")
+        } else {
+            format!("{scope}TrustedOnly")
+        });
+    }
+
+    let mut names = Vec::new();
+    part(
+        &mut names,
+        level,
+        "Package",
+        SigLevel::PACKAGE,
+        SigLevel::PACKAGE_OPTIONAL,
+        SigLevel::PACKAGE_MARGINAL_OK,
+        SigLevel::PACKAGE_UNKNOWN_OK,
+    );
+    part(
+        &mut names,
+        level,
+        "Database",
+        SigLevel::DATABASE,
+        SigLevel::DATABASE_OPTIONAL,
+        SigLevel::DATABASE_MARGINAL_OK,
+        SigLevel::DATABASE_UNKNOWN_OK,
+    );
+    names
+}
+
+fn stale_threshold_days() -> u32 {
+    AppConfig::load()
+        .map(|c| c.stale_db_days())
+        .unwrap_or(crate::config::DEFAULT_STALE_DB_DAYS)
+}
+
+/// Names of sync dbs older than `threshold_days`.
+pub(crate) fn stale_databases(handle: &Alpm, threshold_days: u32) -> Vec<String> {
+    let now = now_secs();
+    handle
+        .syncdbs()
+        .iter()
+        .filter(|db| is_stale(mtime_secs(&db_file(handle, db)), now, threshold_days))
+        .map(|db| db.name().to_string())
+        .collect()
+}
+
+pub(crate) fn stale_message(stale: &[String], threshold_days: u32) -> Option<String> {
+    if stale.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Sync databases older than {} days: {}. Results may be outdated; sync the databases first.",
+        threshold_days,
+        stale.join(", ")
+    ))
+}
+
+pub(crate) fn stale_preflight_warning(handle: &Alpm) -> Option<PreflightWarning> {
+    let threshold = stale_threshold_days();
+    let stale = stale_databases(handle, threshold);
+    let message = stale_message(&stale, threshold)?;
+    Some(PreflightWarning {
+        id: "stale_sync_databases".to_string(),
+        severity: WarningSeverity::Warning,
+        title: "Stale package databases".to_string(),
+        message,
+        packages: stale,
+    })
+}
+
+/// Per-repo freshness and signature state of the sync dbs. The "last update"
+/// is the newest build date among the db's packages: repo-add writes no
+/// timestamp into the db itself, so that is the closest internal marker.
+pub fn sync_db_status() -> Result<()> {
+    let handle = get_handle()?;
+    let threshold = stale_threshold_days();
+    let now = now_secs();
+
+    let databases = handle
+        .syncdbs()
+        .iter()
+        .map(|db| {
+            let path = db_file(&handle, db);
+            let metadata = std::fs::metadata(&path).ok();
+            let mtime = mtime_secs(&path);
+            let mut sig_path = path.clone().into_os_string();
+            sig_path.push(".sig");
+            let pkgs = db.pkgs();
+            let signature_present = Path::new(&sig_path).exists();
+            let validation = db.is_valid();
+            SyncDbStatus {
+                name: db.name().to_string(),
+                path: path.to_string_lossy().into_owned(),
+                exists: metadata.is_some(),
+                file_mtime: mtime,
+                last_update: pkgs.iter().map(|p| p.build_date()).max(),
+                size: metadata.map(|m| m.len()).unwrap_or(0),
+                package_count: pkgs.len(),
+                siglevel: siglevel_names(db.siglevel()),
+                signature_present,
+                signature_valid: signature_present.then_some(validation.is_ok()),
+                validation_error: validation.err().map(|e| e.to_string()),
+                age_days: mtime.map(|t| now.saturating_sub(t) / SECS_PER_DAY),
+                stale: is_stale(mtime, now, threshold),
+            }
+        })
+        .collect();
+
+    emit_json(&SyncDbStatusResponse {
+        databases,
+        stale_threshold_days: threshold,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_750_000_000;
+
+    #[test]
+    fn staleness_respects_threshold() {
+        assert!(!is_stale(Some(NOW - 6 * SECS_PER_DAY), NOW, 7));
+        assert!(is_stale(Some(NOW - 8 * SECS_PER_DAY), NOW, 7));
+        assert!(is_stale(None, NOW, 7));
+    }
+
+    #[test]
+    fn zero_threshold_disables_staleness() {
+        assert!(!is_stale(Some(0), NOW, 0));
+        assert!(!is_stale(None, NOW, 0));
+    }
+
+    #[test]
+    fn siglevel_names_mirror_pacman_conf() {
+        let level = SigLevel::PACKAGE | SigLevel::DATABASE | SigLevel::DATABASE_OPTIONAL;
+        assert_eq!(
+            siglevel_names(level),
+            vec![
+                "PackageRequired",
+                "PackageTrustedOnly",
+                "DatabaseOptional",
+                "DatabaseTrustedOnly"
+            ]
+        );
+        assert_eq!(
+            siglevel_names(SigLevel::NONE),
+            vec!["PackageNever", "DatabaseNever"]
+        );
+        let trust_all =
+            SigLevel::PACKAGE | SigLevel::PACKAGE_MARGINAL_OK | SigLevel::PACKAGE_UNKNOWN_OK;
+        assert_eq!(siglevel_names(trust_all)[1], "PackageTrustAll");
+    }
+}
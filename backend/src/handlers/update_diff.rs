@@ -0,0 +1,164 @@
+use alpm::{Alpm, Package, Pkg};
+use alpm_utils::configure_alpm;
+use anyhow::{Context, Result};
+use pacmanconf::Config;
+use std::collections::BTreeSet;
+use std::io::Read;
+
+use crate::alpm::{dep_string, find_available_updates, get_handle};
+use crate::models::{FieldChange, FileListDiff, ListDiff, UpdateDiff, UpdateDiffResponse};
+use crate::util::emit_json;
+
+pub(crate) const MAX_FILES_LISTED: usize = 500;
+const MAX_CHANGELOG_BYTES: u64 = 64 * 1024;
+
+pub(crate) fn list_diff<S: AsRef<str>>(old: &[S], new: &[S]) -> ListDiff {
+    let old: BTreeSet<&str> = old.iter().map(|s| s.as_ref()).collect();
+    let new: BTreeSet<&str> = new.iter().map(|s| s.as_ref()).collect();
+    ListDiff {
+        added: new.difference(&old).map(|s| s.to_string()).collect(),
+        removed: old.difference(&new).map(|s| s.to_string()).collect(),
+    }
+}
+
+pub(crate) fn file_diff(old: &[String], new: &[String]) -> FileListDiff {
+    let ListDiff { added, removed } = list_diff(old, new);
+    FileListDiff {
+        added_count: added.len(),
+        removed_count: removed.len(),
+        added: added.into_iter().take(MAX_FILES_LISTED).collect(),
+        removed: removed.into_iter().take(MAX_FILES_LISTED).collect(),
+    }
+}
+
+pub(crate) fn deps(list: alpm::AlpmList<&alpm::Dep>) -> Vec<String> {
+    list.iter().map(dep_string).collect()
+}
+
+pub(crate) fn file_names(pkg: &Pkg) -> Vec<String> {
+    pkg.files()
+        .files()
+        .iter()
+        .map(|f| String::from_utf8_lossy(f.name()).into_owned())
+        .collect()
+}
+
+fn read_changelog(pkg: &Package) -> Option<String> {
+    let mut text = String::new();
+    pkg.changelog()
+        .ok()?
+        .take(MAX_CHANGELOG_BYTES)
+        .read_to_string(&mut text)
+        .ok()?;
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// A second handle reading the `.files` sync dbs, which carry file lists
+/// that the regular `.db` files don't.
+fn files_handle() -> Result<Alpm> {
+    let conf = Config::new().context("Failed to parse pacman.conf")?;
+    let mut handle =
+        Alpm::new(&*conf.root_dir, &*conf.db_path).context("Failed to initialize alpm handle")?;
+    handle.set_dbext(".files");
+    configure_alpm(&mut handle, &conf).context("Failed to configure files database handle")?;
+    Ok(handle)
+}
+
+fn diff_package(
+    local: &Package,
+    sync: &Package,
+    repository: &str,
+    files_db: Option<&Alpm>,
+) -> UpdateDiff {
+    let packager = (local.packager() != sync.packager()).then(|| FieldChange {
+        old: local.packager().map(|s| s.to_string()),
+        new: sync.packager().map(|s| s.to_string()),
+    });
+    let licenses_old: Vec<&str> = local.licenses().iter().collect();
+    let licenses_new: Vec<&str> = sync.licenses().iter().collect();
+
+    let files = files_db
+        .and_then(|h| h.syncdbs().iter().find(|db| db.name() == repository))
+        .and_then(|db| db.pkg(sync.name()).ok())
+        .map(|files_pkg| file_diff(&file_names(local), &file_names(files_pkg)));
+
+    UpdateDiff {
+        name: local.name().to_string(),
+        current_version: local.version().to_string(),
+        new_version: sync.version().to_string(),
+        repository: repository.to_string(),
+        depends: list_diff(&deps(local.depends()), &deps(sync.depends())),
+        optdepends: list_diff(&deps(local.optdepends()), &deps(sync.optdepends())),
+        provides: list_diff(&deps(local.provides()), &deps(sync.provides())),
+        conflicts: list_diff(&deps(local.conflicts()), &deps(sync.conflicts())),
+        replaces: list_diff(&deps(local.replaces()), &deps(sync.replaces())),
+        licenses: list_diff(&licenses_old, &licenses_new),
+        packager,
+        files,
+        changelog: read_changelog(local),
+        url: archweb_client::official_package_url(
+            archweb_client::DEFAULT_BASE_URL,
+            repository,
+            sync.arch().unwrap_or("any"),
+            sync.name(),
+        ),
+    }
+}
+
+/// What changes for each pending update, optionally limited to `names`.
+pub fn update_diff(names: &[String]) -> Result<()> {
+    let handle = get_handle()?;
+    // File lists are a bonus: without synced .files dbs the rest still works.
+    let files_db = files_handle().ok();
+    let config = crate::config::AppConfig::load().unwrap_or_default();
+    let updates = find_available_updates(&handle, &config);
+
+    let diffs = updates
+        .iter()
+        .filter(|u| names.is_empty() || names.contains(&u.name))
+        .filter_map(|u| {
+            let local = handle.localdb().pkg(u.name.as_str()).ok()?;
+            let sync = handle
+                .syncdbs()
+                .iter()
+                .find(|db| db.name() == u.repository)?
+                .pkg(u.name.as_str())
+                .ok()?;
+            Some(diff_package(local, sync, &u.repository, files_db.as_ref()))
+        })
+        .collect::<Vec<_>>();
+
+    let response = UpdateDiffResponse {
+        files_available: diffs.iter().any(|d| d.files.is_some()),
+        diffs,
+    };
+    emit_json(&response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_diff_reports_added_and_removed_sorted() {
+        let diff = list_diff(
+            &["glibc", "zlib", "openssl>=3"],
+            &["glibc", "openssl>=3.1", "zstd"],
+        );
+        assert_eq!(diff.added, vec!["openssl>=3.1", "zstd"]);
+        assert_eq!(diff.removed, vec!["openssl>=3", "zlib"]);
+        assert_eq!(list_diff(&["a"], &["a"]), ListDiff::default());
+    }
+
+    #[test]
+    fn file_diff_caps_lists_but_keeps_counts() {
+        let old: Vec<String> = Vec::new();
+        let new: Vec<String> = (0..MAX_FILES_LISTED + 10)
+            .map(|i| format!("usr/share/doc/f{:04}", i))
+            .collect();
+        let diff = file_diff(&old, &new);
+        assert_eq!(diff.added_count, MAX_FILES_LISTED + 10);
+        assert_eq!(diff.added.len(), MAX_FILES_LISTED);
+        assert_eq!(diff.removed_count, 0);
+    }
+}
@@ -0,0 +1,194 @@
+use alpm::{Alpm, Package};
+use anyhow::Result;
+
+use crate::alpm::{dep_string, get_handle};
+use crate::handlers::dependency::matched_provide;
+use crate::handlers::footprint::LocalGraph;
+use crate::models::{WhyInstalledChain, WhyInstalledLink, WhyInstalledResponse};
+use crate::util::emit_json;
+
+/// A shortest path of node indices from an explicit package down to the
+/// target, and whether it needs an optdepends edge.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Chain {
+    pub path: Vec<usize>,
+    pub optional: bool,
+}
+
+fn follow(next: &[Option<usize>], start: usize) -> Vec<usize> {
+    let mut path = vec![start];
+    let mut cur = start;
+    while let Some(n) = next[cur] {
+        path.push(n);
+        cur = n;
+    }
+    path
+}
+
+/// One shortest chain per explicitly installed package that leads to
+/// `target`. Hard depends win: a chain only goes through optdepends when the
+/// root has no path of hard depends at all. Shorter chains sort first.
+pub(crate) fn shortest_chains(graph: &LocalGraph, target: usize) -> Vec<Chain> {
+    let hard = graph.next_hops_to(target, false);
+    let any = graph.next_hops_to(target, true);
+
+    let mut chains: Vec<Chain> = (0..graph.nodes.len())
+        .filter(|&i| i != target && graph.nodes[i].explicit)
+        .filter_map(|i| {
+            if hard[i].is_some() {
+                Some(Chain {
+                    path: follow(&hard, i),
+                    optional: false,
+                })
+            } else {
+                any[i].map(|_| Chain {
+                    path: follow(&any, i),
+                    optional: true,
+                })
+            }
+        })
+        .collect();
+    chains.sort_by(|a, b| {
+        a.path.len().cmp(&b.path.len()).then_with(|| {
+            graph.nodes[a.path[0]]
+                .name
+                .cmp(&graph.nodes[b.path[0]].name)
+        })
+    });
+    chains
+}
+
+/// The depends or optdepends entry of `from` that `to` satisfies, and the
+/// `provides` entry it matched through when not by name.
+fn link(handle: &Alpm, from: &Package, to: &Package) -> WhyInstalledLink {
+    let local = handle.localdb().pkgs();
+    let satisfies = |dep: &alpm::Dep| {
+        local
+            .find_satisfier(dep_string(dep).as_str())
+            .is_some_and(|p| p.name() == to.name())
+    };
+    let (kind, dep) = match from.depends().iter().find(|d| satisfies(d)) {
+        Some(d) => ("depends", Some(d)),
+        None => (
+            "optdepends",
+            from.optdepends().iter().find(|d| satisfies(d)),
+        ),
+    };
+    let via_provides = dep.and_then(|d| matched_provide(d, to));
+
+    WhyInstalledLink {
+        package: from.name().to_string(),
+        dependency: dep.map_or_else(|| to.name().to_string(), dep_string),
+        kind: kind.to_string(),
+        satisfied_by: to.name().to_string(),
+        via_provides,
+    }
+}
+
+pub fn why_installed(name: &str) -> Result<()> {
+    let handle = get_handle()?;
+    let graph = LocalGraph::from_handle(&handle);
+    let target = *graph
+        .index
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Package '{}' not found", name))?;
+    let localdb = handle.localdb();
+
+    let chains: Vec<WhyInstalledChain> = shortest_chains(&graph, target)
+        .into_iter()
+        .map(|chain| {
+            let links = chain
+                .path
+                .windows(2)
+                .filter_map(|pair| {
+                    let from = localdb.pkg(graph.nodes[pair[0]].name.as_str()).ok()?;
+                    let to = localdb.pkg(graph.nodes[pair[1]].name.as_str()).ok()?;
+                    Some(link(&handle, from, to))
+                })
+                .collect();
+            WhyInstalledChain {
+                root: graph.nodes[chain.path[0]].name.clone(),
+                optional: chain.optional,
+                links,
+            }
+        })
+        .collect();
+
+    let node = &graph.nodes[target];
+    emit_json(&WhyInstalledResponse {
+        name: node.name.clone(),
+        version: node.version.clone(),
+        explicit: node.explicit,
+        orphan: !node.explicit && chains.is_empty(),
+        chains,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::footprint::LocalNode;
+    use std::collections::HashMap;
+
+    fn node(name: &str, explicit: bool, deps: Vec<usize>, optdeps: Vec<usize>) -> LocalNode {
+        LocalNode {
+            name: name.to_string(),
+            version: "1-1".to_string(),
+            size: 0,
+            explicit,
+            deps,
+            optdeps,
+        }
+    }
+
+    fn graph(nodes: Vec<LocalNode>) -> LocalGraph {
+        let index: HashMap<String, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.name.clone(), i))
+            .collect();
+        LocalGraph { nodes, index }
+    }
+
+    #[test]
+    fn hard_chains_win_over_optional_ones() {
+        let g = graph(vec![
+            node("glib2", false, vec![], vec![]),
+            node("gimp", true, vec![2], vec![0]),
+            node("gegl", false, vec![0], vec![]),
+            node("viewer", true, vec![], vec![0]),
+            node("nautilus", true, vec![0], vec![]),
+            node("vim", true, vec![], vec![]),
+        ]);
+        let chains = shortest_chains(&g, 0);
+        assert_eq!(
+            chains,
+            vec![
+                Chain {
+                    path: vec![4, 0],
+                    optional: false
+                },
+                Chain {
+                    path: vec![3, 0],
+                    optional: true
+                },
+                Chain {
+                    path: vec![1, 2, 0],
+                    optional: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles_do_not_loop() {
+        let g = graph(vec![
+            node("app", true, vec![1], vec![]),
+            node("a", false, vec![2], vec![]),
+            node("b", false, vec![1, 3], vec![]),
+            node("lib", false, vec![], vec![]),
+        ]);
+        assert_eq!(shortest_chains(&g, 3)[0].path, vec![0, 1, 2, 3]);
+        assert!(shortest_chains(&g, 0).is_empty());
+    }
+}
@@ -1,10 +1,11 @@
 use anyhow::{Context, Result};
 use zbus::blocking::{Connection, Proxy};
-use zbus::zvariant::OwnedFd;
+use zbus::zvariant::{OwnedFd, OwnedValue};
 
 const LOGIND_DEST: &str = "org.freedesktop.login1";
 const LOGIND_PATH: &str = "/org/freedesktop/login1";
 const LOGIND_MANAGER_IFACE: &str = "org.freedesktop.login1.Manager";
+const DBUS_PROPS_IFACE: &str = "org.freedesktop.DBus.Properties";
 
 /// A held logind block inhibitor; dropping the fd releases it. Makes
 /// logind-mediated shutdowns wait for the commit, closing the reboot window
@@ -27,11 +28,44 @@ impl ShutdownInhibitor {
 
     fn try_take(why: &str) -> Result<Self> {
         let conn = Connection::system().context("connect to system bus")?;
-        let proxy = Proxy::new(&conn, LOGIND_DEST, LOGIND_PATH, LOGIND_MANAGER_IFACE)
-            .context("create logind Manager proxy")?;
-        let fd: OwnedFd = proxy
+        let fd: OwnedFd = logind_manager(&conn)?
             .call("Inhibit", &("shutdown", "cockpit-pacman", why, "block"))
             .context("call login1 Inhibit")?;
         Ok(Self { _fd: fd })
     }
 }
+
+fn logind_manager(conn: &Connection) -> Result<Proxy<'_>> {
+    Proxy::new(conn, LOGIND_DEST, LOGIND_PATH, LOGIND_MANAGER_IFACE)
+        .context("create logind Manager proxy")
+}
+
+/// Ask logind to reboot at `usec` (CLOCK_REALTIME microseconds). logind warns
+/// logged-in users and honours block inhibitors when the time comes.
+pub fn schedule_reboot(usec: u64) -> Result<()> {
+    let conn = Connection::system().context("connect to system bus")?;
+    logind_manager(&conn)?
+        .call::<_, _, ()>("ScheduleShutdown", &("reboot", usec))
+        .context("call login1 ScheduleShutdown")
+}
+
+/// The shutdown logind has scheduled, as its type ("reboot", "poweroff", ...)
+/// and CLOCK_REALTIME microseconds; None when nothing is scheduled.
+pub fn scheduled_shutdown() -> Result<Option<(String, u64)>> {
+    let conn = Connection::system().context("connect to system bus")?;
+    let props = Proxy::new(&conn, LOGIND_DEST, LOGIND_PATH, DBUS_PROPS_IFACE)
+        .context("create logind Properties proxy")?;
+    let value: OwnedValue = props
+        .call("Get", &(LOGIND_MANAGER_IFACE, "ScheduledShutdown"))
+        .context("read login1 ScheduledShutdown")?;
+    let (kind, usec) = <(String, u64)>::try_from(value).context("decode ScheduledShutdown")?;
+    Ok((!kind.is_empty() && usec > 0).then_some((kind, usec)))
+}
+
+/// Cancel the scheduled shutdown; false if there was none.
+pub fn cancel_scheduled_shutdown() -> Result<bool> {
+    let conn = Connection::system().context("connect to system bus")?;
+    logind_manager(&conn)?
+        .call("CancelScheduledShutdown", &())
+        .context("call login1 CancelScheduledShutdown")
+}
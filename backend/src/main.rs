@@ -1,27 +1,38 @@
 use std::env;
 use std::time::Duration;
 
+use cockpit_pacman_backend::config::{
+    DesiredState, Notifier, PostUpgradePolicy, ScheduleHooks, UpdatePolicy,
+};
 use cockpit_pacman_backend::handlers::{
-    add_ignored, check_lock, check_security, check_updates, clean_cache, delete_mirror_backup,
-    delete_repo_backup, downgrade_from_archive, downgrade_package, fetch_mirror_status, fetch_news,
-    get_cache_info, get_dependency_tree, get_grouped_history, get_history, get_pacnew_status,
-    get_reboot_status, get_schedule_config, get_scheduled_runs, get_services_status, init_keyring,
-    install_package, keyring_status, list_archive_versions, list_downgrades, list_ignored,
-    list_installed, list_mirror_backups, list_mirrors, list_orphans, list_repo_backups, list_repos,
-    local_package_info, mark_dismissed, mark_news_read, preflight_upgrade,
-    read_credentials_from_stdin, read_dismissal, read_news_state, record_interrupted,
-    refresh_keyring, refresh_mirrors, remove_ignored, remove_orphans, remove_package,
-    remove_stale_lock, restore_mirror_backup, restore_repo_backup, run_upgrade, save_mirrorlist,
-    save_repos, scheduled_run, search, security_info, set_schedule_config, signoff_list,
-    signoff_revoke, signoff_sign, sync_database, sync_package_info, test_mirrors,
+    add_ignored, add_ignored_group, add_orphan_keep, build_package, cancel_pending_reboot,
+    check_foreign_updates, check_lock, check_security, check_updates, clean_cache,
+    delete_mirror_backup, delete_repo_backup, dependency_graph, downgrade_from_archive,
+    downgrade_package, export_packages, fetch_mirror_status, fetch_news, get_cache_info,
+    get_dependency_tree, get_desired_state, get_grouped_history, get_history, get_pacnew_status,
+    get_reboot_status, get_schedule_config, get_scheduled_runs, get_services_status, group_info,
+    import_packages, import_plan, init_keyring, inspect_package, install_optdeps, install_package,
+    keyring_status, largest_footprints, list_archive_versions, list_downgrades, list_groups,
+    list_ignored, list_installed, list_mirror_backups, list_mirrors, list_notifiers,
+    list_optional_only, list_orphan_keep, list_orphans, list_policies, list_repo_backups,
+    list_repos, list_schedules, local_package_info, mark_dismissed, mark_news_read,
+    optional_dependencies, package_footprint, preflight_upgrade, read_credentials_from_stdin,
+    read_dismissal, read_news_state, record_interrupted, refresh_keyring, refresh_mirrors,
+    remove_ignored, remove_ignored_group, remove_notifier, remove_orphan_keep, remove_orphans,
+    remove_package, remove_policy, remove_schedule, remove_stale_lock, restore_mirror_backup,
+    restore_repo_backup, run_upgrade, save_mirrorlist, save_repos, scheduled_run, search,
+    security_info, set_desired_state, set_named_schedule, set_notifier, set_policy,
+    set_post_upgrade, set_schedule_config, set_schedule_hooks, signoff_list, signoff_revoke,
+    signoff_sign, state_apply, state_plan, sync_database, sync_db_status, sync_package_info,
+    sync_repos, system_consistency, test_mirrors, test_notifier, update_diff, why_installed,
 };
 use cockpit_pacman_backend::models::{MirrorEntry, RepoEntry, StructuredError};
 use cockpit_pacman_backend::util::{classify_error, emit_json, shutdown_event_writer};
 use cockpit_pacman_backend::validation::{
     validate_archive_filename, validate_depth, validate_direction, validate_json_payload_size,
     validate_keep_versions, validate_mirror_timeout, validate_mirror_url, validate_package_name,
-    validate_pagination, validate_refresh_protocol, validate_refresh_sort, validate_search_query,
-    validate_signoff_arg,
+    validate_pagination, validate_refresh_protocol, validate_refresh_sort, validate_repo_name,
+    validate_search_query, validate_signoff_arg,
 };
 
 /// Every dispatched subcommand name. Single source of truth for the help text
@@ -31,21 +42,48 @@ const COMMANDS: &[&str] = &[
     "list-installed",
     "check-updates",
     "preflight-upgrade",
+    "update-diff",
     "sync-database",
+    "sync-db-status",
     "upgrade",
     "local-package-info",
     "sync-package-info",
+    "inspect-package",
     "search",
     "keyring-status",
     "refresh-keyring",
     "init-keyring",
     "list-orphans",
+    "system-consistency",
     "remove-orphans",
     "install-package",
     "remove-package",
     "list-ignored",
     "add-ignored",
     "remove-ignored",
+    "add-ignored-group",
+    "remove-ignored-group",
+    "list-orphan-keep",
+    "add-orphan-keep",
+    "remove-orphan-keep",
+    "list-policies",
+    "set-policy",
+    "remove-policy",
+    "list-groups",
+    "group-info",
+    "optdepends",
+    "install-optdeps",
+    "build-package",
+    "list-optional-only",
+    "export-packages",
+    "import-plan",
+    "import-packages",
+    "package-footprint",
+    "largest-footprints",
+    "get-desired-state",
+    "set-desired-state",
+    "state-plan",
+    "state-apply",
     "cache-info",
     "clean-cache",
     "history",
@@ -56,9 +94,19 @@ const COMMANDS: &[&str] = &[
     "downgrade-archive",
     "get-schedule",
     "set-schedule",
+    "list-schedules",
+    "set-named-schedule",
+    "remove-schedule",
+    "set-schedule-hooks",
+    "set-post-upgrade",
+    "cancel-pending-reboot",
     "list-scheduled-runs",
     "scheduled-run",
     "scheduled-record-interrupted",
+    "list-notifiers",
+    "set-notifier",
+    "remove-notifier",
+    "test-notifier",
     "reboot-status",
     "services-status",
     "pacnew-status",
@@ -71,6 +119,8 @@ const COMMANDS: &[&str] = &[
     "restore-mirror-backup",
     "delete-mirror-backup",
     "dependency-tree",
+    "dependency-graph",
+    "why-installed",
     "fetch-news",
     "news-read-state",
     "news-mark-read",
@@ -78,6 +128,7 @@ const COMMANDS: &[&str] = &[
     "signoff-sign",
     "signoff-revoke",
     "check-security",
+    "check-foreign-updates",
     "security-info",
     "check-lock",
     "remove-stale-lock",
@@ -105,15 +156,26 @@ Commands:
                          repo: all|core|extra|multilib|user|...
                          sort_by: name|size|reason
                          sort_dir: asc|desc
-  check-updates          Check for available updates
+  check-updates [source] Check for available updates
+                         source: system (default) compares against the last sync;
+                         fresh refreshes a private copy of the sync dbs first,
+                         leaving /var/lib/pacman/sync untouched
+  update-diff [names]    Show what changes in each pending update: dependency,
+                         provides, conflicts, licence and file-list deltas,
+                         packager change, installed changelog, archweb link
+                         names: comma-separated packages (default: all updates)
   preflight-upgrade [ignore]
                          Check what the upgrade will do (requires root)
                          ignore: comma-separated list of packages to skip
                          Returns conflicts, replacements, keys to import
-  sync-database [force] [timeout]
+  sync-database [force] [timeout] [--repo NAME...]
                          Sync package databases (requires root)
                          force: true|false (default: true)
                          timeout: seconds (default: 300)
+                         --repo: only these repositories, each reported
+                         separately with the server that served it
+  sync-db-status         Show per-repository sync database freshness, size,
+                         package count, SigLevel and signature state
   upgrade [ignore] [timeout]
                          Perform system upgrade (requires root)
                          ignore: comma-separated list of packages to skip
@@ -122,20 +184,35 @@ Commands:
                          Get detailed info for an installed package
   sync-package-info NAME [REPO]
                          Get detailed info for a package from sync databases
+  inspect-package PATH|URL
+                         Inspect a package file without installing it: metadata,
+                         dependencies, files, backup files, install scriptlet,
+                         signature and the difference to the installed version
   search QUERY [offset] [limit] [installed] [sort_by] [sort_dir]
                          Search packages by name/description (paginated)
+                         QUERY: terms, field:value, /regex/, =exact, -field:value
+                         fields: name desc provides depends groups packager
+                                 license repo builddate (YYYY-MM-DD, <, <=, >, >=)
                          installed: all|installed|not-installed
-                         sort_by: name|repository|status
-                         sort_dir: asc|desc
+                         sort_by: relevance|name|repository|status (default: relevance)
+                         sort_dir: asc|desc (relevance is always best first)
   keyring-status         Get pacman keyring status and list keys
   refresh-keyring        Refresh keys from keyserver (requires root)
   init-keyring           Initialize and populate keyring (requires root)
-  list-orphans           List orphan packages (dependencies no longer required)
-  remove-orphans [timeout]
+  list-orphans [optdepends]
+                         List orphan packages: dependencies unreachable from any
+                         explicit or kept package, including orphan chains and cycles
+                         optdepends: false to let optional dependencies go (default: true)
+  system-consistency     Report partial-upgrade symptoms: packages newer than or
+                         missing from the repos, split and lib32 packages out of
+                         step, unsatisfied dependencies and conflicts (pacman -Dk)
+  remove-orphans [timeout] [optdepends]
                          Remove all orphan packages (requires root)
                          timeout: seconds (default: 300)
+                         optdepends: as for list-orphans
   install-package NAME [timeout]
                          Install a package from repositories (requires root)
+                         NAME may be a group; its missing members are installed
                          timeout: seconds (default: 300)
   remove-package NAME [timeout]
                          Remove an installed package (requires root)
@@ -143,6 +220,57 @@ Commands:
   list-ignored           List packages ignored during upgrades
   add-ignored NAME       Add a package to the ignored list (requires root)
   remove-ignored NAME    Remove a package from the ignored list (requires root)
+  add-ignored-group NAME Add a group to the ignored list (requires root)
+  remove-ignored-group NAME
+                         Remove a group from the ignored list (requires root)
+  list-orphan-keep       List dependencies kept back from orphan cleanup
+  add-orphan-keep NAME   Keep a dependency from being treated as orphan (requires root)
+  remove-orphan-keep NAME
+                         Remove a package from the orphan keep list (requires root)
+  list-policies          List per-package and per-group update policies
+  set-policy KIND NAME JSON
+                         Set the update policy for a package or group (requires root)
+                         KIND: package|group
+                         JSON: {"action":"auto|notify_only|hold","until_version":"",
+                                "until_date":"YYYY-MM-DD","reason":""}
+  remove-policy KIND NAME
+                         Remove a package or group update policy (requires root)
+  optdepends NAME        List a package's optional dependencies with install state
+                         and the sync package that would provide each one
+  install-optdeps NAME OPTDEP... [timeout]
+                         Install optional dependencies of an installed package
+                         as dependencies (requires root)
+                         timeout: seconds (default: 300)
+  list-optional-only     List dependencies kept only as another package's optdepend
+  build-package DIR [timeout]
+                         Build the PKGBUILD in DIR with makepkg as the
+                         cockpit-pacman-build user and install the result;
+                         build dependencies are installed as dependencies
+                         (requires root)
+                         timeout: seconds (default: 3600)
+  export-packages [format] [versions]
+                         Export explicitly installed packages, native and foreign
+                         format: json|text (default: json; text is pacman -Qqe style)
+                         versions: true to pin installed versions (default: false)
+  import-plan LIST       Diff a package list (JSON export or text) against the system
+  import-packages LIST [timeout]
+                         Install the packages from LIST that are missing (requires root)
+                         timeout: seconds (default: 300)
+  package-footprint NAME Size reclaimed by removing a package and the dependencies
+                         only it needs, plus the packages that would break
+  largest-footprints [limit]
+                         Rank explicit packages by reclaimable size (default: 20)
+  get-desired-state      Get the desired-state manifest from the app config
+  set-desired-state JSON Replace the desired-state manifest (requires root)
+                         JSON: {"required":[],"forbidden":[],"strict":false}
+  state-plan [-]         Diff the desired state against the system
+                         -: read the manifest JSON from stdin (default: app config)
+  state-apply [-] [timeout]
+                         Install, remove and re-mark packages to match the desired
+                         state in one transaction (requires root)
+                         timeout: seconds (default: 300)
+  list-groups            List package groups in the sync databases
+  group-info NAME        List a group's members per repository with install state
   cache-info             Show package cache information and size
   clean-cache [KEEP] [PKGS]  Clean package cache (requires root)
                          KEEP: number of versions to keep (default: 3)
@@ -170,16 +298,49 @@ Commands:
                          (requires root; downloads and verifies the package)
                          timeout: seconds (default: 300)
   get-schedule           Get scheduled upgrade configuration
-  set-schedule [enabled] [mode] [schedule] [max_packages]
+  set-schedule [enabled] [mode] [schedule] [max_packages] [min_age_days]
                          Configure scheduled upgrades (requires root)
                          enabled: true|false
-                         mode: check|upgrade
+                         mode: check|upgrade|security
                          schedule: systemd OnCalendar spec (e.g., weekly, daily)
                          max_packages: safety limit (0 = unlimited)
-  list-scheduled-runs [offset] [limit]
-                         List scheduled run history
-  scheduled-run          Execute scheduled operation (called by systemd)
-  scheduled-record-interrupted   Record a systemd-killed run (ExecStopPost)
+                         min_age_days: hold back packages built more recently
+                         than this, with their pkgbase and dependencies (0 = off)
+  list-schedules         List the default and all named schedules
+  set-named-schedule NAME [enabled] [mode] [schedule] [max_packages] [min_age_days]
+                         Create or change a named schedule with its own timer
+                         (requires root; arguments as for set-schedule)
+                         mode also: cache-clean|orphan-report|mirror-refresh|
+                         keyring-refresh
+  remove-schedule NAME   Disable and delete a named schedule (requires root)
+  set-schedule-hooks JSON [name]
+                         Set the commands run before and after a schedule's runs
+                         (requires root; without name, the default schedule)
+                         JSON: {"pre":[{"argv":["/path/to/drain"],"timeout_secs":300}],
+                                "post":[...]}
+  set-post-upgrade JSON [name]
+                         Set what a schedule does after a committed upgrade
+                         (requires root; without name, the default schedule)
+                         JSON: {"restart_services":true,"reboot_window":"02:00-05:00"}
+  cancel-pending-reboot  Cancel a reboot scheduled after an upgrade (requires root)
+  list-scheduled-runs [offset] [limit] [schedule] [status] [since] [until]
+                         List scheduled run history with totals
+                         schedule: only runs of this named schedule
+                         (empty = the default schedule, all = every schedule)
+                         status: ok, skipped or failed
+                         since/until: inclusive dates (YYYY-MM-DD)
+  scheduled-run [name]   Execute scheduled operation (called by systemd)
+  scheduled-record-interrupted [name]   Record a systemd-killed run (ExecStopPost)
+  list-notifiers         List notifiers for scheduled runs (secrets hidden)
+  set-notifier NAME JSON Add or replace a notifier (requires root)
+                         JSON: {"type":"sendmail","to":["root@localhost"],"from":""}
+                               {"type":"webhook","url":"https://...","secret":""}
+                               {"type":"command","argv":["/path/to/script"]}
+                         plus "events":[...] (empty = all) and "enabled":true
+                         events: run_failed|run_skipped|updates_available|
+                         security_advisories|reboot_required|pacnew_files
+  remove-notifier NAME   Delete a notifier (requires root)
+  test-notifier NAME     Send a test notification through a notifier (requires root)
   reboot-status          Check if system reboot is recommended
   services-status        List running services whose binaries were replaced
   pacnew-status          List .pacnew/.pacsave config files needing manual merge
@@ -206,6 +367,12 @@ Commands:
                          Get dependency tree for a package
                          depth: 1-10 (default: 3)
                          direction: forward|reverse|both (default: forward)
+  dependency-graph [format] [edges] [root]
+                         Whole-system dependency graph, resolved through provides
+                         format: json|dot|graphml (default: json)
+                         edges: comma-separated optdepends,makedepends (default: none)
+                         root: only what is reachable from this installed package
+  why-installed NAME     Shortest dependency chains from explicit packages to NAME
   fetch-news [days]      Fetch recent Arch Linux news items
                          days: lookback period (default: 30)
   news-read-state        Get read state of news items
@@ -218,6 +385,9 @@ Commands:
                          credentials: base64-encoded JSON {username, password} on stdin
   check-security         Check installed packages against Arch Security Tracker
   security-info NAME     Get security advisory history for a package
+  check-foreign-updates  Check foreign (non-repo) packages against the AUR:
+                         newer versions, out-of-date flags, orphaned or
+                         deleted packages; falls back to the last cached answer
   check-lock             Check if the pacman database lock exists and if it's stale
   remove-stale-lock      Remove a stale database lock (requires root)
   list-repos             List configured repositories from pacman.conf
@@ -318,7 +488,49 @@ fn parse_refresh_mirrors(args: &[String]) -> RefreshMirrorsArgs {
     (count, arg_opt(args, 3), protocol, sort_by)
 }
 
-type SetScheduleArgs = (Option<bool>, Option<String>, Option<String>, Option<usize>);
+/// `install-optdeps NAME OPTDEP... [timeout]`: a trailing all-digit argument is
+/// the timeout, since no package is named with digits only.
+fn parse_install_optdeps(args: &[String]) -> (Vec<String>, Option<u64>) {
+    let mut optdeps: Vec<String> = args.iter().skip(3).cloned().collect();
+    let timeout = optdeps
+        .last()
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|s| s.parse().ok());
+    if timeout.is_some() {
+        optdeps.pop();
+    }
+    optdeps.retain(|s| !s.is_empty());
+    (optdeps, timeout)
+}
+
+/// `sync-database [force] [timeout] [--repo NAME...]`: names after `--repo`
+/// may also be comma-separated.
+fn parse_sync_database(args: &[String]) -> (bool, Option<u64>, Vec<String>) {
+    let rest = args.get(2..).unwrap_or_default();
+    let split = rest.iter().position(|a| a == "--repo");
+    let positional = &rest[..split.unwrap_or(rest.len())];
+    let force = positional.first().map(|s| s == "true").unwrap_or(true);
+    let timeout = positional.get(1).and_then(|s| s.parse().ok());
+    let repos = split
+        .map(|i| {
+            rest[i + 1..]
+                .iter()
+                .flat_map(|a| a.split(','))
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    (force, timeout, repos)
+}
+
+type SetScheduleArgs = (
+    Option<bool>,
+    Option<String>,
+    Option<String>,
+    Option<usize>,
+    Option<u32>,
+);
 fn parse_set_schedule(args: &[String]) -> SetScheduleArgs {
     let enabled = args.get(2).and_then(|s| match s.as_str() {
         "true" => Some(true),
@@ -326,7 +538,14 @@ fn parse_set_schedule(args: &[String]) -> SetScheduleArgs {
         _ => None,
     });
     let max_packages = args.get(5).and_then(|s| s.parse().ok());
-    (enabled, arg_opt(args, 3), arg_opt(args, 4), max_packages)
+    let min_age_days = args.get(6).and_then(|s| s.parse().ok());
+    (
+        enabled,
+        arg_opt(args, 3),
+        arg_opt(args, 4),
+        max_packages,
+        min_age_days,
+    )
 }
 
 fn main() {
@@ -353,7 +572,23 @@ fn main() {
                 )
             })
         }
-        "check-updates" => check_updates(),
+        "check-updates" => check_updates(args.get(2).is_some_and(|s| s == "fresh")),
+        "update-diff" => {
+            let names: Vec<String> = args
+                .get(2)
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    s.split(',')
+                        .map(|p| p.trim().to_string())
+                        .filter(|p| !p.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            names
+                .iter()
+                .try_for_each(|n| validate_package_name(n))
+                .and_then(|_| update_diff(&names))
+        }
         "preflight-upgrade" => {
             let ignore_pkgs: Vec<String> = args
                 .get(2)
@@ -368,10 +603,21 @@ fn main() {
             preflight_upgrade(&ignore_pkgs)
         }
         "sync-database" => {
-            let force = args.get(2).map(|s| s == "true").unwrap_or(true);
-            let timeout = args.get(3).and_then(|s| s.parse().ok());
-            sync_database(force, timeout)
+            let (force, timeout, repos) = parse_sync_database(&args);
+            if args.iter().any(|a| a == "--repo") && repos.is_empty() {
+                eprintln!("Error: --repo requires at least one repository name");
+                std::process::exit(1);
+            }
+            if repos.is_empty() {
+                sync_database(force, timeout)
+            } else {
+                repos
+                    .iter()
+                    .try_for_each(|r| validate_repo_name(r))
+                    .and_then(|_| sync_repos(&repos, force, timeout))
+            }
         }
+        "sync-db-status" => sync_db_status(),
         "upgrade" => {
             let ignore_pkgs: Vec<String> = args
                 .get(2)
@@ -412,6 +658,13 @@ fn main() {
                     )
                 })
         }
+        "inspect-package" => {
+            if args.len() < 3 {
+                eprintln!("Error: inspect-package requires a file path or URL");
+                std::process::exit(1);
+            }
+            inspect_package(&args[2])
+        }
         "sync-package-info" => {
             if args.len() < 3 {
                 eprintln!("Error: sync-package-info requires a package name");
@@ -423,10 +676,11 @@ fn main() {
         "keyring-status" => keyring_status(),
         "refresh-keyring" => refresh_keyring(),
         "init-keyring" => init_keyring(),
-        "list-orphans" => list_orphans(),
+        "list-orphans" => list_orphans(args.get(2).is_none_or(|s| s != "false")),
+        "system-consistency" => system_consistency(),
         "remove-orphans" => {
             let timeout = args.get(2).and_then(|s| s.parse().ok());
-            remove_orphans(timeout)
+            remove_orphans(timeout, args.get(3).is_none_or(|s| s != "false"))
         }
         "install-package" => {
             if args.len() < 3 {
@@ -459,6 +713,139 @@ fn main() {
             }
             validate_package_name(&args[2]).and_then(|_| remove_ignored(&args[2]))
         }
+        "list-orphan-keep" => list_orphan_keep(),
+        "add-orphan-keep" => {
+            if args.len() < 3 {
+                eprintln!("Error: add-orphan-keep requires a package name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| add_orphan_keep(&args[2]))
+        }
+        "remove-orphan-keep" => {
+            if args.len() < 3 {
+                eprintln!("Error: remove-orphan-keep requires a package name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| remove_orphan_keep(&args[2]))
+        }
+        "list-policies" => list_policies(),
+        "set-policy" => {
+            if args.len() < 5 {
+                eprintln!("Error: set-policy requires a kind, a name and a JSON policy");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[3])
+                .and_then(|_| validate_json_payload_size(&args[4]))
+                .and_then(|_| {
+                    serde_json::from_str::<UpdatePolicy>(&args[4])
+                        .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))
+                })
+                .and_then(|policy| set_policy(&args[2], &args[3], policy))
+        }
+        "remove-policy" => {
+            if args.len() < 4 {
+                eprintln!("Error: remove-policy requires a kind and a name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[3]).and_then(|_| remove_policy(&args[2], &args[3]))
+        }
+        "add-ignored-group" => {
+            if args.len() < 3 {
+                eprintln!("Error: add-ignored-group requires a group name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| add_ignored_group(&args[2]))
+        }
+        "remove-ignored-group" => {
+            if args.len() < 3 {
+                eprintln!("Error: remove-ignored-group requires a group name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| remove_ignored_group(&args[2]))
+        }
+        "optdepends" => {
+            if args.len() < 3 {
+                eprintln!("Error: optdepends requires a package name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| optional_dependencies(&args[2]))
+        }
+        "install-optdeps" => {
+            let (optdeps, timeout) = parse_install_optdeps(&args);
+            if args.len() < 3 || optdeps.is_empty() {
+                eprintln!("Error: install-optdeps requires a package name and optdepends");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2])
+                .and_then(|_| optdeps.iter().try_for_each(|d| validate_package_name(d)))
+                .and_then(|_| install_optdeps(&args[2], &optdeps, timeout))
+        }
+        "list-optional-only" => list_optional_only(),
+        "build-package" => {
+            if args.len() < 3 {
+                eprintln!("Error: build-package requires a PKGBUILD directory");
+                std::process::exit(1);
+            }
+            let timeout = args.get(3).and_then(|s| s.parse().ok());
+            build_package(&args[2], timeout)
+        }
+        "export-packages" => {
+            let format = arg_opt(&args, 2);
+            let with_versions = args.get(3).is_some_and(|s| s == "true");
+            export_packages(format.as_deref(), with_versions)
+        }
+        "import-plan" => {
+            if args.len() < 3 {
+                eprintln!("Error: import-plan requires a package list");
+                std::process::exit(1);
+            }
+            validate_json_payload_size(&args[2]).and_then(|_| import_plan(&args[2]))
+        }
+        "import-packages" => {
+            if args.len() < 3 {
+                eprintln!("Error: import-packages requires a package list");
+                std::process::exit(1);
+            }
+            let timeout = args.get(3).and_then(|s| s.parse().ok());
+            validate_json_payload_size(&args[2]).and_then(|_| import_packages(&args[2], timeout))
+        }
+        "package-footprint" => {
+            if args.len() < 3 {
+                eprintln!("Error: package-footprint requires a package name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| package_footprint(&args[2]))
+        }
+        "largest-footprints" => {
+            let limit = arg_usize(&args, 2, 20);
+            validate_pagination(0, limit).and_then(|_| largest_footprints(limit))
+        }
+        "get-desired-state" => get_desired_state(),
+        "set-desired-state" => {
+            if args.len() < 3 {
+                eprintln!("Error: set-desired-state requires a JSON manifest");
+                std::process::exit(1);
+            }
+            validate_json_payload_size(&args[2])
+                .and_then(|_| {
+                    serde_json::from_str::<DesiredState>(&args[2])
+                        .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))
+                })
+                .and_then(set_desired_state)
+        }
+        "state-plan" => state_plan(arg_opt(&args, 2).as_deref()),
+        "state-apply" => {
+            let timeout = args.get(3).and_then(|s| s.parse().ok());
+            state_apply(arg_opt(&args, 2).as_deref(), timeout)
+        }
+        "list-groups" => list_groups(),
+        "group-info" => {
+            if args.len() < 3 {
+                eprintln!("Error: group-info requires a group name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| group_info(&args[2]))
+        }
         "cache-info" => get_cache_info(),
         "clean-cache" => {
             let keep_versions = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(3);
@@ -542,16 +929,114 @@ fn main() {
         }
         "get-schedule" => get_schedule_config(),
         "set-schedule" => {
-            let (enabled, mode, schedule, max_packages) = parse_set_schedule(&args);
-            set_schedule_config(enabled, mode.as_deref(), schedule.as_deref(), max_packages)
+            let (enabled, mode, schedule, max_packages, min_age_days) = parse_set_schedule(&args);
+            set_schedule_config(
+                enabled,
+                mode.as_deref(),
+                schedule.as_deref(),
+                max_packages,
+                min_age_days,
+            )
+        }
+        "list-schedules" => list_schedules(),
+        "set-named-schedule" => {
+            if args.len() < 3 {
+                eprintln!("Error: set-named-schedule requires a NAME");
+                std::process::exit(1);
+            }
+            // Same positions as set-schedule once NAME is skipped.
+            let (enabled, mode, schedule, max_packages, min_age_days) =
+                parse_set_schedule(&args[1..]);
+            set_named_schedule(
+                &args[2],
+                enabled,
+                mode.as_deref(),
+                schedule.as_deref(),
+                max_packages,
+                min_age_days,
+            )
+        }
+        "remove-schedule" => {
+            if args.len() < 3 {
+                eprintln!("Error: remove-schedule requires a NAME");
+                std::process::exit(1);
+            }
+            remove_schedule(&args[2])
         }
+        "set-schedule-hooks" => {
+            if args.len() < 3 {
+                eprintln!("Error: set-schedule-hooks requires a JSON hook set");
+                std::process::exit(1);
+            }
+            validate_json_payload_size(&args[2])
+                .and_then(|_| {
+                    serde_json::from_str::<ScheduleHooks>(&args[2])
+                        .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))
+                })
+                .and_then(|hooks| set_schedule_hooks(arg_opt(&args, 3).as_deref(), hooks))
+        }
+        "set-post-upgrade" => {
+            if args.len() < 3 {
+                eprintln!("Error: set-post-upgrade requires a JSON policy");
+                std::process::exit(1);
+            }
+            validate_json_payload_size(&args[2])
+                .and_then(|_| {
+                    serde_json::from_str::<PostUpgradePolicy>(&args[2])
+                        .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))
+                })
+                .and_then(|policy| set_post_upgrade(arg_opt(&args, 3).as_deref(), policy))
+        }
+        "cancel-pending-reboot" => cancel_pending_reboot(),
         "list-scheduled-runs" => {
             let offset = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
             let limit = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(50);
-            validate_pagination(offset, limit).and_then(|_| get_scheduled_runs(offset, limit))
+            // Unlike arg_opt_not_all, an empty name selects the default
+            // schedule; only "all" (or no argument) selects every schedule.
+            let schedule = args.get(4).map(|s| s.as_str()).filter(|s| *s != "all");
+            let status = arg_opt(&args, 5);
+            let since = arg_opt(&args, 6);
+            let until = arg_opt(&args, 7);
+            validate_pagination(offset, limit).and_then(|_| {
+                get_scheduled_runs(
+                    offset,
+                    limit,
+                    schedule,
+                    status.as_deref(),
+                    since.as_deref(),
+                    until.as_deref(),
+                )
+            })
+        }
+        "scheduled-run" => scheduled_run(arg_opt(&args, 2).as_deref()),
+        "scheduled-record-interrupted" => record_interrupted(arg_opt(&args, 2).as_deref()),
+        "list-notifiers" => list_notifiers(),
+        "set-notifier" => {
+            if args.len() < 4 {
+                eprintln!("Error: set-notifier requires a NAME and a JSON notifier");
+                std::process::exit(1);
+            }
+            validate_json_payload_size(&args[3])
+                .and_then(|_| {
+                    serde_json::from_str::<Notifier>(&args[3])
+                        .map_err(|e| anyhow::anyhow!("Invalid JSON: {}", e))
+                })
+                .and_then(|notifier| set_notifier(&args[2], notifier))
+        }
+        "remove-notifier" => {
+            if args.len() < 3 {
+                eprintln!("Error: remove-notifier requires a NAME");
+                std::process::exit(1);
+            }
+            remove_notifier(&args[2])
+        }
+        "test-notifier" => {
+            if args.len() < 3 {
+                eprintln!("Error: test-notifier requires a NAME");
+                std::process::exit(1);
+            }
+            test_notifier(&args[2])
         }
-        "scheduled-run" => scheduled_run(),
-        "scheduled-record-interrupted" => record_interrupted(),
         "reboot-status" => get_reboot_status(),
         "services-status" => get_services_status(),
         "pacnew-status" => get_pacnew_status(),
@@ -630,6 +1115,23 @@ fn main() {
                 .and_then(|_| validate_direction(direction))
                 .and_then(|_| get_dependency_tree(&args[2], depth, direction))
         }
+        "dependency-graph" => {
+            let format = arg_opt(&args, 2);
+            let edges = arg_opt(&args, 3);
+            let root = arg_opt(&args, 4);
+            root.as_deref()
+                .map_or(Ok(()), validate_package_name)
+                .and_then(|_| {
+                    dependency_graph(format.as_deref(), edges.as_deref(), root.as_deref())
+                })
+        }
+        "why-installed" => {
+            if args.len() < 3 {
+                eprintln!("Error: why-installed requires a package name");
+                std::process::exit(1);
+            }
+            validate_package_name(&args[2]).and_then(|_| why_installed(&args[2]))
+        }
         "fetch-news" => {
             let days = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(30u32);
             fetch_news(days)
@@ -698,6 +1200,7 @@ fn main() {
                 .and_then(|creds| signoff_revoke(&creds, &args[2..]))
         }
         "check-security" => check_security(),
+        "check-foreign-updates" => check_foreign_updates(),
         "security-info" => {
             if args.len() < 3 {
                 eprintln!("Error: security-info requires a package name");
@@ -800,6 +1303,67 @@ mod tests {
         parts.iter().map(|s| s.to_string()).collect()
     }
 
+    #[test]
+    fn sync_database_repo_selection() {
+        assert_eq!(
+            parse_sync_database(&svec(&["bin", "sync-database"])),
+            (true, None, vec![])
+        );
+        assert_eq!(
+            parse_sync_database(&svec(&["bin", "sync-database", "false", "60"])),
+            (false, Some(60), vec![])
+        );
+        assert_eq!(
+            parse_sync_database(&svec(&[
+                "bin",
+                "sync-database",
+                "--repo",
+                "core",
+                "extra,multilib"
+            ])),
+            (true, None, svec(&["core", "extra", "multilib"]))
+        );
+        assert_eq!(
+            parse_sync_database(&svec(&[
+                "bin",
+                "sync-database",
+                "true",
+                "120",
+                "--repo",
+                "chaotic-aur"
+            ])),
+            (true, Some(120), svec(&["chaotic-aur"]))
+        );
+    }
+
+    #[test]
+    fn install_optdeps_trailing_timeout() {
+        assert_eq!(
+            parse_install_optdeps(&svec(&["bin", "install-optdeps", "mpv", "yt-dlp"])),
+            (vec!["yt-dlp".to_string()], None)
+        );
+        assert_eq!(
+            parse_install_optdeps(&svec(&[
+                "bin",
+                "install-optdeps",
+                "mpv",
+                "yt-dlp",
+                "libcdio",
+                "600"
+            ])),
+            (vec!["yt-dlp".to_string(), "libcdio".to_string()], Some(600))
+        );
+        // Names that merely start with a digit are optdepends, not timeouts.
+        assert_eq!(
+            parse_install_optdeps(&svec(&["bin", "install-optdeps", "foo", "7zip"])),
+            (vec!["7zip".to_string()], None)
+        );
+        assert_eq!(
+            parse_install_optdeps(&svec(&["bin", "install-optdeps", "foo", "300"])),
+            (vec![], Some(300))
+        );
+    }
+
     #[test]
     fn list_installed_positions() {
         let args = svec(&[
@@ -942,22 +1506,29 @@ mod tests {
                 "true",
                 "upgrade",
                 "weekly",
-                "50"
+                "50",
+                "2"
             ])),
             (
                 Some(true),
                 Some("upgrade".to_string()),
                 Some("weekly".to_string()),
-                Some(50)
+                Some(50),
+                Some(2)
             )
         );
         assert_eq!(
             parse_set_schedule(&svec(&["bin", "set-schedule", "false"])),
-            (Some(false), None, None, None)
+            (Some(false), None, None, None, None)
         );
         assert_eq!(
             parse_set_schedule(&svec(&["bin", "set-schedule"])),
-            (None, None, None, None)
+            (None, None, None, None, None)
+        );
+        let named = svec(&["bin", "set-named-schedule", "nightly", "true", "security"]);
+        assert_eq!(
+            parse_set_schedule(&named[1..]),
+            (Some(true), Some("security".to_string()), None, None, None)
         );
     }
 
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use crate::config::PolicyAction;
+
 /// Wire mirror of archweb_client's Signoff so the type can derive TS bindings
 /// without depending on the external crate's type. Mapped from the client type
 /// in the signoff handler.
@@ -62,6 +64,38 @@ pub struct UpdatesResponse {
     pub warnings: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct SyncDbStatus {
+    pub name: String,
+    pub path: String,
+    pub exists: bool,
+    #[ts(type = "number | null")]
+    pub file_mtime: Option<i64>,
+    /// Newest package build date in the db.
+    #[ts(type = "number | null")]
+    pub last_update: Option<i64>,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub package_count: usize,
+    pub siglevel: Vec<String>,
+    pub signature_present: bool,
+    /// Whether libalpm accepts the db and its signature; null when there is
+    /// no signature to check.
+    pub signature_valid: Option<bool>,
+    pub validation_error: Option<String>,
+    #[ts(type = "number | null")]
+    pub age_days: Option<i64>,
+    pub stale: bool,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct SyncDbStatusResponse {
+    pub databases: Vec<SyncDbStatus>,
+    pub stale_threshold_days: u32,
+}
+
 #[derive(Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../src/bindings/index.ts")]
 pub struct UpdateInfo {
@@ -77,6 +111,13 @@ pub struct UpdateInfo {
     pub repository: String,
     #[serde(default)]
     pub ignored: bool,
+    /// The update policy in effect, if any. Held updates are also `ignored`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub policy: Option<PolicyAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub policy_note: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, TS)]
@@ -139,6 +180,49 @@ pub struct SearchResponse {
     pub repositories: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GroupSummary {
+    pub name: String,
+    pub repositories: Vec<String>,
+    pub total: usize,
+    pub installed: usize,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GroupListResponse {
+    pub groups: Vec<GroupSummary>,
+    pub total: usize,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GroupMember {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub installed: bool,
+    pub installed_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GroupRepository {
+    pub repository: String,
+    pub members: Vec<GroupMember>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GroupInfoResponse {
+    pub name: String,
+    pub repositories: Vec<GroupRepository>,
+    pub total: usize,
+    pub total_installed: usize,
+    pub ignored: bool,
+}
+
 #[derive(Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../src/bindings/index.ts")]
 pub struct SyncPackageDetails {
@@ -297,6 +381,14 @@ pub enum StreamEvent {
         #[ts(optional)]
         message: Option<String>,
     },
+    #[serde(rename = "repo_sync")]
+    RepoSync {
+        repo: String,
+        success: bool,
+        updated: bool,
+        server: Option<String>,
+        message: Option<String>,
+    },
     #[serde(rename = "mirror_test")]
     MirrorTest {
         url: String,
@@ -346,6 +438,197 @@ pub struct OrphanResponse {
     pub total_size: i64,
 }
 
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct OptionalDependency {
+    pub name: String,
+    pub depend: String,
+    pub description: Option<String>,
+    pub installed: bool,
+    pub satisfied_by: Option<String>,
+    pub provider: Option<String>,
+    pub provider_repository: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct OptionalDependenciesResponse {
+    pub package: String,
+    pub installed: bool,
+    pub optdepends: Vec<OptionalDependency>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct OptionalOnlyPackage {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    #[ts(type = "number")]
+    pub installed_size: i64,
+    pub optional_for: Vec<String>,
+    pub repository: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct OptionalOnlyResponse {
+    pub packages: Vec<OptionalOnlyPackage>,
+    #[ts(type = "number")]
+    pub total_size: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ExportedPackage {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct PackageExport {
+    #[serde(default)]
+    pub native: Vec<ExportedPackage>,
+    #[serde(default)]
+    pub foreign: Vec<ExportedPackage>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ImportTarget {
+    pub name: String,
+    pub version: String,
+    pub repository: String,
+    pub pinned_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ImportPlan {
+    pub to_install: Vec<ImportTarget>,
+    pub already_installed: Vec<String>,
+    pub foreign: Vec<String>,
+    pub unknown: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct StatePlan {
+    pub install: Vec<String>,
+    pub remove: Vec<String>,
+    pub mark_explicit: Vec<String>,
+    pub mark_dependency: Vec<String>,
+    /// Required packages that are neither installed nor in any sync repo.
+    pub unresolved: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct StatePlanResponse {
+    pub source: String,
+    pub configured: bool,
+    pub in_sync: bool,
+    pub plan: StatePlan,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct FootprintDependency {
+    pub name: String,
+    #[ts(type = "number")]
+    pub installed_size: i64,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct PackageFootprint {
+    pub name: String,
+    pub version: String,
+    #[ts(type = "number")]
+    pub installed_size: i64,
+    /// The package plus every dependency that would be left unneeded.
+    #[ts(type = "number")]
+    pub reclaimable_size: i64,
+    pub exclusive_dependencies: Vec<FootprintDependency>,
+    /// Installed packages that depend on something being removed.
+    pub breaks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct FootprintSummary {
+    pub name: String,
+    pub version: String,
+    #[ts(type = "number")]
+    pub installed_size: i64,
+    #[ts(type = "number")]
+    pub reclaimable_size: i64,
+    pub exclusive_count: usize,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct LargestFootprintsResponse {
+    pub packages: Vec<FootprintSummary>,
+    pub total: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ListDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct FieldChange {
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct FileListDiff {
+    /// Capped lists; the counts are the full totals.
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub added_count: usize,
+    pub removed_count: usize,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct UpdateDiff {
+    pub name: String,
+    pub current_version: String,
+    pub new_version: String,
+    pub repository: String,
+    pub depends: ListDiff,
+    pub optdepends: ListDiff,
+    pub provides: ListDiff,
+    pub conflicts: ListDiff,
+    pub replaces: ListDiff,
+    pub licenses: ListDiff,
+    pub packager: Option<FieldChange>,
+    /// None when no files database is synced for the repository.
+    pub files: Option<FileListDiff>,
+    /// The installed package's changelog, when it ships one.
+    pub changelog: Option<String>,
+    /// The new version's archweb page; None outside the official repos.
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct UpdateDiffResponse {
+    pub diffs: Vec<UpdateDiff>,
+    pub files_available: bool,
+}
+
 #[derive(Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../src/bindings/index.ts")]
 pub struct CachePackage {
@@ -445,6 +728,112 @@ pub struct ScheduledRunEntry {
     pub packages_upgraded: usize,
     pub error: Option<String>,
     pub details: Vec<String>,
+    /// Name of the schedule that ran; absent for the default schedule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub schedule: Option<String>,
+    /// Updates the run found and what became of each; empty for runs logged
+    /// before these were recorded.
+    #[serde(default)]
+    pub packages: Vec<ScheduledPackage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub durations: Option<RunDurations>,
+    /// Bytes the run had to download; cached packages count for nothing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub download_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub skip_reason: Option<SkipReason>,
+}
+
+/// What became of one update in a scheduled run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum PackageResult {
+    Upgraded,
+    /// Found but not applied: a check run, or a run skipped or failed.
+    Available,
+    HeldByPolicy,
+    /// Held back by `min_age_days`.
+    TooNew,
+    /// Reported only by a security run.
+    Deferred,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ScheduledPackage {
+    pub name: String,
+    /// None for a package the upgrade newly installed.
+    pub old_version: Option<String>,
+    pub new_version: String,
+    pub repository: String,
+    #[ts(type = "number")]
+    pub download_size: i64,
+    #[ts(type = "number")]
+    pub installed_size: i64,
+    pub result: PackageResult,
+}
+
+/// Why a scheduled run was skipped.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    DbLocked,
+    PreHookFailed,
+    /// More updates than `max_packages`.
+    SafetyLimit,
+    /// A security run's fixes need more updates than its limit.
+    SecurityLimit,
+    /// Conflicts, removals or key imports need a person.
+    ManualIntervention,
+}
+
+/// Wall-clock time of each phase a run reached, in milliseconds.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct RunDurations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub sync_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub prepare_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub download_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub commit_ms: Option<u64>,
+    /// Pre- and post-run hooks together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub hooks_ms: Option<u64>,
+}
+
+impl RunDurations {
+    pub fn is_empty(&self) -> bool {
+        *self == RunDurations::default()
+    }
+}
+
+/// Totals over the runs matching a `list-scheduled-runs` filter, before
+/// pagination.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ScheduledRunStats {
+    pub ok: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub packages_upgraded: usize,
+    #[ts(type = "number")]
+    pub download_bytes: u64,
+    pub last_ok: Option<String>,
+    pub last_failed: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, TS)]
@@ -452,6 +841,8 @@ pub struct ScheduledRunEntry {
 pub struct ScheduledRunsResponse {
     pub runs: Vec<ScheduledRunEntry>,
     pub total: usize,
+    #[serde(default)]
+    pub stats: ScheduledRunStats,
 }
 
 #[derive(Serialize, Deserialize, TS)]
@@ -782,3 +1173,238 @@ pub struct SaveReposResponse {
     pub backup_path: Option<String>,
     pub message: String,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct NamedVersion {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct NewerThanRepo {
+    pub name: String,
+    pub installed_version: String,
+    pub repo_version: String,
+    pub repository: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct SplitVersionMismatch {
+    pub pkgbase: String,
+    pub packages: Vec<NamedVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct Lib32Mismatch {
+    pub name: String,
+    pub version: String,
+    pub native: String,
+    pub native_version: String,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct MissingDependency {
+    pub package: String,
+    pub dependency: String,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct InstalledConflict {
+    pub package1: String,
+    pub package2: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct SystemConsistencyResponse {
+    pub newer_than_repo: Vec<NewerThanRepo>,
+    pub not_in_repos: Vec<NamedVersion>,
+    pub split_mismatches: Vec<SplitVersionMismatch>,
+    pub lib32_mismatches: Vec<Lib32Mismatch>,
+    pub missing_dependencies: Vec<MissingDependency>,
+    pub conflicts: Vec<InstalledConflict>,
+    pub issue_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ForeignPackage {
+    pub name: String,
+    pub installed_version: String,
+    /// None when the package is not (or no longer) in the AUR.
+    pub aur_version: Option<String>,
+    pub newer_available: bool,
+    /// Unix time the AUR package was flagged out of date.
+    #[ts(type = "number | null")]
+    pub out_of_date: Option<i64>,
+    pub maintainer: Option<String>,
+    pub orphaned: bool,
+    pub in_aur: bool,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct ForeignUpdatesResponse {
+    pub packages: Vec<ForeignPackage>,
+    /// Foreign packages the stale cache has no entry for.
+    pub unchecked: Vec<String>,
+    /// True when served from the on-disk cache because the AUR was unreachable.
+    pub stale: bool,
+    #[ts(type = "number | null")]
+    pub cached_at: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct BackupFile {
+    pub path: String,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct SignatureInfo {
+    pub status: String,
+    pub validity: String,
+    pub fingerprint: String,
+    /// Signer uid from the pacman keyring; None when the key is not in it.
+    pub uid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct PackageSignatureCheck {
+    pub present: bool,
+    pub valid: bool,
+    pub signatures: Vec<SignatureInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct InstalledComparison {
+    pub installed_version: String,
+    /// upgrade, downgrade or reinstall
+    pub change: String,
+    pub depends: ListDiff,
+    pub provides: ListDiff,
+    pub conflicts: ListDiff,
+    pub files: FileListDiff,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct InspectPackageResponse {
+    pub source: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub arch: Option<String>,
+    pub packager: Option<String>,
+    #[ts(type = "number")]
+    pub build_date: i64,
+    #[ts(type = "number")]
+    pub installed_size: i64,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub depends: Vec<String>,
+    pub optdepends: Vec<String>,
+    pub makedepends: Vec<String>,
+    pub provides: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub replaces: Vec<String>,
+    /// Capped list; `files_truncated` says whether the package has more.
+    pub files: Vec<String>,
+    pub files_truncated: bool,
+    pub backup: Vec<BackupFile>,
+    pub has_scriptlet: bool,
+    pub scriptlet: Option<String>,
+    pub signature: PackageSignatureCheck,
+    pub installed: Option<InstalledComparison>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct WhyInstalledLink {
+    pub package: String,
+    pub dependency: String,
+    /// depends or optdepends
+    pub kind: String,
+    pub satisfied_by: String,
+    /// The `provides` entry of `satisfied_by` that matched, when it was not
+    /// pulled in by name.
+    pub via_provides: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct WhyInstalledChain {
+    /// The explicitly installed package the chain starts from.
+    pub root: String,
+    /// True when no chain of hard depends exists and an optdepends edge is
+    /// needed to reach the package.
+    pub optional: bool,
+    pub links: Vec<WhyInstalledLink>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct WhyInstalledResponse {
+    pub name: String,
+    pub version: String,
+    pub explicit: bool,
+    pub chains: Vec<WhyInstalledChain>,
+    /// Installed as a dependency but nothing explicit leads to it any more.
+    pub orphan: bool,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GraphPackage {
+    pub name: String,
+    pub version: String,
+    pub installed: bool,
+    pub reason: Option<String>,
+    pub repository: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct GraphDependency {
+    pub source: String,
+    pub target: String,
+    /// depends, optdepends or makedepends
+    pub edge_type: String,
+    /// The dependency string as declared, version constraint included.
+    pub dependency: String,
+    /// The `provides` entry of `target` that satisfied it, when not by name.
+    pub via_provides: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct UnresolvedDependency {
+    pub package: String,
+    pub dependency: String,
+    pub edge_type: String,
+}
+
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/bindings/index.ts")]
+pub struct DependencyGraphResponse {
+    pub root: Option<String>,
+    pub nodes: Vec<GraphPackage>,
+    pub edges: Vec<GraphDependency>,
+    /// Groups of packages that depend on each other in a loop, by hard
+    /// depends only.
+    pub cycles: Vec<Vec<String>>,
+    pub unresolved: Vec<UnresolvedDependency>,
+}
@@ -5,9 +5,10 @@ use crate::models::{
 };
 use crate::util::parse_package_filename;
 use crate::validation::{
-    validate_archive_filename, validate_depth, validate_direction, validate_json_payload_size,
-    validate_keep_versions, validate_max_packages, validate_mirror_timeout, validate_mirror_url,
-    validate_package_name, validate_pagination, validate_schedule, validate_search_query,
+    validate_archive_filename, validate_date, validate_depth, validate_direction,
+    validate_json_payload_size, validate_keep_versions, validate_max_packages,
+    validate_mirror_timeout, validate_mirror_url, validate_package_name, validate_pagination,
+    validate_run_status, validate_schedule, validate_schedule_name, validate_search_query,
     validate_version,
 };
 
@@ -58,6 +59,8 @@ fn test_updates_response_serialization() {
             new_size: 145_000_000,
             repository: "core".to_string(),
             ignored: false,
+            policy: None,
+            policy_note: None,
         }],
         warnings: vec![],
     };
@@ -618,6 +621,20 @@ fn test_validate_direction_invalid() {
     assert!(validate_direction("down").is_err());
 }
 
+#[test]
+fn test_validate_run_filters() {
+    for status in ["ok", "skipped", "failed"] {
+        assert!(validate_run_status(status).is_ok());
+    }
+    assert!(validate_run_status("success").is_err());
+    assert!(validate_run_status("").is_err());
+
+    assert!(validate_date("2025-06-01").is_ok());
+    assert!(validate_date("2025-02-30").is_err());
+    assert!(validate_date("2025-6-1x").is_err());
+    assert!(validate_date("").is_err());
+}
+
 #[test]
 fn test_validate_max_packages_valid() {
     assert!(validate_max_packages(0).is_ok());
@@ -642,6 +659,21 @@ fn test_validate_max_packages_too_large() {
     assert!(validate_max_packages(usize::MAX).is_err());
 }
 
+#[test]
+fn test_validate_schedule_name() {
+    assert!(validate_schedule_name("nightly-security").is_ok());
+    assert!(validate_schedule_name("monthly_full").is_ok());
+    assert!(validate_schedule_name("2am").is_ok());
+
+    assert!(validate_schedule_name("").is_err());
+    assert!(validate_schedule_name("-leading").is_err());
+    assert!(validate_schedule_name("Nightly").is_err());
+    assert!(validate_schedule_name("a b").is_err());
+    assert!(validate_schedule_name("../etc").is_err());
+    assert!(validate_schedule_name("x@y").is_err());
+    assert!(validate_schedule_name(&"a".repeat(65)).is_err());
+}
+
 #[test]
 fn test_config_add_ignored_new_package() {
     use crate::config::AppConfig;
@@ -1242,6 +1274,8 @@ fn make_update_info(ignored: bool) -> UpdateInfo {
         new_size: 95_000,
         repository: "core".to_string(),
         ignored,
+        policy: None,
+        policy_note: None,
     }
 }
 
@@ -1542,3 +1576,35 @@ fn test_classify_error_falls_back_to_message() {
     let unknown = anyhow::anyhow!("package conflict detected");
     assert_eq!(classify_error(&unknown), None);
 }
+
+#[test]
+fn test_config_ignored_groups_add_remove() {
+    use crate::config::AppConfig;
+
+    let mut config = AppConfig::default();
+    assert!(config.add_ignored_group("plasma"));
+    assert!(config.add_ignored_group("gnome"));
+    assert!(!config.add_ignored_group("plasma"));
+    assert_eq!(
+        config.ignored_groups,
+        vec!["gnome".to_string(), "plasma".to_string()]
+    );
+
+    assert!(config.remove_ignored_group("gnome"));
+    assert!(!config.remove_ignored_group("gnome"));
+    assert_eq!(config.ignored_groups, vec!["plasma".to_string()]);
+    // Groups and packages are separate lists.
+    assert!(!config.is_ignored("plasma"));
+}
+
+#[test]
+fn test_config_ignored_groups_default_when_absent() {
+    use crate::config::{AppConfig, IgnoredPackagesResponse};
+
+    let config: AppConfig = serde_json::from_str(r#"{"ignored_packages": ["linux"]}"#).unwrap();
+    assert!(config.ignored_groups.is_empty());
+
+    let response: IgnoredPackagesResponse = (&config).into();
+    assert_eq!(response.total, 1);
+    assert!(response.groups.is_empty());
+}
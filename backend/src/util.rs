@@ -283,6 +283,27 @@ fn write_event_flushed<W: Write>(w: &mut W, event: &StreamEvent) -> io::Result<(
     }
 }
 
+/// Uid and gid of the user `name`; None when there is no such user.
+pub fn lookup_user(name: &str) -> Option<(u32, u32)> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: passwd is plain old data; all-zero is a valid value that
+    // getpwnam_r overwrites.
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    // SAFETY: every pointer is valid for the call and buf outlives it.
+    let rc = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    (rc == 0 && !result.is_null()).then_some((pwd.pw_uid, pwd.pw_gid))
+}
+
 pub fn emit_json<T: Serialize>(response: &T) -> Result<()> {
     println!("{}", serde_json::to_string(response)?);
     Ok(())
@@ -505,15 +526,36 @@ pub(crate) fn terminate_child(
 /// bounded by `recv_timeout`; on timeout the child is SIGKILLed so the worker
 /// unblocks. Used for systemctl calls, which can block on a wedged systemd.
 pub(crate) fn output_with_timeout(
+    cmd: std::process::Command,
+    timeout: Duration,
+) -> Result<std::process::Output> {
+    output_with_input_timeout(cmd, None, timeout)
+}
+
+/// [`output_with_timeout`], feeding `input` to the child's stdin from a
+/// separate thread so a child that doesn't read it can't block us.
+pub(crate) fn output_with_input_timeout(
     mut cmd: std::process::Command,
+    input: Option<Vec<u8>>,
     timeout: Duration,
 ) -> Result<std::process::Output> {
+    use std::io::Write;
     use std::process::Stdio;
     use std::sync::mpsc;
 
     cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
-    let child = cmd.spawn().context("Failed to spawn command")?;
+    if input.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    let mut child = cmd.spawn().context("Failed to spawn command")?;
     let pid = child.id();
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        std::thread::spawn(move || {
+            // A child that exits without reading gives EPIPE; its exit status
+            // is what matters.
+            let _ = stdin.write_all(&input);
+        });
+    }
     let (tx, rx) = mpsc::channel();
     std::thread::spawn(move || {
         let _ = tx.send(child.wait_with_output());
@@ -170,6 +170,43 @@ pub fn validate_max_packages(max: usize) -> Result<()> {
     Ok(())
 }
 
+pub fn validate_min_age_days(days: u32) -> Result<()> {
+    if days > 90 {
+        anyhow::bail!("min_age_days must be at most 90 (got {})", days);
+    }
+    Ok(())
+}
+
+/// Named schedules become systemd instance names, so keep them to characters
+/// that need no unit-name escaping.
+pub fn validate_schedule_name(name: &str) -> Result<()> {
+    validate_plain_name("Schedule", name)
+}
+
+pub fn validate_notifier_name(name: &str) -> Result<()> {
+    validate_plain_name("Notifier", name)
+}
+
+fn validate_plain_name(kind: &str, name: &str) -> Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("{} name cannot be empty", kind);
+    }
+    if name.len() > 64 {
+        anyhow::bail!("{} name too long (max 64)", kind);
+    }
+    if !name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"-_".contains(&b))
+    {
+        anyhow::bail!(
+            "{} name contains invalid characters (allowed: lowercase alphanumeric, hyphen, underscore; must start with a letter or digit)",
+            kind
+        );
+    }
+    Ok(())
+}
+
 pub fn validate_mirror_url(url: &str) -> Result<()> {
     if url.is_empty() {
         anyhow::bail!("Mirror URL cannot be empty");
@@ -227,6 +264,22 @@ pub fn validate_direction(direction: &str) -> Result<()> {
     }
 }
 
+pub fn validate_run_status(status: &str) -> Result<()> {
+    match status {
+        "ok" | "skipped" | "failed" => Ok(()),
+        _ => anyhow::bail!(
+            "Invalid status '{}' (expected: ok, skipped, failed)",
+            status
+        ),
+    }
+}
+
+pub fn validate_date(date: &str) -> Result<()> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| anyhow::anyhow!("Invalid date '{}' (expected YYYY-MM-DD)", date))
+}
+
 const MAX_JSON_PAYLOAD_BYTES: usize = 1024 * 1024; // 1 MiB
 
 pub fn validate_signoff_arg(value: &str, field: &str) -> Result<()> {
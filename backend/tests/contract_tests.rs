@@ -7,18 +7,22 @@
 //!
 //! Run with: cargo test --test contract_tests
 
+use cockpit_pacman_backend::config::{
+    Notifier, NotifierEntry, NotifierSink, NotifiersResponse, NotifyEvent, PolicyAction,
+};
 use cockpit_pacman_backend::models::{
     CacheInfo, CachePackage, ConflictInfo, DependencyEdge, DependencyNode, DependencyTreeResponse,
     GroupedLogResponse, KeyInfo, KeyringKey, KeyringStatusResponse, LogEntry, LogGroup,
     MirrorEntry, MirrorListResponse, MirrorStatus, MirrorStatusResponse, MirrorTestResult,
-    NewsItem, NewsResponse, OrphanPackage, OrphanResponse, Package, PackageDetails,
-    PackageListResponse, PackageSecurityAdvisory, PacnewFile, PacnewStatus, PreflightResponse,
-    PreflightWarning, ProviderChoice, RebootStatus, RefreshMirrorsResponse, ReplacementInfo,
-    RestartBlocked, RestoreMirrorBackupResponse, SaveMirrorlistResponse, ScheduledRunEntry,
+    NewsItem, NewsResponse, OptionalDependency, OrphanPackage, OrphanResponse, Package,
+    PackageDetails, PackageListResponse, PackageResult, PackageSecurityAdvisory, PacnewFile,
+    PacnewStatus, PreflightResponse, PreflightWarning, ProviderChoice, RebootStatus,
+    RefreshMirrorsResponse, ReplacementInfo, RestartBlocked, RestoreMirrorBackupResponse,
+    RunDurations, SaveMirrorlistResponse, ScheduledPackage, ScheduledRunEntry, ScheduledRunStats,
     ScheduledRunsResponse, SearchResponse, SearchResult, SecurityInfoAdvisory, SecurityInfoGroup,
     SecurityInfoIssue, SecurityInfoResponse, SecurityResponse, ServiceRestart, ServicesStatus,
-    StreamEvent, SyncPackageDetails, UpdateInfo, UpdateStats, UpdatesResponse, VersionMatch,
-    WarningSeverity,
+    SkipReason, StreamEvent, SyncPackageDetails, UpdateDiffResponse, UpdateInfo, UpdateStats,
+    UpdatesResponse, VersionMatch, WarningSeverity,
 };
 use serde_json::Value;
 
@@ -198,6 +202,8 @@ fn update_info_all_fields_present_and_typed() {
         new_size: 145_000_000,
         repository: "core".into(),
         ignored: false,
+        policy: None,
+        policy_note: None,
     };
     let v = to_json(&info);
 
@@ -213,6 +219,34 @@ fn update_info_all_fields_present_and_typed() {
     assert_eq!(v["repository"], "core");
 }
 
+#[test]
+fn update_info_policy_fields_are_optional() {
+    let info = UpdateInfo {
+        name: "linux".into(),
+        current_version: "6.7.0-arch1-1".into(),
+        new_version: "6.7.1-arch1-1".into(),
+        download_size: 0,
+        current_size: 0,
+        new_size: 0,
+        repository: "core".into(),
+        ignored: true,
+        policy: Some(PolicyAction::Hold),
+        policy_note: Some("held: regression".into()),
+    };
+    let v = to_json(&info);
+    assert_eq!(v["policy"], "hold");
+    assert_string(&v, "policy_note");
+
+    let info = UpdateInfo {
+        policy: None,
+        policy_note: None,
+        ..info
+    };
+    let v = to_json(&info);
+    assert!(v.get("policy").is_none());
+    assert!(v.get("policy_note").is_none());
+}
+
 #[test]
 fn updates_response_fixture_matches_struct_shape() {
     let fixture = parse_fixture(include_str!("../../test/fixtures/updates.json"));
@@ -468,6 +502,37 @@ fn search_results_fixture_matches_struct_shape() {
     assert_null(not_installed, "installed_version");
 }
 
+// UpdateDiffResponse
+
+#[test]
+fn update_diff_fixture_matches_struct_shape() {
+    let fixture = parse_fixture(include_str!("../../test/fixtures/update-diff.json"));
+
+    assert_array(&fixture, "diffs");
+    assert_bool(&fixture, "files_available");
+
+    // Official repo: archweb link
+    let official = &fixture["diffs"][0];
+    assert_string(official, "repository");
+    assert_array(&official["depends"], "added");
+    assert_number(&official["files"], "added_count");
+    assert_string(official, "url");
+
+    // Third-party repo: no link
+    let custom = &fixture["diffs"][1];
+    assert_string(&custom["packager"], "new");
+    assert_null(custom, "files");
+    assert_null(custom, "url");
+}
+
+#[test]
+fn update_diff_fixture_round_trip() {
+    serde_json::from_str::<UpdateDiffResponse>(include_str!(
+        "../../test/fixtures/update-diff.json"
+    ))
+    .unwrap();
+}
+
 // SyncPackageDetails
 
 #[test]
@@ -508,6 +573,30 @@ fn sync_package_details_has_download_size_not_install_date() {
     );
 }
 
+// OptionalDependency
+
+#[test]
+fn optional_dependency_unresolved_fields_are_null() {
+    let dep = OptionalDependency {
+        name: "python-pyqt6".into(),
+        depend: "python-pyqt6".into(),
+        description: Some("for the GUI".into()),
+        installed: false,
+        satisfied_by: None,
+        provider: None,
+        provider_repository: None,
+    };
+    let v = to_json(&dep);
+
+    assert_string(&v, "name");
+    assert_string(&v, "depend");
+    assert_string(&v, "description");
+    assert_bool(&v, "installed");
+    assert_null(&v, "satisfied_by");
+    assert_null(&v, "provider");
+    assert_null(&v, "provider_repository");
+}
+
 // PreflightResponse
 
 #[test]
@@ -1374,6 +1463,11 @@ fn scheduled_run_entry_error_nullable() {
         packages_upgraded: 5,
         error: None,
         details: vec!["linux upgraded".into()],
+        schedule: None,
+        packages: vec![],
+        durations: None,
+        download_bytes: None,
+        skip_reason: None,
     };
     let v = to_json(&entry);
 
@@ -1385,6 +1479,97 @@ fn scheduled_run_entry_error_nullable() {
     assert_number(&v, "packages_upgraded");
     assert_null(&v, "error");
     assert_array(&v, "details");
+    assert!(v.get("schedule").is_none());
+    assert_array(&v, "packages");
+    assert!(v.get("durations").is_none());
+    assert!(v.get("download_bytes").is_none());
+    assert!(v.get("skip_reason").is_none());
+}
+
+#[test]
+fn scheduled_run_entry_structured_fields() {
+    let entry = ScheduledRunEntry {
+        timestamp: "2024-01-01T03:00:00+0000".into(),
+        mode: "upgrade".into(),
+        success: true,
+        status: "skipped".into(),
+        packages_checked: 2,
+        packages_upgraded: 0,
+        error: None,
+        details: vec![],
+        schedule: None,
+        packages: vec![ScheduledPackage {
+            name: "linux".into(),
+            old_version: Some("6.6.9-1".into()),
+            new_version: "6.7.0.arch1-1".into(),
+            repository: "core".into(),
+            download_size: 143_000_000,
+            installed_size: 152_000_000,
+            result: PackageResult::HeldByPolicy,
+        }],
+        durations: Some(RunDurations {
+            sync_ms: Some(2150),
+            ..Default::default()
+        }),
+        download_bytes: Some(0),
+        skip_reason: Some(SkipReason::ManualIntervention),
+    };
+    let v = to_json(&entry);
+
+    let pkg = &v["packages"][0];
+    assert_string(pkg, "name");
+    assert_string(pkg, "old_version");
+    assert_string(pkg, "new_version");
+    assert_string(pkg, "repository");
+    assert_number(pkg, "download_size");
+    assert_number(pkg, "installed_size");
+    assert_eq!(pkg["result"], "held_by_policy");
+    assert_number(&v["durations"], "sync_ms");
+    assert!(v["durations"].get("commit_ms").is_none());
+    assert_number(&v, "download_bytes");
+    assert_eq!(v["skip_reason"], "manual_intervention");
+}
+
+#[test]
+fn package_result_serialization() {
+    assert_eq!(to_json(&PackageResult::Upgraded), "upgraded");
+    assert_eq!(to_json(&PackageResult::Available), "available");
+    assert_eq!(to_json(&PackageResult::HeldByPolicy), "held_by_policy");
+    assert_eq!(to_json(&PackageResult::TooNew), "too_new");
+    assert_eq!(to_json(&PackageResult::Deferred), "deferred");
+}
+
+#[test]
+fn skip_reason_serialization() {
+    assert_eq!(to_json(&SkipReason::DbLocked), "db_locked");
+    assert_eq!(to_json(&SkipReason::PreHookFailed), "pre_hook_failed");
+    assert_eq!(to_json(&SkipReason::SafetyLimit), "safety_limit");
+    assert_eq!(to_json(&SkipReason::SecurityLimit), "security_limit");
+    assert_eq!(
+        to_json(&SkipReason::ManualIntervention),
+        "manual_intervention"
+    );
+}
+
+#[test]
+fn scheduled_run_entry_names_its_schedule() {
+    let entry = ScheduledRunEntry {
+        timestamp: "2024-01-01T03:00:00+0000".into(),
+        mode: "security".into(),
+        success: true,
+        status: "ok".into(),
+        packages_checked: 10,
+        packages_upgraded: 2,
+        error: None,
+        details: vec![],
+        schedule: Some("nightly".into()),
+        packages: vec![],
+        durations: None,
+        download_bytes: None,
+        skip_reason: None,
+    };
+    let v = to_json(&entry);
+    assert_eq!(v["schedule"], "nightly");
 }
 
 // OrphanResponse
@@ -1540,10 +1725,51 @@ fn scheduled_runs_response_shape() {
     let resp = ScheduledRunsResponse {
         runs: vec![],
         total: 0,
+        stats: ScheduledRunStats::default(),
     };
     let v = to_json(&resp);
     assert_array(&v, "runs");
     assert_number(&v, "total");
+    let stats = &v["stats"];
+    assert_number(stats, "ok");
+    assert_number(stats, "skipped");
+    assert_number(stats, "failed");
+    assert_number(stats, "packages_upgraded");
+    assert_number(stats, "download_bytes");
+    assert_null(stats, "last_ok");
+    assert_null(stats, "last_failed");
+}
+
+// NotifiersResponse
+
+#[test]
+fn notifiers_response_shape() {
+    let resp = NotifiersResponse {
+        notifiers: vec![NotifierEntry {
+            name: "ops".into(),
+            notifier: Notifier {
+                sink: NotifierSink::Webhook {
+                    url: "https://hooks.example.com/pacman".into(),
+                    secret: None,
+                },
+                events: vec![NotifyEvent::RunFailed, NotifyEvent::RebootRequired],
+                enabled: true,
+            },
+            has_secret: true,
+        }],
+    };
+    let v = to_json(&resp);
+    assert_array(&v, "notifiers");
+    let entry = &v["notifiers"][0];
+    assert_string(entry, "name");
+    assert_bool(entry, "has_secret");
+    // The sink is flattened next to the shared fields, tagged by `type`.
+    let notifier = &entry["notifier"];
+    assert_eq!(notifier["type"], "webhook");
+    assert_string(notifier, "url");
+    assert!(notifier.get("secret").is_none());
+    assert_eq!(notifier["events"][1], "reboot_required");
+    assert_bool(notifier, "enabled");
 }
 
 // Each test deserializes the shared fixture directly into the Rust type.
@@ -1840,10 +2066,20 @@ fn scheduled_runs_fixture_matches_struct_shape() {
     assert_array(success_run, "details");
     assert_eq!(success_run["success"], true);
 
-    // Second run: failed, error is a string
+    assert_array(success_run, "packages");
+    assert_string(&success_run["packages"][0], "new_version");
+    assert_number(&success_run["durations"], "commit_ms");
+    assert_number(success_run, "download_bytes");
+
+    // Second run: failed, error is a string, nothing recorded per package
     let failed_run = &fixture["runs"][1];
     assert_string(failed_run, "error");
     assert_eq!(failed_run["success"], false);
+    assert_array(failed_run, "packages");
+    assert!(failed_run["packages"].as_array().unwrap().is_empty());
+
+    assert_number(&fixture["stats"], "ok");
+    assert_string(&fixture["stats"], "last_failed");
 }
 
 #[test]
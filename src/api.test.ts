@@ -18,6 +18,7 @@ import {
   syncDatabase,
   listArchiveVersions,
   downgradeFromArchive,
+  getScheduledRuns,
   StreamEvent,
   BackendError,
   isNetworkErrorCode,
@@ -792,3 +793,59 @@ describe("downgradeFromArchive", () => {
     expect(callbacks.onError).not.toHaveBeenCalled();
   });
 });
+
+describe("getScheduledRuns", () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  it("lists runs of every schedule without filters by default", async () => {
+    mockSpawn.mockReturnValue(
+      createMockSpawnPromise(JSON.stringify({ runs: [], total: 0 }))
+    );
+
+    await getScheduledRuns();
+
+    expect(mockSpawn).toHaveBeenCalledWith(
+      [
+        "/usr/libexec/cockpit-pacman/cockpit-pacman-backend",
+        "list-scheduled-runs",
+        "0",
+        "50",
+        "all",
+        "",
+        "",
+        "",
+      ],
+      expect.any(Object)
+    );
+  });
+
+  it("passes schedule, status and date filters", async () => {
+    mockSpawn.mockReturnValue(
+      createMockSpawnPromise(JSON.stringify({ runs: [], total: 0 }))
+    );
+
+    await getScheduledRuns({
+      limit: 10,
+      schedule: "nightly",
+      status: "failed",
+      since: "2024-01-01",
+      until: "2024-01-31",
+    });
+
+    expect(mockSpawn).toHaveBeenCalledWith(
+      [
+        "/usr/libexec/cockpit-pacman/cockpit-pacman-backend",
+        "list-scheduled-runs",
+        "0",
+        "10",
+        "nightly",
+        "failed",
+        "2024-01-01",
+        "2024-01-31",
+      ],
+      expect.any(Object)
+    );
+  });
+});
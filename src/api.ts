@@ -392,8 +392,8 @@ export async function listInstalled(
   ]);
 }
 
-export async function checkUpdates(): Promise<UpdatesResponse> {
-  return runBackend<UpdatesResponse>("check-updates");
+export async function checkUpdates(fresh = false): Promise<UpdatesResponse> {
+  return runBackend<UpdatesResponse>("check-updates", fresh ? ["fresh"] : []);
 }
 
 export async function checkLock(): Promise<LockStatus> {
@@ -762,6 +762,10 @@ export function downgradeFromArchive(
 export interface ScheduledRunsParams {
   offset?: number;
   limit?: number;
+  schedule?: string;
+  status?: "ok" | "skipped" | "failed";
+  since?: string;
+  until?: string;
 }
 
 export async function getScheduleConfig(): Promise<ScheduleConfig> {
@@ -786,8 +790,15 @@ export async function setScheduleConfig(params: SetScheduleParams): Promise<Sche
 }
 
 export async function getScheduledRuns(params: ScheduledRunsParams = {}): Promise<ScheduledRunsResponse> {
-  const { offset = 0, limit = 50 } = params;
-  return runBackend<ScheduledRunsResponse>("list-scheduled-runs", [String(offset), String(limit)]);
+  const { offset = 0, limit = 50, schedule = "all", status = "", since = "", until = "" } = params;
+  return runBackend<ScheduledRunsResponse>("list-scheduled-runs", [
+    String(offset),
+    String(limit),
+    schedule,
+    status,
+    since,
+    until,
+  ]);
 }
 
 export type RebootReason = "kernel_update" | "critical_packages" | "none";
@@ -1,5 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
+export type BackupFile = { path: string, hash: string, };
+
 /**
  * How a config backup came to exist. Defaults to Manual so backups predating
  * the provenance manifest still classify.
@@ -16,24 +18,128 @@ export type ConflictInfo = { package1: string, package2: string, };
 
 export type DependencyEdge = { source: string, target: string, edge_type: string, };
 
+export type DependencyGraphResponse = { root: string | null, nodes: Array<GraphPackage>, edges: Array<GraphDependency>, 
+/**
+ * Groups of packages that depend on each other in a loop, by hard
+ * depends only.
+ */
+cycles: Array<Array<string>>, unresolved: Array<UnresolvedDependency>, };
+
 export type DependencyNode = { id: string, name: string, version: string, depth: number, installed: boolean, reason: string | null, repository: string | null, };
 
 export type DependencyTreeResponse = { nodes: Array<DependencyNode>, edges: Array<DependencyEdge>, root: string, max_depth_reached: boolean, warnings: Array<string>, };
 
+/**
+ * Package set a machine should converge to. `required` packages must be
+ * installed explicitly and `forbidden` ones absent. With `strict`, explicit
+ * packages listed in neither `required` nor `orphan_keep` are demoted to
+ * dependencies, so orphan cleanup can take them.
+ */
+export type DesiredState = { required: Array<string>, forbidden: Array<string>, strict: boolean, };
+
 export type DismissalState = { signature: string | null, };
 
 export type DowngradeResponse = { packages: Array<CachedVersion>, total: number, };
 
+export type ExportedPackage = { name: string, version?: string, };
+
+export type FieldChange = { old: string | null, new: string | null, };
+
+export type FileListDiff = { 
+/**
+ * Capped lists; the counts are the full totals.
+ */
+added: Array<string>, removed: Array<string>, added_count: number, removed_count: number, };
+
+export type FootprintDependency = { name: string, installed_size: number, };
+
+export type FootprintSummary = { name: string, version: string, installed_size: number, reclaimable_size: number, exclusive_count: number, };
+
+export type ForeignPackage = { name: string, installed_version: string, 
+/**
+ * None when the package is not (or no longer) in the AUR.
+ */
+aur_version: string | null, newer_available: boolean, 
+/**
+ * Unix time the AUR package was flagged out of date.
+ */
+out_of_date: number | null, maintainer: string | null, orphaned: boolean, in_aur: boolean, };
+
+export type ForeignUpdatesResponse = { packages: Array<ForeignPackage>, 
+/**
+ * Foreign packages the stale cache has no entry for.
+ */
+unchecked: Array<string>, 
+/**
+ * True when served from the on-disk cache because the AUR was unreachable.
+ */
+stale: boolean, cached_at: number | null, };
+
+export type GraphDependency = { source: string, target: string, 
+/**
+ * depends, optdepends or makedepends
+ */
+edge_type: string, 
+/**
+ * The dependency string as declared, version constraint included.
+ */
+dependency: string, 
+/**
+ * The `provides` entry of `target` that satisfied it, when not by name.
+ */
+via_provides: string | null, };
+
+export type GraphPackage = { name: string, version: string, installed: boolean, reason: string | null, repository: string | null, };
+
+export type GroupInfoResponse = { name: string, repositories: Array<GroupRepository>, total: number, total_installed: number, ignored: boolean, };
+
+export type GroupListResponse = { groups: Array<GroupSummary>, total: number, };
+
+export type GroupMember = { name: string, version: string, description: string | null, installed: boolean, installed_version: string | null, };
+
+export type GroupRepository = { repository: string, members: Array<GroupMember>, };
+
+export type GroupSummary = { name: string, repositories: Array<string>, total: number, installed: number, };
+
 export type GroupedLogResponse = { groups: Array<LogGroup>, total_groups: number, total_upgraded: number, total_installed: number, total_removed: number, total_other: number, };
 
+/**
+ * A program run around a scheduled run. No shell is involved.
+ */
+export type HookCommand = { argv: Array<string>, timeout_secs: number, };
+
 export type IgnoreOperationResponse = { success: boolean, package: string, message: string, };
 
-export type IgnoredPackagesResponse = { packages: Array<string>, total: number, };
+export type IgnoredPackagesResponse = { packages: Array<string>, total: number, groups: Array<string>, };
+
+export type ImportPlan = { to_install: Array<ImportTarget>, already_installed: Array<string>, foreign: Array<string>, unknown: Array<string>, };
+
+export type ImportTarget = { name: string, version: string, repository: string, pinned_version: string | null, };
+
+export type InspectPackageResponse = { source: string, name: string, version: string, description: string | null, url: string | null, arch: string | null, packager: string | null, build_date: number, installed_size: number, licenses: Array<string>, groups: Array<string>, depends: Array<string>, optdepends: Array<string>, makedepends: Array<string>, provides: Array<string>, conflicts: Array<string>, replaces: Array<string>, 
+/**
+ * Capped list; `files_truncated` says whether the package has more.
+ */
+files: Array<string>, files_truncated: boolean, backup: Array<BackupFile>, has_scriptlet: boolean, scriptlet: string | null, signature: PackageSignatureCheck, installed: InstalledComparison | null, };
+
+export type InstalledComparison = { installed_version: string, 
+/**
+ * upgrade, downgrade or reinstall
+ */
+change: string, depends: ListDiff, provides: ListDiff, conflicts: ListDiff, files: FileListDiff, };
+
+export type InstalledConflict = { package1: string, package2: string, reason: string, };
 
 export type KeyringKey = { fingerprint: string, uid: string, created: string | null, expires: string | null, trust: string, };
 
 export type KeyringStatusResponse = { keys: Array<KeyringKey>, total: number, master_key_initialized: boolean, warnings: Array<string>, };
 
+export type LargestFootprintsResponse = { packages: Array<FootprintSummary>, total: number, };
+
+export type Lib32Mismatch = { name: string, version: string, native: string, native_version: string, };
+
+export type ListDiff = { added: Array<string>, removed: Array<string>, };
+
 export type ListReposResponse = { repos: Array<RepoEntry>, };
 
 export type LockRemoveResult = { removed: boolean, error?: string, };
@@ -60,6 +166,12 @@ export type MirrorStatusResponse = { mirrors: Array<MirrorStatus>, total: number
 
 export type MirrorTestResult = { url: string, success: boolean, speed_bps: number | null, latency_ms: number | null, error: string | null, };
 
+export type MissingDependency = { package: string, dependency: string, };
+
+export type NamedVersion = { name: string, version: string, };
+
+export type NewerThanRepo = { name: string, installed_version: string, repo_version: string, repository: string, };
+
 export type NewsItem = { title: string, link: string, published: string, summary: string, };
 
 export type NewsReadState = { dismissed: Array<string>, };
@@ -70,6 +182,40 @@ export type NewsResponse = { items: Array<NewsItem>,
  */
 stale?: boolean, };
 
+export type Notifier = { 
+/**
+ * Events to send; empty means all of them.
+ */
+events?: Array<NotifyEvent>, enabled: boolean, } & ({ "type": "sendmail", to: Array<string>, from?: string, } | { "type": "webhook", url: string, secret?: string, } | { "type": "command", argv: Array<string>, });
+
+export type NotifierEntry = { name: string, 
+/**
+ * The notifier with any webhook secret removed.
+ */
+notifier: Notifier, has_secret: boolean, };
+
+/**
+ * Where a notification goes.
+ */
+export type NotifierSink = { "type": "sendmail", to: Array<string>, from?: string, } | { "type": "webhook", url: string, secret?: string, } | { "type": "command", argv: Array<string>, };
+
+export type NotifiersResponse = { notifiers: Array<NotifierEntry>, };
+
+/**
+ * Occasions a notifier can be told about.
+ */
+export type NotifyEvent = "run_failed" | "run_skipped" | "updates_available" | "security_advisories" | "reboot_required" | "pacnew_files" | "test";
+
+export type OptionalDependenciesResponse = { package: string, installed: boolean, optdepends: Array<OptionalDependency>, };
+
+export type OptionalDependency = { name: string, depend: string, description: string | null, installed: boolean, satisfied_by: string | null, provider: string | null, provider_repository: string | null, };
+
+export type OptionalOnlyPackage = { name: string, version: string, description: string | null, installed_size: number, optional_for: Array<string>, repository: string | null, };
+
+export type OptionalOnlyResponse = { packages: Array<OptionalOnlyPackage>, total_size: number, };
+
+export type OrphanKeepResponse = { packages: Array<string>, total: number, };
+
 export type OrphanPackage = { name: string, version: string, description: string | null, installed_size: number, install_date: number | null, repository: string | null, };
 
 export type OrphanResponse = { orphans: Array<OrphanPackage>, total_size: number, };
@@ -78,14 +224,58 @@ export type Package = { name: string, version: string, description: string | nul
 
 export type PackageDetails = { name: string, version: string, description: string | null, url: string | null, licenses: Array<string>, groups: Array<string>, provides: Array<string>, depends: Array<string>, optdepends: Array<string>, conflicts: Array<string>, replaces: Array<string>, required_by: Array<string>, optional_for: Array<string>, installed_size: number, packager: string | null, architecture: string | null, build_date: number, install_date: number | null, reason: string, validation: Array<string>, repository: string | null, update_stats: UpdateStats | null, };
 
+export type PackageExport = { native: Array<ExportedPackage>, foreign: Array<ExportedPackage>, };
+
+export type PackageFootprint = { name: string, version: string, installed_size: number, 
+/**
+ * The package plus every dependency that would be left unneeded.
+ */
+reclaimable_size: number, exclusive_dependencies: Array<FootprintDependency>, 
+/**
+ * Installed packages that depend on something being removed.
+ */
+breaks: Array<string>, };
+
 export type PackageListResponse = { packages: Array<Package>, total: number, total_explicit: number, total_dependency: number, repositories: Array<string>, warnings: Array<string>, };
 
+/**
+ * What became of one update in a scheduled run.
+ */
+export type PackageResult = "upgraded" | "available" | "held_by_policy" | "too_new" | "deferred";
+
 export type PackageSecurityAdvisory = { package: string, severity: string, advisory_type: string, avg_name: string, cve_ids: Array<string>, fixed_version: string | null, status: string, };
 
+export type PackageSignatureCheck = { present: boolean, valid: boolean, signatures: Array<SignatureInfo>, error: string | null, };
+
 export type PacnewFile = { path: string, package: string, kind: string, };
 
 export type PacnewStatus = { has_pacnew: boolean, files: Array<PacnewFile>, };
 
+export type PoliciesResponse = { packages: Array<PolicyEntry>, groups: Array<PolicyEntry>, };
+
+export type PolicyAction = "auto" | "notify_only" | "hold";
+
+export type PolicyEntry = { name: string, policy: UpdatePolicy, description: string, 
+/**
+ * Set when a hold has run out; it is dropped on the next upgrade.
+ */
+expired: string | null, };
+
+/**
+ * What a scheduled run does once an upgrade is committed.
+ */
+export type PostUpgradePolicy = { 
+/**
+ * Restart services still running replaced binaries, except those
+ * tagged `restart_blocked`.
+ */
+restart_services: boolean, 
+/**
+ * `HH:MM-HH:MM` in local time. When set and the upgrade needs a reboot,
+ * one is scheduled through logind inside this window.
+ */
+reboot_window?: string, };
+
 export type PreflightKeyInfo = { fingerprint: string, uid: string, };
 
 export type PreflightResponse = { success: boolean, error?: string, conflicts?: Array<ConflictInfo>, replacements?: Array<ReplacementInfo>, removals?: Array<string>, providers?: Array<ProviderChoice>, import_keys?: Array<PreflightKeyInfo>, warnings?: Array<PreflightWarning>, packages_to_upgrade: number, total_download_size: number, };
@@ -114,19 +304,65 @@ export type RestoreMirrorBackupResponse = { success: boolean, backup_path: strin
 
 export type RestoreRepoBackupResponse = { success: boolean, backup_path: string | null, message: string, };
 
+/**
+ * Wall-clock time of each phase a run reached, in milliseconds.
+ */
+export type RunDurations = { sync_ms?: number, prepare_ms?: number, download_ms?: number, commit_ms?: number, 
+/**
+ * Pre- and post-run hooks together.
+ */
+hooks_ms?: number, };
+
 export type SaveMirrorlistResponse = { success: boolean, backup_path: string | null, message: string, };
 
 export type SaveReposResponse = { success: boolean, backup_path: string | null, message: string, };
 
-export type ScheduleConfig = { enabled: boolean, mode: string, schedule: string, max_packages: number, timer_active: boolean, timer_next_run: string | null, };
+export type ScheduleConfig = { 
+/**
+ * Set for named schedules; absent for the default one.
+ */
+name?: string, enabled: boolean, mode: string, schedule: string, max_packages: number, min_age_days: number, hooks?: ScheduleHooks, post_upgrade?: PostUpgradePolicy, timer_active: boolean, timer_next_run: string | null, };
 
-export type ScheduleMode = "check" | "upgrade";
+/**
+ * Commands run in order before and after a schedule's runs. A failing `pre`
+ * hook skips the run; `post` hooks see its outcome.
+ */
+export type ScheduleHooks = { pre: Array<HookCommand>, post: Array<HookCommand>, };
+
+export type ScheduleMode = "check" | "upgrade" | "security" | "cache-clean" | "orphan-report" | "mirror-refresh" | "keyring-refresh";
 
 export type ScheduleSetResponse = { success: boolean, message: string, };
 
-export type ScheduledRunEntry = { timestamp: string, mode: string, success: boolean, status: string, packages_checked: number, packages_upgraded: number, error: string | null, details: Array<string>, };
+export type ScheduledPackage = { name: string, 
+/**
+ * None for a package the upgrade newly installed.
+ */
+old_version: string | null, new_version: string, repository: string, download_size: number, installed_size: number, result: PackageResult, };
+
+export type ScheduledRunEntry = { timestamp: string, mode: string, success: boolean, status: string, packages_checked: number, packages_upgraded: number, error: string | null, details: Array<string>, 
+/**
+ * Name of the schedule that ran; absent for the default schedule.
+ */
+schedule?: string, 
+/**
+ * Updates the run found and what became of each; empty for runs logged
+ * before these were recorded.
+ */
+packages: Array<ScheduledPackage>, durations?: RunDurations, 
+/**
+ * Bytes the run had to download; cached packages count for nothing.
+ */
+download_bytes?: number, skip_reason?: SkipReason, };
+
+/**
+ * Totals over the runs matching a `list-scheduled-runs` filter, before
+ * pagination.
+ */
+export type ScheduledRunStats = { ok: number, skipped: number, failed: number, packages_upgraded: number, download_bytes: number, last_ok: string | null, last_failed: string | null, };
+
+export type ScheduledRunsResponse = { runs: Array<ScheduledRunEntry>, total: number, stats: ScheduledRunStats, };
 
-export type ScheduledRunsResponse = { runs: Array<ScheduledRunEntry>, total: number, };
+export type SchedulesResponse = { schedules: Array<ScheduleConfig>, };
 
 export type SearchResponse = { results: Array<SearchResult>, total: number, total_installed: number, total_not_installed: number, repositories: Array<string>, };
 
@@ -150,6 +386,12 @@ export type ServiceRestart = { name: string, pid: number, affected_packages: Arr
 
 export type ServicesStatus = { restart_required: boolean, services: Array<ServiceRestart>, };
 
+export type SignatureInfo = { status: string, validity: string, fingerprint: string, 
+/**
+ * Signer uid from the pacman keyring; None when the key is not in it.
+ */
+uid: string | null, };
+
 /**
  * Wire mirror of archweb_client's Signoff so the type can derive TS bindings
  * without depending on the external crate's type. Mapped from the client type
@@ -163,11 +405,70 @@ export type SignoffGroupWithLocal = { pkgbase: string, pkgnames: Array<string>,
 
 export type SignoffListResponse = { signoff_groups: Array<SignoffGroupWithLocal>, total: number, };
 
-export type StreamEvent = { "type": "log", level: string, message: string, } | { "type": "progress", operation: string, package: string, percent: number, current: number, total: number, } | { "type": "download", filename: string, event: string, downloaded?: number, total?: number, } | { "type": "event", event: string, package?: string, } | { "type": "complete", success: boolean, message?: string, } | { "type": "mirror_test", url: string, current: number, total: number, result: MirrorTestResult, };
+/**
+ * Why a scheduled run was skipped.
+ */
+export type SkipReason = "db_locked" | "pre_hook_failed" | "safety_limit" | "security_limit" | "manual_intervention";
+
+export type SplitVersionMismatch = { pkgbase: string, packages: Array<NamedVersion>, };
+
+export type StatePlan = { install: Array<string>, remove: Array<string>, mark_explicit: Array<string>, mark_dependency: Array<string>, 
+/**
+ * Required packages that are neither installed nor in any sync repo.
+ */
+unresolved: Array<string>, };
+
+export type StatePlanResponse = { source: string, configured: boolean, in_sync: boolean, plan: StatePlan, };
+
+export type StreamEvent = { "type": "log", level: string, message: string, } | { "type": "progress", operation: string, package: string, percent: number, current: number, total: number, } | { "type": "download", filename: string, event: string, downloaded?: number, total?: number, } | { "type": "event", event: string, package?: string, } | { "type": "complete", success: boolean, message?: string, } | { "type": "repo_sync", repo: string, success: boolean, updated: boolean, server: string | null, message: string | null, } | { "type": "mirror_test", url: string, current: number, total: number, result: MirrorTestResult, };
+
+export type SyncDbStatus = { name: string, path: string, exists: boolean, file_mtime: number | null, 
+/**
+ * Newest package build date in the db.
+ */
+last_update: number | null, size: number, package_count: number, siglevel: Array<string>, signature_present: boolean, 
+/**
+ * Whether libalpm accepts the db and its signature; null when there is
+ * no signature to check.
+ */
+signature_valid: boolean | null, validation_error: string | null, age_days: number | null, stale: boolean, };
+
+export type SyncDbStatusResponse = { databases: Array<SyncDbStatus>, stale_threshold_days: number, };
 
 export type SyncPackageDetails = { name: string, version: string, description: string | null, url: string | null, licenses: Array<string>, groups: Array<string>, provides: Array<string>, depends: Array<string>, optdepends: Array<string>, conflicts: Array<string>, replaces: Array<string>, download_size: number, installed_size: number, packager: string | null, architecture: string | null, build_date: number, repository: string, };
 
-export type UpdateInfo = { name: string, current_version: string, new_version: string, download_size: number, current_size: number, new_size: number, repository: string, ignored: boolean, };
+export type SystemConsistencyResponse = { newer_than_repo: Array<NewerThanRepo>, not_in_repos: Array<NamedVersion>, split_mismatches: Array<SplitVersionMismatch>, lib32_mismatches: Array<Lib32Mismatch>, missing_dependencies: Array<MissingDependency>, conflicts: Array<InstalledConflict>, issue_count: number, };
+
+export type UnresolvedDependency = { package: string, dependency: string, edge_type: string, };
+
+export type UpdateDiff = { name: string, current_version: string, new_version: string, repository: string, depends: ListDiff, optdepends: ListDiff, provides: ListDiff, conflicts: ListDiff, replaces: ListDiff, licenses: ListDiff, packager: FieldChange | null, 
+/**
+ * None when no files database is synced for the repository.
+ */
+files: FileListDiff | null, 
+/**
+ * The installed package's changelog, when it ships one.
+ */
+changelog: string | null, 
+/**
+ * The new version's archweb page; None outside the official repos.
+ */
+url: string | null, };
+
+export type UpdateDiffResponse = { diffs: Array<UpdateDiff>, files_available: boolean, };
+
+export type UpdateInfo = { name: string, current_version: string, new_version: string, download_size: number, current_size: number, new_size: number, repository: string, ignored: boolean, 
+/**
+ * The update policy in effect, if any. Held updates are also `ignored`.
+ */
+policy?: PolicyAction, policy_note?: string, };
+
+/**
+ * How updates to a package or group are handled. A hold ends once
+ * `until_version` (or newer) is available or `until_date` (YYYY-MM-DD) has
+ * arrived, whichever comes first; with neither it lasts until removed.
+ */
+export type UpdatePolicy = { action: PolicyAction, until_version?: string, until_date?: string, reason?: string, };
 
 export type UpdateStats = { update_count: number, first_installed: string | null, last_updated: string | null, avg_days_between_updates: number | null, };
 
@@ -176,3 +477,31 @@ export type UpdatesResponse = { updates: Array<UpdateInfo>, warnings: Array<stri
 export type VersionMatch = "match" | "mismatch" | "not_installed";
 
 export type WarningSeverity = "info" | "warning" | "danger";
+
+export type WhyInstalledChain = { 
+/**
+ * The explicitly installed package the chain starts from.
+ */
+root: string, 
+/**
+ * True when no chain of hard depends exists and an optdepends edge is
+ * needed to reach the package.
+ */
+optional: boolean, links: Array<WhyInstalledLink>, };
+
+export type WhyInstalledLink = { package: string, dependency: string, 
+/**
+ * depends or optdepends
+ */
+kind: string, satisfied_by: string, 
+/**
+ * The `provides` entry of `satisfied_by` that matched, when it was not
+ * pulled in by name.
+ */
+via_provides: string | null, };
+
+export type WhyInstalledResponse = { name: string, version: string, explicit: boolean, chains: Array<WhyInstalledChain>, 
+/**
+ * Installed as a dependency but nothing explicit leads to it any more.
+ */
+orphan: boolean, };
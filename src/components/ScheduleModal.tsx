@@ -63,6 +63,7 @@ const SCHEDULE_PRESETS = [
 const MODE_OPTIONS: { value: ScheduleMode; label: string }[] = [
   { value: "upgrade", label: "Auto-upgrade" },
   { value: "check", label: "Check only" },
+  { value: "security", label: "Security fixes only" },
 ];
 
 export const ScheduleModal: React.FC<ScheduleModalProps> = ({
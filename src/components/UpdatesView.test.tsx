@@ -82,7 +82,7 @@ describe("UpdatesView", () => {
     mockCheckUpdates.mockResolvedValue(mockUpdatesResponse);
     mockPreflightUpgrade.mockResolvedValue(mockPreflightResponse);
     mockGetSyncPackageInfo.mockResolvedValue(mockSyncPackageDetails);
-    mockListIgnoredPackages.mockResolvedValue({ packages: [], total: 0 });
+    mockListIgnoredPackages.mockResolvedValue({ packages: [], total: 0, groups: [] });
     mockGetRebootStatus.mockResolvedValue({
       requires_reboot: false,
       reason: "none",
@@ -117,7 +117,19 @@ describe("UpdatesView", () => {
     mockMarkServicesDismissed.mockResolvedValue(undefined);
     mockGetRebootDismissal.mockResolvedValue({ signature: null });
     mockMarkRebootDismissed.mockResolvedValue(undefined);
-    mockGetScheduledRuns.mockResolvedValue({ runs: [], total: 0 });
+    mockGetScheduledRuns.mockResolvedValue({
+      runs: [],
+      total: 0,
+      stats: {
+        ok: 0,
+        skipped: 0,
+        failed: 0,
+        packages_upgraded: 0,
+        download_bytes: 0,
+        last_ok: null,
+        last_failed: null,
+      },
+    });
     mockGetScheduledDismissal.mockResolvedValue({ signature: null });
     mockMarkScheduledDismissed.mockResolvedValue(undefined);
     mockRunUpgrade.mockReturnValue({ cancel: vi.fn(), forceStop: vi.fn() });
@@ -2014,7 +2026,7 @@ describe("UpdatesView", () => {
         ],
         warnings: [],
       });
-      mockListIgnoredPackages.mockResolvedValue({ packages: ["linux"], total: 1 });
+      mockListIgnoredPackages.mockResolvedValue({ packages: ["linux"], total: 1, groups: [] });
     });
 
     const getRowCheckbox = (pkgName: string) => {
@@ -990,6 +990,31 @@ describe("getScheduledRuns contract", () => {
     const result = await getScheduledRuns();
     expect(result.total).toBe(result.runs.length);
   });
+
+  it("successful run has structured package records and durations", async () => {
+    spawnReturns(scheduledRunsFixture);
+    const result = await getScheduledRuns();
+
+    const successRun: ScheduledRunEntry = result.runs[0];
+    const pkg = successRun.packages[0];
+    expect(typeof pkg.name).toBe("string");
+    expect(typeof pkg.old_version).toBe("string");
+    expect(typeof pkg.new_version).toBe("string");
+    expect(typeof pkg.repository).toBe("string");
+    expect(typeof pkg.download_size).toBe("number");
+    expect(pkg.result).toBe("upgraded");
+    expect(typeof successRun.durations?.commit_ms).toBe("number");
+    expect(typeof successRun.download_bytes).toBe("number");
+  });
+
+  it("stats total the fixture runs", async () => {
+    spawnReturns(scheduledRunsFixture);
+    const result = await getScheduledRuns();
+
+    expect(result.stats.ok + result.stats.skipped + result.stats.failed).toBe(result.total);
+    expect(typeof result.stats.download_bytes).toBe("number");
+    expect(typeof result.stats.last_ok).toBe("string");
+  });
 });
 
 describe("getSyncPackageInfo contract", () => {